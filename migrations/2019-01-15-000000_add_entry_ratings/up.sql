@@ -0,0 +1,51 @@
+CREATE TABLE entry_ratings (
+    entry_id           TEXT PRIMARY KEY NOT NULL,
+    total              FLOAT NOT NULL,
+    diversity          FLOAT NOT NULL,
+    diversity_count    INTEGER NOT NULL,
+    renewable          FLOAT NOT NULL,
+    renewable_count    INTEGER NOT NULL,
+    fairness           FLOAT NOT NULL,
+    fairness_count     INTEGER NOT NULL,
+    humanity           FLOAT NOT NULL,
+    humanity_count     INTEGER NOT NULL,
+    transparency       FLOAT NOT NULL,
+    transparency_count INTEGER NOT NULL,
+    solidarity         FLOAT NOT NULL,
+    solidarity_count   INTEGER NOT NULL
+);
+
+INSERT INTO entry_ratings
+SELECT
+    entry_id,
+    (diversity + renewable + fairness + humanity + transparency + solidarity) / 6.0,
+    diversity,
+    diversity_count,
+    renewable,
+    renewable_count,
+    fairness,
+    fairness_count,
+    humanity,
+    humanity_count,
+    transparency,
+    transparency_count,
+    solidarity,
+    solidarity_count
+FROM (
+    SELECT
+        entry_id,
+        IFNULL(AVG(CASE WHEN context = 'diversity'    THEN value END), 0.0) AS diversity,
+        COUNT(CASE WHEN context = 'diversity'    THEN 1 END)                AS diversity_count,
+        IFNULL(AVG(CASE WHEN context = 'renewable'    THEN value END), 0.0) AS renewable,
+        COUNT(CASE WHEN context = 'renewable'    THEN 1 END)                AS renewable_count,
+        IFNULL(AVG(CASE WHEN context = 'fairness'     THEN value END), 0.0) AS fairness,
+        COUNT(CASE WHEN context = 'fairness'     THEN 1 END)                AS fairness_count,
+        IFNULL(AVG(CASE WHEN context = 'humanity'     THEN value END), 0.0) AS humanity,
+        COUNT(CASE WHEN context = 'humanity'     THEN 1 END)                AS humanity_count,
+        IFNULL(AVG(CASE WHEN context = 'transparency' THEN value END), 0.0) AS transparency,
+        COUNT(CASE WHEN context = 'transparency' THEN 1 END)                AS transparency_count,
+        IFNULL(AVG(CASE WHEN context = 'solidarity'   THEN value END), 0.0) AS solidarity,
+        COUNT(CASE WHEN context = 'solidarity'   THEN 1 END)                AS solidarity_count
+    FROM ratings
+    GROUP BY entry_id
+);
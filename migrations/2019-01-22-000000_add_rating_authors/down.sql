@@ -0,0 +1,3 @@
+DROP INDEX ratings_created_by_entry_id_context;
+
+-- The column `created_by` of `ratings` can't be dropped in SQLite
@@ -0,0 +1,5 @@
+ALTER TABLE ratings ADD COLUMN created_by TEXT REFERENCES users(username);
+
+-- Only a single rating per user, entry and context
+-- (anonymous ratings without an author are not restricted)
+CREATE UNIQUE INDEX ratings_created_by_entry_id_context ON ratings (created_by, entry_id, context);
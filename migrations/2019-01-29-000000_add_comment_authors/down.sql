@@ -0,0 +1 @@
+-- The column `created_by` of `comments` can't be dropped in SQLite
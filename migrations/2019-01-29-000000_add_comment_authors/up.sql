@@ -0,0 +1 @@
+ALTER TABLE comments ADD COLUMN created_by TEXT REFERENCES users(username);
@@ -0,0 +1,3 @@
+DROP INDEX comments_fk_parent_id;
+
+-- The column `parent_id` of `comments` can't be dropped in SQLite
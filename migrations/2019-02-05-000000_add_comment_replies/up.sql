@@ -0,0 +1,3 @@
+ALTER TABLE comments ADD COLUMN parent_id TEXT REFERENCES comments(id);
+
+CREATE INDEX comments_fk_parent_id ON comments (parent_id);
@@ -0,0 +1,3 @@
+DROP TABLE reports;
+
+-- The column `archived` of `entries` can't be dropped in SQLite
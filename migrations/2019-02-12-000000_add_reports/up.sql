@@ -0,0 +1,14 @@
+ALTER TABLE entries ADD COLUMN archived INTEGER;
+
+CREATE TABLE reports (
+    id          TEXT PRIMARY KEY NOT NULL,
+    created     INTEGER NOT NULL,
+    target      TEXT NOT NULL,
+    target_id   TEXT NOT NULL,
+    reason      TEXT NOT NULL,
+    reported_by TEXT,
+    resolution  TEXT,
+    resolved_by TEXT,
+    FOREIGN KEY (reported_by) REFERENCES users(username),
+    FOREIGN KEY (resolved_by) REFERENCES users(username)
+);
@@ -0,0 +1 @@
+-- The column `pending` of `entries` can't be dropped in SQLite
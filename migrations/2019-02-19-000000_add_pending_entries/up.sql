@@ -0,0 +1 @@
+ALTER TABLE entries ADD COLUMN pending BOOLEAN DEFAULT 0 NOT NULL;
@@ -0,0 +1,7 @@
+CREATE TABLE login_histories (
+    username            TEXT PRIMARY KEY NOT NULL,
+    failed_attempts     INTEGER NOT NULL,
+    last_failed_attempt INTEGER,
+    last_login          INTEGER,
+    FOREIGN KEY (username) REFERENCES users(username)
+);
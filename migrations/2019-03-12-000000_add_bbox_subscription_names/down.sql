@@ -0,0 +1 @@
+-- The column `name` of `bbox_subscriptions` can't be dropped in SQLite
@@ -0,0 +1 @@
+ALTER TABLE bbox_subscriptions ADD COLUMN name TEXT;
@@ -0,0 +1 @@
+-- The columns `categories`, `tags` and `changes` of `bbox_subscriptions` can't be dropped in SQLite
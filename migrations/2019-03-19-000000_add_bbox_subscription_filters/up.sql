@@ -0,0 +1,4 @@
+-- Comma separated lists, empty if not filtered
+ALTER TABLE bbox_subscriptions ADD COLUMN categories TEXT NOT NULL DEFAULT '';
+ALTER TABLE bbox_subscriptions ADD COLUMN tags TEXT NOT NULL DEFAULT '';
+ALTER TABLE bbox_subscriptions ADD COLUMN changes TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+DROP TABLE pending_notifications;
+-- The column `delivery` of `bbox_subscriptions` can't be dropped in SQLite
@@ -0,0 +1,13 @@
+ALTER TABLE bbox_subscriptions ADD COLUMN delivery TEXT NOT NULL DEFAULT 'instant';
+
+CREATE TABLE pending_notifications (
+    id       TEXT PRIMARY KEY NOT NULL,
+    username TEXT NOT NULL,
+    delivery TEXT NOT NULL,
+    created  INTEGER NOT NULL,
+    subject  TEXT NOT NULL,
+    body     TEXT NOT NULL,
+    FOREIGN KEY (username) REFERENCES users(username)
+);
+
+CREATE INDEX pending_notifications_username ON pending_notifications (username);
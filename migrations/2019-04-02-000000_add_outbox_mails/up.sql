@@ -0,0 +1,13 @@
+CREATE TABLE outbox_mails (
+    id           TEXT PRIMARY KEY NOT NULL,
+    created      INTEGER NOT NULL,
+    recipients   TEXT NOT NULL,
+    subject      TEXT NOT NULL,
+    content      TEXT NOT NULL,
+    status       TEXT NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    next_attempt INTEGER NOT NULL,
+    last_error   TEXT
+);
+
+CREATE INDEX outbox_mails_status_next_attempt ON outbox_mails (status, next_attempt);
@@ -0,0 +1 @@
+-- The column `language` of `users` can't be dropped in SQLite
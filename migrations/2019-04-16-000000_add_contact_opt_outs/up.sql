@@ -0,0 +1,4 @@
+CREATE TABLE contact_opt_outs (
+    email   TEXT PRIMARY KEY NOT NULL,
+    created INTEGER NOT NULL
+);
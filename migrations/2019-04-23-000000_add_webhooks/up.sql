@@ -0,0 +1,24 @@
+CREATE TABLE webhooks (
+    id      TEXT PRIMARY KEY NOT NULL,
+    org_id  TEXT NOT NULL,
+    created INTEGER NOT NULL,
+    url     TEXT NOT NULL,
+    secret  TEXT NOT NULL,
+    events  TEXT NOT NULL,
+    FOREIGN KEY (org_id) REFERENCES organizations(id)
+);
+
+CREATE TABLE webhook_deliveries (
+    id           TEXT PRIMARY KEY NOT NULL,
+    webhook_id   TEXT NOT NULL,
+    created      INTEGER NOT NULL,
+    event        TEXT NOT NULL,
+    payload      TEXT NOT NULL,
+    status       TEXT NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    next_attempt INTEGER NOT NULL,
+    last_error   TEXT,
+    FOREIGN KEY (webhook_id) REFERENCES webhooks(id)
+);
+
+CREATE INDEX webhook_deliveries_status_next_attempt ON webhook_deliveries (status, next_attempt);
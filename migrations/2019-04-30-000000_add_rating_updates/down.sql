@@ -0,0 +1 @@
+-- The column `updated` of `ratings` can't be dropped in SQLite
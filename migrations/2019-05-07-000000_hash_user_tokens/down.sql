@@ -0,0 +1,10 @@
+DROP TABLE user_tokens;
+CREATE TABLE user_tokens (
+    id       TEXT PRIMARY KEY NOT NULL,
+    token    TEXT UNIQUE NOT NULL,
+    username TEXT NOT NULL,
+    created  INTEGER NOT NULL,
+    FOREIGN KEY (username) REFERENCES users(username)
+);
+
+CREATE INDEX user_tokens_username ON user_tokens (username);
@@ -0,0 +1,13 @@
+-- The plaintext tokens can't be hashed here,
+-- so all issued tokens have to be renewed.
+DROP TABLE user_tokens;
+CREATE TABLE user_tokens (
+    id         TEXT PRIMARY KEY NOT NULL,
+    token_hash TEXT UNIQUE NOT NULL,
+    username   TEXT NOT NULL,
+    created    INTEGER NOT NULL,
+    expires    INTEGER NOT NULL,
+    FOREIGN KEY (username) REFERENCES users(username)
+);
+
+CREATE INDEX user_tokens_username ON user_tokens (username);
@@ -0,0 +1,8 @@
+UPDATE bbox_subscriptions SET categories = CASE categories
+    WHEN '[]' THEN ''
+    ELSE replace(replace(replace(substr(categories, 3, length(categories) - 4), '","', ','), '\"', '"'), '\\', '\')
+END;
+UPDATE bbox_subscriptions SET tags = CASE tags
+    WHEN '[]' THEN ''
+    ELSE replace(replace(replace(substr(tags, 3, length(tags) - 4), '","', ','), '\"', '"'), '\\', '\')
+END;
@@ -0,0 +1,10 @@
+-- JSON arrays instead of comma separated lists
+-- so that the values may contain commas
+UPDATE bbox_subscriptions SET categories = CASE categories
+    WHEN '' THEN '[]'
+    ELSE '["' || replace(replace(replace(categories, '\', '\\'), '"', '\"'), ',', '","') || '"]'
+END;
+UPDATE bbox_subscriptions SET tags = CASE tags
+    WHEN '' THEN '[]'
+    ELSE '["' || replace(replace(replace(tags, '\', '\\'), '"', '\"'), ',', '","') || '"]'
+END;
@@ -0,0 +1,18 @@
+CREATE TABLE pending_notification_mails (
+    id       TEXT PRIMARY KEY NOT NULL,
+    username TEXT NOT NULL,
+    delivery TEXT NOT NULL,
+    created  INTEGER NOT NULL,
+    subject  TEXT NOT NULL,
+    body     TEXT NOT NULL,
+    FOREIGN KEY (username) REFERENCES users(username)
+);
+
+INSERT INTO pending_notification_mails
+    SELECT id, username, delivery, created, title, ''
+    FROM pending_notifications;
+
+DROP TABLE pending_notifications;
+ALTER TABLE pending_notification_mails RENAME TO pending_notifications;
+
+CREATE INDEX pending_notifications_username ON pending_notifications (username);
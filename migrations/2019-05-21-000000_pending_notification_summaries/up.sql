@@ -0,0 +1,22 @@
+-- Only a summary of each change is stored and
+-- the digest is rendered when it is sent.
+CREATE TABLE pending_notification_summaries (
+    id        TEXT PRIMARY KEY NOT NULL,
+    username  TEXT NOT NULL,
+    delivery  TEXT NOT NULL,
+    created   INTEGER NOT NULL,
+    kind      TEXT NOT NULL,
+    title     TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    FOREIGN KEY (username) REFERENCES users(username)
+);
+
+-- The queued mails are kept with their subject as title
+INSERT INTO pending_notification_summaries
+    SELECT id, username, delivery, created, 'new_entry', subject, ''
+    FROM pending_notifications;
+
+DROP TABLE pending_notifications;
+ALTER TABLE pending_notification_summaries RENAME TO pending_notifications;
+
+CREATE INDEX pending_notifications_username ON pending_notifications (username);
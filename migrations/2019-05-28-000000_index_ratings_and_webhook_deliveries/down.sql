@@ -0,0 +1,3 @@
+DROP INDEX ratings_entry_id;
+
+DROP INDEX webhook_deliveries_fk_webhook_id;
@@ -0,0 +1,5 @@
+-- Look up the ratings of an entry and the payloads of a webhook
+
+CREATE INDEX ratings_entry_id ON ratings (entry_id);
+
+CREATE INDEX webhook_deliveries_fk_webhook_id ON webhook_deliveries (webhook_id);
@@ -20,6 +20,7 @@ pub struct Entry {
     pub categories     : Vec<String>,
     pub tags           : Vec<String>,
     pub ratings        : Vec<String>,
+    pub rating_summary : RatingSummary,
     pub license        : Option<String>,
     pub image_url      : Option<String>,
     pub image_link_url : Option<String>,
@@ -120,17 +121,100 @@ pub struct Rating {
     pub id: String,
     pub title: String,
     pub created: u64,
+    pub updated: Option<u64>,
     pub value: i8,
     pub context: e::RatingContext,
     pub comments: Vec<Comment>,
     pub source: String,
 }
 
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AvgRating {
+    pub average: f64,
+    pub count: u64,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RatingSummary {
+    pub total        : f64,
+    pub diversity    : AvgRating,
+    pub renewable    : AvgRating,
+    pub fairness     : AvgRating,
+    pub humanity     : AvgRating,
+    pub transparency : AvgRating,
+    pub solidarity   : AvgRating,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Comment {
     pub id: String,
     pub created: u64,
     pub text: String,
+    pub replies: Vec<Comment>,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize, Deserialize)]
+pub struct Report {
+    pub id          : String,
+    pub created     : u64,
+    pub target      : e::ReportTarget,
+    pub target_id   : String,
+    pub reason      : String,
+    pub reported_by : Option<String>,
+}
+
+/// A mail of the outbox without its content
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize, Deserialize)]
+pub struct OutboxMail {
+    pub id           : String,
+    pub created      : u64,
+    pub recipients   : Vec<String>,
+    pub subject      : String,
+    pub status       : e::MailStatus,
+    pub attempts     : u32,
+    pub next_attempt : u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error   : Option<String>,
+}
+
+/// A webhook of an organization
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize, Deserialize)]
+pub struct Webhook {
+    pub id      : String,
+    pub created : u64,
+    pub url     : String,
+    pub events  : Vec<e::WebhookEvent>,
+    /// The secret is only revealed once after the webhook has been created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret  : Option<String>,
+}
+
+/// The body of the requests to webhooks
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize, Deserialize)]
+pub struct WebhookPayload<T> {
+    pub event   : e::WebhookEvent,
+    /// The ID of the changed object
+    pub id      : String,
+    pub created : u64,
+    /// The changed object (missing if it has been archived)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data    : Option<T>,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize, Deserialize)]
+pub struct UserToken {
+    pub id      : String,
+    pub created : u64,
+    pub expires : u64,
+    /// The token is only revealed once after it has been issued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token   : Option<String>,
 }
 
 #[derive(Serialize)]
@@ -154,27 +238,78 @@ impl From<e::Entry> for EntryIdWithCoordinates {
 pub struct SearchResponse {
     pub visible: Vec<EntryIdWithCoordinates>,
     pub invisible: Vec<EntryIdWithCoordinates>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<Event>>,
 }
 
 #[derive(Serialize)]
 pub struct User {
     pub username: String,
     pub email: String,
+    pub language: Option<String>,
+    pub last_login: Option<u64>,
+    pub failed_login_attempts: u32,
+    pub locked_until: Option<u64>,
 }
 
 #[derive(Serialize)]
 pub struct BboxSubscription {
     pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub south_west_lat: f64,
     pub south_west_lng: f64,
     pub north_east_lat: f64,
     pub north_east_lng: f64,
+    pub categories: Vec<String>,
+    pub tags: Vec<String>,
+    pub changes: Vec<e::ChangeKind>,
+    pub delivery: e::Delivery,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize)]
+pub struct ExportedRating {
+    pub id       : String,
+    pub entry_id : String,
+    pub created  : u64,
+    pub updated  : Option<u64>,
+    pub title    : String,
+    pub value    : i8,
+    pub context  : e::RatingContext,
+    pub source   : Option<String>,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize)]
+pub struct ExportedComment {
+    pub id        : String,
+    pub rating_id : String,
+    pub parent_id : Option<String>,
+    pub created   : u64,
+    pub text      : String,
+}
+
+/// All personal data of a user.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Serialize)]
+pub struct UserExport {
+    pub user               : User,
+    pub bbox_subscriptions : Vec<BboxSubscription>,
+    pub tokens             : Vec<UserToken>,
+    pub events             : Vec<Event>,
+    pub ratings            : Vec<ExportedRating>,
+    pub comments           : Vec<ExportedComment>,
 }
 
 // Entity -> JSON
 
 impl Entry {
-    pub fn from_entry_with_ratings(e: e::Entry, ratings: Vec<e::Rating>) -> Entry {
+    pub fn from_entry_with_ratings(
+        e: e::Entry,
+        ratings: Vec<e::Rating>,
+        avg_ratings: e::AvgRatings,
+    ) -> Entry {
         let e::Entry {
             id,
             created,
@@ -218,9 +353,200 @@ impl Entry {
             categories,
             tags,
             ratings: ratings.into_iter().map(|r| r.id).collect(),
+            rating_summary: avg_ratings.into(),
             license,
             image_url,
             image_link_url,
         }
     }
 }
+
+impl From<e::AvgRating> for AvgRating {
+    fn from(r: e::AvgRating) -> Self {
+        AvgRating {
+            average: r.value,
+            count: r.count,
+        }
+    }
+}
+
+impl From<e::AvgRatings> for RatingSummary {
+    fn from(r: e::AvgRatings) -> Self {
+        RatingSummary {
+            total: r.total,
+            diversity: r.diversity.into(),
+            renewable: r.renewable.into(),
+            fairness: r.fairness.into(),
+            humanity: r.humanity.into(),
+            transparency: r.transparency.into(),
+            solidarity: r.solidarity.into(),
+        }
+    }
+}
+
+impl Comment {
+    /// Arranges the comments of a rating as threads of replies.
+    ///
+    /// Comments that don't reply to another comment of
+    /// the list are returned as top level comments.
+    pub fn threads(mut comments: Vec<e::Comment>) -> Vec<Comment> {
+        comments.sort_by_key(|c| c.created);
+        let (replies, top_level): (Vec<_>, Vec<_>) = comments.iter().cloned().partition(|c| {
+            c.parent_id
+                .as_ref()
+                .map(|p_id| comments.iter().any(|c| c.id == *p_id))
+                .unwrap_or(false)
+        });
+        top_level
+            .into_iter()
+            .map(|c| Comment::with_replies(c, &replies))
+            .collect()
+    }
+
+    fn with_replies(c: e::Comment, replies: &[e::Comment]) -> Comment {
+        Comment {
+            replies: replies
+                .iter()
+                .filter(|r| r.parent_id.as_ref() == Some(&c.id))
+                .cloned()
+                .map(|r| Comment::with_replies(r, replies))
+                .collect(),
+            id: c.id,
+            created: c.created,
+            text: c.text,
+        }
+    }
+}
+
+impl From<e::BboxSubscription> for BboxSubscription {
+    fn from(s: e::BboxSubscription) -> Self {
+        BboxSubscription {
+            id: s.id,
+            name: s.name,
+            south_west_lat: s.bbox.south_west.lat,
+            south_west_lng: s.bbox.south_west.lng,
+            north_east_lat: s.bbox.north_east.lat,
+            north_east_lng: s.bbox.north_east.lng,
+            categories: s.categories,
+            tags: s.tags,
+            changes: s.changes,
+            delivery: s.delivery,
+        }
+    }
+}
+
+impl From<e::Rating> for ExportedRating {
+    fn from(r: e::Rating) -> Self {
+        let e::Rating {
+            id,
+            entry_id,
+            created,
+            updated,
+            title,
+            value,
+            context,
+            source,
+            ..
+        } = r;
+        ExportedRating {
+            id,
+            entry_id,
+            created,
+            updated,
+            title,
+            value,
+            context,
+            source,
+        }
+    }
+}
+
+impl From<e::Comment> for ExportedComment {
+    fn from(c: e::Comment) -> Self {
+        ExportedComment {
+            id: c.id,
+            rating_id: c.rating_id,
+            parent_id: c.parent_id,
+            created: c.created,
+            text: c.text,
+        }
+    }
+}
+
+impl From<e::UserToken> for UserToken {
+    fn from(t: e::UserToken) -> Self {
+        UserToken {
+            id: t.id,
+            created: t.created,
+            expires: t.expires,
+            token: None,
+        }
+    }
+}
+
+impl From<e::OutboxMail> for OutboxMail {
+    fn from(m: e::OutboxMail) -> Self {
+        let e::OutboxMail {
+            id,
+            created,
+            recipients,
+            subject,
+            status,
+            attempts,
+            next_attempt,
+            last_error,
+            ..
+        } = m;
+        OutboxMail {
+            id,
+            created,
+            recipients,
+            subject,
+            status,
+            attempts,
+            next_attempt,
+            last_error,
+        }
+    }
+}
+
+impl From<e::Webhook> for Webhook {
+    fn from(w: e::Webhook) -> Self {
+        let e::Webhook {
+            id,
+            created,
+            url,
+            events,
+            ..
+        } = w;
+        Webhook {
+            id,
+            created,
+            url,
+            events,
+            secret: None,
+        }
+    }
+}
+
+impl From<e::Report> for Report {
+    fn from(r: e::Report) -> Self {
+        let e::Report {
+            id,
+            created,
+            target,
+            target_id,
+            reason,
+            reported_by,
+            ..
+        } = r;
+        Report {
+            id,
+            created,
+            target,
+            target_id,
+            reason,
+            reported_by,
+        }
+    }
+}
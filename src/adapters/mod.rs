@@ -1,3 +1,4 @@
 pub mod csv;
 pub mod json;
+pub mod templates;
 pub mod user_communication;
@@ -0,0 +1,368 @@
+//! Subjects and bodies of the mails with `{placeholder}`s.
+//!
+//! The built-in templates live in `templates/mail/<language>/<name>.txt`
+//! and can be overridden by files with the same layout within the
+//! directory that is configured in the `[mail]` section.
+//! A template starts with a `Subject:` line followed by an empty line
+//! and the body. Snippets that are inserted into other templates
+//! only consist of a body.
+//! Templates with an additional `<name>.html` file are also sent
+//! with an HTML part, the values that are inserted into them are escaped.
+//! The pages that confirm the links within the mails are rendered
+//! from the same templates.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+const FALLBACK_LANGUAGE: &str = "de";
+
+macro_rules! builtin {
+    ($ext:expr, $($lang:expr => [$($name:expr),*]),*) => {
+        &[$($((
+            $lang,
+            $name,
+            include_str!(concat!("../../templates/mail/", $lang, "/", $name, $ext)),
+        ),)*)*]
+    };
+}
+
+const BUILTIN: &[(&str, &str, &str)] = builtin!(
+    ".txt",
+    "de" => [
+        "email_confirmation", "entry", "new_entry", "changed_entry", "approved_entry",
+        "new_event", "new_rating", "changed_rating", "digest", "unsubscribe_footer",
+        "unsubscribe_link",
+        "unsubscribe_named_link", "report", "report_entry", "report_rating", "report_comment",
+        "changed_entry_contact", "changed_field", "field_labels", "change_labels",
+        "digest_entry", "digest_event", "confirmation", "confirm_unsubscribe",
+        "confirm_contact_opt_out", "confirm_revert_entry"
+    ],
+    "en" => [
+        "email_confirmation", "entry", "new_entry", "changed_entry", "approved_entry",
+        "new_event", "new_rating", "changed_rating", "digest", "unsubscribe_footer",
+        "unsubscribe_link",
+        "unsubscribe_named_link", "report", "report_entry", "report_rating", "report_comment",
+        "changed_entry_contact", "changed_field", "field_labels", "change_labels",
+        "digest_entry", "digest_event", "confirmation", "confirm_unsubscribe",
+        "confirm_contact_opt_out", "confirm_revert_entry"
+    ]
+);
+
+const BUILTIN_HTML: &[(&str, &str, &str)] = builtin!(
+    ".html",
+    "de" => [
+        "layout", "entry", "new_entry", "changed_entry", "approved_entry",
+        "unsubscribe_footer", "unsubscribe_link", "unsubscribe_named_link",
+        "changed_entry_contact", "changed_field", "confirmation"
+    ],
+    "en" => [
+        "layout", "entry", "new_entry", "changed_entry", "approved_entry",
+        "unsubscribe_footer", "unsubscribe_link", "unsubscribe_named_link",
+        "changed_entry_contact", "changed_field", "confirmation"
+    ]
+);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Template {
+    subject: String,
+    body: String,
+}
+
+impl Template {
+    fn parse(txt: &str) -> Template {
+        let txt = txt.trim_end_matches(|c| c == '\n' || c == '\r');
+        if txt.starts_with("Subject:") {
+            let mut parts = txt.splitn(2, '\n');
+            let subject = parts.next().unwrap_or_default()["Subject:".len()..].trim();
+            let body = parts.next().unwrap_or_default();
+            Template {
+                subject: subject.into(),
+                body: body.trim_start_matches(|c| c == '\n' || c == '\r').into(),
+            }
+        } else {
+            Template {
+                subject: String::new(),
+                body: txt.into(),
+            }
+        }
+    }
+}
+
+/// Replaces all `{name}` placeholders with the given values.
+/// Unknown placeholders are left untouched.
+fn render(txt: &str, vars: &[(&str, &str)]) -> String {
+    let mut res = String::with_capacity(txt.len());
+    let mut rest = txt;
+    while let Some(start) = rest.find('{') {
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            vars.iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                res.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                res.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+/// Escapes the characters with a special meaning in HTML.
+pub fn escape_html(txt: &str) -> String {
+    let mut res = String::with_capacity(txt.len());
+    for c in txt.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn html_name(name: &str) -> String {
+    format!("{}.html", name)
+}
+
+/// The mail templates of all languages.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    default_language: String,
+    site_name: String,
+    site_url: String,
+    templates: HashMap<(String, String), Template>,
+}
+
+impl Templates {
+    /// Creates the built-in templates. The site name and URL
+    /// are available as `{site_name}` and `{site_url}`.
+    pub fn new(default_language: &str, site_name: &str, site_url: &str) -> Templates {
+        let html = BUILTIN_HTML
+            .iter()
+            .map(|(lang, name, txt)| (*lang, html_name(name), *txt));
+        let templates = BUILTIN
+            .iter()
+            .map(|(lang, name, txt)| (*lang, name.to_string(), *txt))
+            .chain(html)
+            .map(|(lang, name, txt)| ((lang.to_string(), name), Template::parse(txt)))
+            .collect();
+        Templates {
+            default_language: default_language.into(),
+            site_name: site_name.into(),
+            site_url: site_url.trim_end_matches('/').into(),
+            templates,
+        }
+    }
+
+    /// Overrides the templates with the files `<language>/<name>.txt`
+    /// and `<language>/<name>.html` within the given directory.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        for lang_dir in fs::read_dir(dir)? {
+            let lang_dir = lang_dir?.path();
+            if !lang_dir.is_dir() {
+                continue;
+            }
+            let lang = match lang_dir.file_name().and_then(|n| n.to_str()) {
+                Some(lang) => lang.to_string(),
+                None => continue,
+            };
+            for file in fs::read_dir(&lang_dir)? {
+                let file = file?.path();
+                let name = match (
+                    file.file_stem().and_then(|n| n.to_str()),
+                    file.extension().and_then(|e| e.to_str()),
+                ) {
+                    (Some(name), Some("txt")) => name.to_string(),
+                    (Some(name), Some("html")) => html_name(name),
+                    _ => continue,
+                };
+                let template = Template::parse(&fs::read_to_string(&file)?);
+                debug!("loaded mail template {}/{}", lang, name);
+                self.templates.insert((lang.clone(), name), template);
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up the template in the requested language
+    /// and falls back to the default language.
+    fn get(&self, lang: Option<&str>, name: &str) -> &Template {
+        self.languages(lang)
+            .filter_map(|lang| self.templates.get(&(lang.to_string(), name.to_string())))
+            .next()
+            .unwrap_or_else(|| panic!("Missing the built-in mail template {}", name))
+    }
+
+    fn languages<'a>(&'a self, lang: Option<&'a str>) -> impl Iterator<Item = &'a str> {
+        lang.into_iter()
+            .chain(vec![self.default_language.as_str(), FALLBACK_LANGUAGE])
+    }
+
+    /// Looks up the HTML variant of a template. There is none if the
+    /// plain text template of the same language comes without it,
+    /// so both parts of a mail are always written in the same language.
+    fn get_html(&self, lang: Option<&str>, name: &str) -> Option<&Template> {
+        for lang in self.languages(lang) {
+            let html = self.templates.get(&(lang.to_string(), html_name(name)));
+            if html.is_some() {
+                return html;
+            }
+            if self
+                .templates
+                .contains_key(&(lang.to_string(), name.to_string()))
+            {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Returns the subject and body of the template.
+    pub fn render(
+        &self,
+        lang: Option<&str>,
+        name: &str,
+        vars: &[(&str, &str)],
+    ) -> (String, String) {
+        let mut vars = vars.to_vec();
+        vars.push(("site_name", &self.site_name));
+        vars.push(("site_url", &self.site_url));
+        let t = self.get(lang, name);
+        (render(&t.subject, &vars), render(&t.body, &vars))
+    }
+
+    /// Returns the body of a snippet.
+    pub fn render_snippet(&self, lang: Option<&str>, name: &str, vars: &[(&str, &str)]) -> String {
+        self.render(lang, name, vars).1
+    }
+
+    /// Returns the HTML body of the template if there is one.
+    /// The `vars` are escaped whereas the `html` snippets
+    /// are inserted unchanged.
+    pub fn render_html(
+        &self,
+        lang: Option<&str>,
+        name: &str,
+        vars: &[(&str, &str)],
+        html: &[(&str, &str)],
+    ) -> Option<String> {
+        let t = self.get_html(lang, name)?;
+        let escaped: Vec<_> = vars
+            .iter()
+            .chain(&[
+                ("site_name", &*self.site_name),
+                ("site_url", &*self.site_url),
+            ])
+            .map(|(name, value)| (*name, escape_html(value)))
+            .collect();
+        let vars: Vec<_> = escaped
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .chain(html.iter().cloned())
+            .collect();
+        Some(render(&t.body, &vars))
+    }
+}
+
+impl Default for Templates {
+    fn default() -> Templates {
+        Templates::new("de", "Karte von morgen", "https://kartevonmorgen.org")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_templates() {
+        let t = Template::parse("Subject: Hello {name}\n\nHi,\n{text}\n");
+        assert_eq!(t.subject, "Hello {name}");
+        assert_eq!(t.body, "Hi,\n{text}");
+        let t = Template::parse("Only a {snippet}\n");
+        assert_eq!(t.subject, "");
+        assert_eq!(t.body, "Only a {snippet}");
+    }
+
+    #[test]
+    fn render_placeholders() {
+        assert_eq!(
+            render("{a} and {b} but not {c} or {", &[("a", "x"), ("b", "{a}")]),
+            "x and {a} but not {c} or {"
+        );
+    }
+
+    #[test]
+    fn fall_back_to_the_default_language() {
+        let t = Templates::default();
+        let (de, _) = t.render(None, "digest", &[]);
+        assert_eq!(de, "Karte von morgen - Zusammenfassung");
+        assert_eq!(t.render(Some("fr"), "digest", &[]).0, de);
+        let (en, _) = t.render(Some("en"), "digest", &[]);
+        assert_eq!(en, "Karte von morgen - Digest");
+        let t = Templates::new("en", "Map of tomorrow", "https://example.org/");
+        assert_eq!(
+            t.render(Some("fr"), "digest", &[]).0,
+            "Map of tomorrow - Digest"
+        );
+    }
+
+    #[test]
+    fn render_escaped_html() {
+        let t = Templates::default();
+        assert_eq!(
+            escape_html("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+        let html = t
+            .render_html(
+                Some("en"),
+                "layout",
+                &[("footer", "<b>")],
+                &[("content", "<p>Hi</p>")],
+            )
+            .unwrap();
+        assert!(html.contains("<p>Hi</p>"));
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(t.render_html(None, "digest", &[], &[]).is_none());
+    }
+
+    #[test]
+    fn override_templates() {
+        let dir = std::env::temp_dir().join(format!("templates-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("fr")).unwrap();
+        fs::write(
+            dir.join("fr").join("digest.txt"),
+            "Subject: Résumé\n\n{changes}",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("fr").join("new_entry.txt"),
+            "Subject: Nouveau\n\n{entry}",
+        )
+        .unwrap();
+        let mut t = Templates::default();
+        t.load_dir(&dir).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        let (subject, body) = t.render(Some("fr"), "digest", &[("changes", "-")]);
+        assert_eq!(subject, "Résumé");
+        assert_eq!(body, "-");
+        assert_eq!(
+            t.render(Some("fr"), "report", &[]).0,
+            "Karte von morgen - neue Meldung"
+        );
+        assert!(t.render_html(Some("fr"), "new_entry", &[], &[]).is_none());
+        assert!(t.render_html(Some("fr"), "layout", &[], &[]).is_some());
+    }
+}
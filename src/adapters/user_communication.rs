@@ -1,16 +1,40 @@
+//! The subjects and bodies of all mails in the language of the recipient.
+
+use super::templates::{escape_html, Templates};
 use crate::core::entities::*;
-use crate::core::usecases::{NewEntry, UpdateEntry};
+use crate::core::usecases::{ChangedField, NewEntry, RateEntry, UpdateEntry};
+use chrono::NaiveDateTime;
 
-pub fn email_confirmation_email(u_id: &str) -> String {
-    format!(
-        "Na du Weltverbesserer*,\nwir freuen uns dass du bei der Karte von morgen mit dabei bist!\n\nBitte bestätige deine Email-Adresse hier:\nhttps://kartevonmorgen.org/#/?confirm_email={}.\n\neuphorische Grüße\ndas Karte von morgen-Team",
-        u_id
-    )
+/// The subject and body of a mail
+/// with an optional HTML variant of the body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub subject: String,
+    pub body: String,
+    pub html: Option<String>,
+}
+
+impl From<(String, String)> for Message {
+    fn from((subject, body): (String, String)) -> Message {
+        Message {
+            subject,
+            body,
+            html: None,
+        }
+    }
 }
 
-pub fn new_entry_email(e: &NewEntry, id: &str, categories: &[String]) -> String {
-    let intro_sentence = "ein neuer Eintrag auf der Karte von morgen wurde erstellt";
+pub fn email_confirmation_email(t: &Templates, lang: Option<&str>, u_id: &str) -> Message {
+    t.render(lang, "email_confirmation", &[("id", u_id)]).into()
+}
 
+pub fn new_entry_email(
+    t: &Templates,
+    lang: Option<&str>,
+    e: &NewEntry,
+    id: &str,
+    categories: &[String],
+) -> Message {
     //TODO: check fields
     let address = Some(Address {
         street: e.street.clone(),
@@ -44,13 +68,34 @@ pub fn new_entry_email(e: &NewEntry, id: &str, categories: &[String]) -> String
         image_url: None,
         image_link_url: None,
     };
-    entry_email(&entry, categories, &e.tags, intro_sentence)
+    entry_email(t, lang, "new_entry", &entry, categories, &e.tags)
 }
 
-//TODO: calc diff
-pub fn changed_entry_email(e: &UpdateEntry, categories: &[String]) -> String {
-    let intro_sentence = "folgender Eintrag der Karte von morgen wurde verändert";
+pub fn approved_entry_email(
+    t: &Templates,
+    lang: Option<&str>,
+    e: &Entry,
+    categories: &[String],
+) -> Message {
+    entry_email(t, lang, "approved_entry", e, categories, &e.tags)
+}
 
+pub fn reverted_entry_email(
+    t: &Templates,
+    lang: Option<&str>,
+    e: &Entry,
+    categories: &[String],
+) -> Message {
+    entry_email(t, lang, "changed_entry", e, categories, &e.tags)
+}
+
+//TODO: calc diff
+pub fn changed_entry_email(
+    t: &Templates,
+    lang: Option<&str>,
+    e: &UpdateEntry,
+    categories: &[String],
+) -> Message {
     let address = Some(Address {
         street: e.street.clone(),
         zip: e.zip.clone(),
@@ -83,15 +128,17 @@ pub fn changed_entry_email(e: &UpdateEntry, categories: &[String]) -> String {
         image_url: None,
         image_link_url: None,
     };
-    entry_email(&entry, categories, &e.tags, intro_sentence)
+    entry_email(t, lang, "changed_entry", &entry, categories, &e.tags)
 }
 
 pub fn entry_email(
+    t: &Templates,
+    lang: Option<&str>,
+    template: &str,
     e: &Entry,
     categories: &[String],
     tags: &[String],
-    intro_sentence: &str,
-) -> String {
+) -> Message {
     let category = if !categories.is_empty() {
         categories[0].clone()
     } else {
@@ -118,38 +165,508 @@ pub fn entry_email(
         ]
         .join(" "),
         country.unwrap_or_else(|| "".into()),
-    ]
-    .join(", ");
+    ];
+    let address_lines: Vec<_> = address
+        .iter()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let address = address.join(", ");
 
     let Contact { email, telephone } = e.contact.clone().unwrap_or_else(|| Contact {
         email: None,
         telephone: None,
     });
 
-    format!(
-        "Hallo,
-{introSentence}:\n
-{title} ({category})
-{description}\n
-    Tags: {tags}
-    Adresse: {address}
-    Webseite: {homepage}
-    Email-Adresse: {email}
-    Telefon: {telephone}\n
-Eintrag anschauen oder bearbeiten:
-https://kartevonmorgen.org/#/?entry={id}\n
-Du kannst dein Abonnement des Kartenbereichs abbestellen indem du dich auf https://kartevonmorgen.org einloggst.\n
-euphorische Grüße
-das Karte von morgen-Team",
-        introSentence = intro_sentence,
-        title = &e.title,
-        id = &e.id,
-        description = &e.description,
-        address = address,
-        email = email.unwrap_or_else(||"".into()),
-        telephone = telephone.unwrap_or_else(||"".into()),
-        homepage = e.homepage.clone().unwrap_or_else(||"".into()),
-        category = category,
-        tags = tags.join(", ")
+    let email = email.unwrap_or_default();
+    let telephone = telephone.unwrap_or_default();
+    let homepage = e.homepage.clone().unwrap_or_default();
+    let tags = tags.join(", ");
+    let vars = [
+        ("title", e.title.as_str()),
+        ("id", &e.id),
+        ("email", &email),
+        ("telephone", &telephone),
+        ("homepage", &homepage),
+        ("tags", &tags),
+    ];
+
+    let mut txt_vars = vars.to_vec();
+    txt_vars.push(("description", &e.description));
+    txt_vars.push(("address", &address));
+    txt_vars.push(("category", &category));
+    let entry = t.render_snippet(lang, "entry", &txt_vars);
+    let mut msg: Message = t
+        .render(lang, template, &[("title", &e.title), ("entry", &entry)])
+        .into();
+
+    let mut html_vars = vars.to_vec();
+    let categories = categories.join(", ");
+    html_vars.push(("categories", &categories));
+    let address_block = address_lines
+        .iter()
+        .map(|l| escape_html(l))
+        .collect::<Vec<_>>()
+        .join("<br>");
+    let description = escape_html(&e.description).replace('\n', "<br>");
+    msg.html = t
+        .render_html(
+            lang,
+            "entry",
+            &html_vars,
+            &[("address", &address_block), ("description", &description)],
+        )
+        .and_then(|entry| {
+            t.render_html(lang, template, &[("title", &e.title)], &[("entry", &entry)])
+        });
+    msg
+}
+
+/// The label of a name in a snippet like `field_labels`
+/// (one `<name>: <label>` per line).
+fn label(t: &Templates, lang: Option<&str>, snippet: &str, name: &str) -> String {
+    t.render_snippet(lang, snippet, &[])
+        .lines()
+        .filter_map(|l| {
+            let mut parts = l.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(n), Some(label)) if n.trim() == name => Some(label.trim().to_string()),
+                _ => None,
+            }
+        })
+        .next()
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Tells the contact of an entry what has been changed.
+pub fn changed_entry_contact_email(
+    t: &Templates,
+    lang: Option<&str>,
+    e: &Entry,
+    changes: &[ChangedField],
+    revert_url: &str,
+    opt_out_url: &str,
+) -> Message {
+    let labels: Vec<_> = changes
+        .iter()
+        .map(|c| label(t, lang, "field_labels", c.name))
+        .collect();
+    let txt_changes: Vec<_> = changes
+        .iter()
+        .zip(&labels)
+        .map(|(c, label)| {
+            t.render_snippet(
+                lang,
+                "changed_field",
+                &[("field", label), ("old", &c.old), ("new", &c.new)],
+            )
+        })
+        .collect();
+    let vars = [
+        ("title", e.title.as_str()),
+        ("id", &e.id),
+        ("revert_url", revert_url),
+        ("opt_out_url", opt_out_url),
+    ];
+    let mut txt_vars = vars.to_vec();
+    let txt_changes = txt_changes.join("\n\n");
+    txt_vars.push(("changes", &txt_changes));
+    let mut msg: Message = t.render(lang, "changed_entry_contact", &txt_vars).into();
+    msg.html = changes
+        .iter()
+        .zip(&labels)
+        .map(|(c, label)| {
+            t.render_html(
+                lang,
+                "changed_field",
+                &[("field", label), ("old", &c.old), ("new", &c.new)],
+                &[],
+            )
+        })
+        .collect::<Option<Vec<_>>>()
+        .and_then(|rows| {
+            t.render_html(
+                lang,
+                "changed_entry_contact",
+                &vars,
+                &[("changes", &rows.join("\n"))],
+            )
+        });
+    msg
+}
+
+pub fn new_event_email(t: &Templates, lang: Option<&str>, e: &Event) -> Message {
+    let start = NaiveDateTime::from_timestamp(e.start as i64, 0).format("%d.%m.%Y %H:%M");
+    let address = e
+        .location
+        .as_ref()
+        .and_then(|l| l.address.as_ref())
+        .map(|a| {
+            vec![
+                a.street.clone().unwrap_or_default(),
+                vec![
+                    a.zip.clone().unwrap_or_default(),
+                    a.city.clone().unwrap_or_default(),
+                ]
+                .join(" "),
+            ]
+            .join(", ")
+        })
+        .unwrap_or_default();
+    t.render(
+        lang,
+        "new_event",
+        &[
+            ("title", &e.title),
+            ("start", &start.to_string()),
+            ("description", &e.description.clone().unwrap_or_default()),
+            ("tags", &e.tags.join(", ")),
+            ("address", &address),
+            ("homepage", &e.homepage.clone().unwrap_or_default()),
+        ],
     )
+    .into()
+}
+
+/// Ratings of logged in users are updated
+/// if they rate the entry again.
+pub fn rating_email(
+    t: &Templates,
+    lang: Option<&str>,
+    e: &Entry,
+    r: &RateEntry,
+    updated: bool,
+) -> Message {
+    t.render(
+        lang,
+        if updated {
+            "changed_rating"
+        } else {
+            "new_rating"
+        },
+        &[
+            ("entry", &e.title),
+            ("title", &r.title),
+            ("value", &r.value.to_string()),
+            ("comment", &r.comment),
+            ("id", &e.id),
+        ],
+    )
+    .into()
+}
+
+pub fn digest_email(
+    t: &Templates,
+    lang: Option<&str>,
+    notifications: &[PendingNotification],
+) -> Message {
+    let changes: Vec<_> = notifications
+        .iter()
+        .map(|n| {
+            let created = NaiveDateTime::from_timestamp(n.created as i64, 0)
+                .format("%d.%m.%Y %H:%M")
+                .to_string();
+            let change = label(t, lang, "change_labels", &String::from(n.kind));
+            // Events and notifications from before the summaries have no link
+            let snippet = if n.kind == ChangeKind::NewEvent || n.target_id.is_empty() {
+                "digest_event"
+            } else {
+                "digest_entry"
+            };
+            t.render_snippet(
+                lang,
+                snippet,
+                &[
+                    ("change", &change),
+                    ("title", &n.title),
+                    ("created", &created),
+                    ("id", &n.target_id),
+                ],
+            )
+        })
+        .collect();
+    let changes = changes.join("\n\n");
+    t.render(lang, "digest", &[("changes", &changes)]).into()
+}
+
+/// The footer of notification mails with a link
+/// to unsubscribe from each of the subscriptions.
+pub fn unsubscribe_footer(
+    t: &Templates,
+    lang: Option<&str>,
+    links: &[(&BboxSubscription, String)],
+) -> String {
+    let links: Vec<_> = links
+        .iter()
+        .map(|(s, url)| match s.name {
+            Some(ref name) => t.render_snippet(
+                lang,
+                "unsubscribe_named_link",
+                &[("name", name), ("url", url)],
+            ),
+            None => t.render_snippet(lang, "unsubscribe_link", &[("url", url)]),
+        })
+        .collect();
+    let footer = t.render_snippet(lang, "unsubscribe_footer", &[("links", &links.join("\n"))]);
+    format!("\n\n{}", footer)
+}
+
+/// The HTML variant of the footer of notification mails.
+pub fn unsubscribe_footer_html(
+    t: &Templates,
+    lang: Option<&str>,
+    links: &[(&BboxSubscription, String)],
+) -> Option<String> {
+    let links = links
+        .iter()
+        .map(|(s, url)| match s.name {
+            Some(ref name) => t.render_html(
+                lang,
+                "unsubscribe_named_link",
+                &[("name", name), ("url", url)],
+                &[],
+            ),
+            None => t.render_html(lang, "unsubscribe_link", &[("url", url)], &[]),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    t.render_html(
+        lang,
+        "unsubscribe_footer",
+        &[],
+        &[("links", &links.join("<br>\n"))],
+    )
+}
+
+pub fn report_email(t: &Templates, lang: Option<&str>, r: &Report) -> Message {
+    let target = match r.target {
+        ReportTarget::Entry => "report_entry",
+        ReportTarget::Rating => "report_rating",
+        ReportTarget::Comment => "report_comment",
+    };
+    let target = t.render_snippet(lang, target, &[("id", &r.target_id)]);
+    t.render(
+        lang,
+        "report",
+        &[("target", &target), ("reason", &r.reason), ("id", &r.id)],
+    )
+    .into()
+}
+
+/// The page behind a link within a mail that asks to confirm the action
+/// of the link, because mail scanners and prefetchers follow all links.
+/// The subject of the template is the label of the button.
+pub fn confirmation_page(
+    t: &Templates,
+    lang: Option<&str>,
+    template: &str,
+    vars: &[(&str, &str)],
+) -> String {
+    let (action, question) = t.render(lang, template, vars);
+    t.render_html(
+        lang,
+        "confirmation",
+        &[("action", &action), ("question", &question)],
+        &[],
+    )
+    .unwrap_or(question)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        Report {
+            id: "r1".into(),
+            created: 0,
+            target: ReportTarget::Entry,
+            target_id: "e1".into(),
+            reason: "Spam".into(),
+            reported_by: None,
+            resolution: None,
+            resolved_by: None,
+        }
+    }
+
+    #[test]
+    fn render_report_mails_in_the_language_of_the_scout() {
+        let t = Templates::default();
+        let Message {
+            subject,
+            body,
+            html,
+        } = report_email(&t, None, &report());
+        assert!(html.is_none());
+        assert_eq!(subject, "Karte von morgen - neue Meldung");
+        assert!(body.starts_with(
+            "Hallo,\njemand hat den Eintrag https://kartevonmorgen.org/#/?entry=e1 gemeldet:\n\nSpam\n\n"
+        ));
+        assert!(body.ends_with("euphorische Grüße\ndas Karte von morgen-Team"));
+        let Message { subject, body, .. } = report_email(&t, Some("en"), &report());
+        assert_eq!(subject, "Karte von morgen - new report");
+        assert!(body.contains("someone reported the entry https://kartevonmorgen.org/#/?entry=e1:"));
+    }
+
+    #[test]
+    fn render_confirmation_pages() {
+        let t = Templates::default();
+        let page = confirmation_page(
+            &t,
+            Some("en"),
+            "confirm_revert_entry",
+            &[("title", "<Foo>")],
+        );
+        assert!(page.contains("<form method=\"post\">"));
+        assert!(page.contains(">Revert the change</button>"));
+        assert!(page.contains("the entry &quot;&lt;Foo&gt;&quot; on Karte von morgen?"));
+        let page = confirmation_page(&t, None, "confirm_unsubscribe", &[]);
+        assert!(page.contains(">Abbestellen</button>"));
+    }
+
+    #[test]
+    fn render_new_and_changed_ratings() {
+        let t = Templates::new("de", "Map", "https://example.org");
+        let e = Entry::build().id("e1").title("Foo").finish();
+        let r = RateEntry {
+            entry: "e1".into(),
+            title: "Nice".into(),
+            value: 2,
+            context: RatingContext::Fairness,
+            comment: "Really nice".into(),
+            source: None,
+            user: None,
+        };
+        let new = rating_email(&t, None, &e, &r, false);
+        assert_eq!(new.subject, "Map - neue Bewertung: Foo");
+        let changed = rating_email(&t, None, &e, &r, true);
+        assert_eq!(changed.subject, "Map - geänderte Bewertung: Foo");
+        assert!(changed.body.contains(
+            "eine Bewertung des Eintrags \"Foo\" auf der Map wurde geändert:\n\nNice (2)"
+        ));
+        let changed = rating_email(&t, Some("en"), &e, &r, true);
+        assert_eq!(changed.subject, "Map - changed rating: Foo");
+    }
+
+    #[test]
+    fn render_digests_with_a_summary_of_each_change() {
+        let t = Templates::new("de", "Map", "https://example.org");
+        let n = PendingNotification {
+            id: "n1".into(),
+            username: "foo".into(),
+            delivery: Delivery::Daily,
+            created: 0,
+            kind: ChangeKind::NewEntry,
+            title: "Foo".into(),
+            target_id: "e1".into(),
+        };
+        let event = PendingNotification {
+            id: "n2".into(),
+            kind: ChangeKind::NewEvent,
+            title: "Bar".into(),
+            target_id: "ev1".into(),
+            ..n.clone()
+        };
+        let Message { subject, body, .. } = digest_email(&t, None, &[n.clone(), event.clone()]);
+        assert_eq!(subject, "Map - Zusammenfassung");
+        assert!(body.contains(
+            "Neuer Eintrag: Foo (01.01.1970 00:00)\nhttps://example.org/#/?entry=e1\n\nNeues Event: Bar (01.01.1970 00:00)\n\n"
+        ));
+        assert_eq!(body.matches("Hallo").count(), 1);
+        assert_eq!(body.matches("euphorische Grüße").count(), 1);
+        let Message { body, .. } = digest_email(&t, Some("en"), &[n]);
+        assert!(body.contains("New entry: Foo (01.01.1970 00:00)\n"));
+    }
+
+    #[test]
+    fn render_unsubscribe_links() {
+        let t = Templates::new("de", "Map", "https://example.org");
+        let mut s = BboxSubscription {
+            id: "1".into(),
+            name: Some("Home".into()),
+            bbox: Bbox {
+                south_west: Coordinate { lat: 0.0, lng: 0.0 },
+                north_east: Coordinate { lat: 1.0, lng: 1.0 },
+            },
+            username: "a".into(),
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            delivery: Delivery::Instant,
+        };
+        let footer = unsubscribe_footer(&t, None, &[(&s, "https://u/1".into())]);
+        assert_eq!(
+            footer,
+            "\n\n--\nDu erhältst diese E-Mail, weil du einen Kartenbereich auf der Map abonniert hast.\nAbonnement \"Home\" abbestellen:\nhttps://u/1"
+        );
+        s.name = None;
+        let footer = unsubscribe_footer(&t, Some("en"), &[(&s, "https://u/1".into())]);
+        assert!(footer.ends_with("on Map.\nUnsubscribe:\nhttps://u/1"));
+        let footer = unsubscribe_footer_html(&t, Some("en"), &[(&s, "https://u/1?a&b".into())]);
+        assert!(footer
+            .unwrap()
+            .contains("<a href=\"https://u/1?a&amp;b\">Unsubscribe</a>"));
+    }
+
+    #[test]
+    fn render_changes_for_the_contact_of_an_entry() {
+        let t = Templates::new("de", "Map", "https://example.org");
+        let e = Entry::build().id("e1").title("Foo").finish();
+        let changes = vec![ChangedField {
+            name: "homepage",
+            old: "https://a.org".into(),
+            new: "https://b.org?a&b".into(),
+        }];
+        let msg = changed_entry_contact_email(
+            &t,
+            Some("en"),
+            &e,
+            &changes,
+            "https://api/revert",
+            "https://api/opt-out",
+        );
+        assert_eq!(msg.subject, "Map - your entry has been changed: Foo");
+        assert!(msg
+            .body
+            .contains("Website\n  before: https://a.org\n  now:    https://b.org?a&b"));
+        assert!(msg.body.contains("revert it:\nhttps://api/revert"));
+        assert!(msg.body.ends_with("changes:\nhttps://api/opt-out"));
+        let html = msg.html.unwrap();
+        assert!(html.contains(
+            "<del>https://a.org</del></td><td valign=\"top\"><ins>https://b.org?a&amp;b</ins>"
+        ));
+        assert!(html.contains("<a href=\"https://api/revert\">revert it</a>"));
+        let msg = changed_entry_contact_email(&t, None, &e, &changes, "r", "o");
+        assert!(msg.body.contains("Webseite\n  vorher: https://a.org"));
+    }
+
+    #[test]
+    fn render_entry_mails_with_html() {
+        let t = Templates::new("de", "Map", "https://example.org");
+        let mut e = Entry::build()
+            .id("e1")
+            .title("<Foo & Bar>")
+            .description("first\nsecond")
+            .categories(vec!["c1"])
+            .tags(vec!["a", "b"])
+            .finish();
+        e.location.address = Some(Address {
+            street: Some("Street 1".into()),
+            zip: Some("12345".into()),
+            city: Some("City".into()),
+            country: None,
+        });
+        let categories = vec!["Initiative".to_string(), "Company".to_string()];
+        let msg = approved_entry_email(&t, Some("en"), &e, &categories);
+        assert_eq!(msg.subject, "Map - new entry: <Foo & Bar>");
+        assert!(msg.body.contains("<Foo & Bar> (Initiative)"));
+        let html = msg.html.unwrap();
+        assert!(html.contains(
+            "<h2><a href=\"https://example.org/#/?entry=e1\">&lt;Foo &amp; Bar&gt;</a></h2>"
+        ));
+        assert!(html.contains("<p>first<br>second</p>"));
+        assert!(html.contains("<p>Street 1<br>12345 City</p>"));
+        assert!(html.contains("<td>Initiative, Company</td>"));
+        assert!(html.contains("<td>a, b</td>"));
+        assert!(html.contains("a new entry on Map has been approved"));
+    }
 }
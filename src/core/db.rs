@@ -5,10 +5,19 @@ type Result<T> = result::Result<T, RepoError>;
 
 pub trait EntryGateway {
     fn create_entry(&mut self, _: Entry) -> Result<()>;
+    /// Pending entries are excluded from all queries
+    /// except `pending_entries` until they are approved.
+    fn create_pending_entry(&mut self, _: Entry) -> Result<()>;
     fn get_entry(&self, _: &str) -> Result<Entry>;
+    /// Returns any version of an entry (e.g. to revert a change).
+    fn get_entry_version(&self, id: &str, version: u64) -> Result<Entry>;
     fn get_entries_by_bbox(&self, _: &Bbox) -> Result<Vec<Entry>>;
     fn all_entries(&self) -> Result<Vec<Entry>>;
+    fn pending_entries(&self) -> Result<Vec<Entry>>;
+    fn approve_entry(&mut self, id: &str) -> Result<()>;
     fn update_entry(&mut self, _: &Entry) -> Result<()>;
+    /// Archived entries are excluded from all queries.
+    fn archive_entry(&mut self, id: &str, archived: u64) -> Result<()>;
     fn import_multiple_entries(&mut self, _: &[Entry]) -> Result<()>;
 }
 
@@ -26,12 +35,99 @@ pub trait UserGateway {
     fn get_user(&self, username: &str) -> Result<User>;
     // TODO: fn get_user_by_email(&self, email: &str) -> Result<User>;
     fn all_users(&self) -> Result<Vec<User>>;
+    /// Deletes the user together with the subscriptions, the pending
+    /// notifications, the login history and the access tokens.
+    /// Events, ratings, comments and reports of the user are kept anonymously.
     fn delete_user(&mut self, username: &str) -> Result<()>;
+    /// Returns `RepoError::NotFound` if the user never tried to log in.
+    fn get_login_history(&self, username: &str) -> Result<LoginHistory>;
+    /// Creates or replaces the login history of a user.
+    fn update_login_history(&mut self, history: &LoginHistory) -> Result<()>;
+}
+
+pub trait UserTokenGateway {
+    fn create_user_token(&mut self, _: UserToken) -> Result<()>;
+    fn get_user_token(&self, id: &str) -> Result<UserToken>;
+    fn get_user_token_by_hash(&self, token_hash: &str) -> Result<UserToken>;
+    fn user_tokens(&self, username: &str) -> Result<Vec<UserToken>>;
+    fn delete_user_token(&mut self, id: &str) -> Result<()>;
+}
+
+pub trait RatingGateway {
+    /// Creates a rating and updates the average ratings of the entry.
+    fn create_rating(&mut self, _: Rating) -> Result<()>;
+    fn get_rating(&self, id: &str) -> Result<Rating>;
+    /// Returns `RepoError::NotFound` if the user didn't rate
+    /// the entry in this context.
+    fn get_rating_of_user(
+        &self,
+        entry_id: &str,
+        context: &RatingContext,
+        username: &str,
+    ) -> Result<Rating>;
+    fn all_ratings(&self) -> Result<Vec<Rating>>;
+    fn ratings_of_entry(&self, entry_id: &str) -> Result<Vec<Rating>>;
+    /// Updates a rating and the average ratings of the entry.
+    fn update_rating(&mut self, _: &Rating) -> Result<()>;
+    /// Deletes a rating with all its comments
+    /// and updates the average ratings of the entry.
+    fn delete_rating(&mut self, id: &str) -> Result<()>;
 }
 
 pub trait CommentGateway {
     fn create_comment(&mut self, _: Comment) -> Result<()>;
+    fn get_comment(&self, id: &str) -> Result<Comment>;
     fn all_comments(&self) -> Result<Vec<Comment>>;
+    fn comments_of_rating(&self, rating_id: &str) -> Result<Vec<Comment>>;
+    fn update_comment(&mut self, _: &Comment) -> Result<()>;
+    /// Deletes all or none of the comments (in the given order).
+    fn delete_comments(&mut self, ids: &[String]) -> Result<()>;
+}
+
+pub trait AvgRatingGateway {
+    /// Entries without any ratings have empty (default) averages.
+    fn get_avg_ratings(&self, entry_id: &str) -> Result<AvgRatings>;
+    fn all_avg_ratings(&self) -> Result<Vec<AvgRatings>>;
+}
+
+pub trait ReportGateway {
+    fn create_report(&mut self, _: Report) -> Result<()>;
+    fn get_report(&self, id: &str) -> Result<Report>;
+    fn all_reports(&self) -> Result<Vec<Report>>;
+    fn update_report(&mut self, _: &Report) -> Result<()>;
+}
+
+pub trait NotificationGateway {
+    fn create_pending_notification(&mut self, _: PendingNotification) -> Result<()>;
+    fn all_pending_notifications(&self) -> Result<Vec<PendingNotification>>;
+    fn delete_pending_notification(&mut self, id: &str) -> Result<()>;
+    fn create_contact_opt_out(&mut self, email: &str) -> Result<()>;
+    fn is_contact_opted_out(&self, email: &str) -> Result<bool>;
+}
+
+pub trait OutboxGateway {
+    fn create_outbox_mail(&mut self, _: OutboxMail) -> Result<()>;
+    fn get_outbox_mail(&self, id: &str) -> Result<OutboxMail>;
+    fn all_outbox_mails(&self) -> Result<Vec<OutboxMail>>;
+    /// The pending mails that should be delivered at `now` ordered by their creation.
+    fn due_outbox_mails(&self, now: u64) -> Result<Vec<OutboxMail>>;
+    fn update_outbox_mail(&mut self, _: &OutboxMail) -> Result<()>;
+    fn delete_outbox_mail(&mut self, id: &str) -> Result<()>;
+}
+
+pub trait WebhookGateway {
+    fn create_webhook(&mut self, _: Webhook) -> Result<()>;
+    fn get_webhook(&self, id: &str) -> Result<Webhook>;
+    fn all_webhooks(&self) -> Result<Vec<Webhook>>;
+    /// Deletes the webhook together with its undelivered payloads.
+    fn delete_webhook(&mut self, id: &str) -> Result<()>;
+    fn create_webhook_delivery(&mut self, _: WebhookDelivery) -> Result<()>;
+    fn get_webhook_delivery(&self, id: &str) -> Result<WebhookDelivery>;
+    fn all_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>>;
+    /// The pending payloads that should be delivered at `now` ordered by their creation.
+    fn due_webhook_deliveries(&self, now: u64) -> Result<Vec<WebhookDelivery>>;
+    fn update_webhook_delivery(&mut self, _: &WebhookDelivery) -> Result<()>;
+    fn delete_webhook_delivery(&mut self, id: &str) -> Result<()>;
 }
 
 pub trait OrganizationGateway {
@@ -43,21 +139,31 @@ pub trait OrganizationGateway {
 //TODO:
 //  - TagGeatway
 //  - CategoryGateway
-//  - RatingGateway
 //  - SubscriptionGateway
 
 pub trait Db:
-    EntryGateway + UserGateway + CommentGateway + EventGateway + OrganizationGateway
+    EntryGateway
+    + UserGateway
+    + UserTokenGateway
+    + RatingGateway
+    + CommentGateway
+    + EventGateway
+    + OrganizationGateway
+    + AvgRatingGateway
+    + ReportGateway
+    + NotificationGateway
+    + OutboxGateway
+    + WebhookGateway
 {
     fn create_tag_if_it_does_not_exist(&mut self, _: &Tag) -> Result<()>;
     fn create_category_if_it_does_not_exist(&mut self, _: &Category) -> Result<()>;
-    fn create_rating(&mut self, _: Rating) -> Result<()>;
     fn create_bbox_subscription(&mut self, _: &BboxSubscription) -> Result<()>;
 
     fn all_categories(&self) -> Result<Vec<Category>>;
     fn all_tags(&self) -> Result<Vec<Tag>>;
-    fn all_ratings(&self) -> Result<Vec<Rating>>;
     fn all_bbox_subscriptions(&self) -> Result<Vec<BboxSubscription>>;
+    fn get_bbox_subscription(&self, id: &str) -> Result<BboxSubscription>;
+    fn bbox_subscriptions_by_username(&self, username: &str) -> Result<Vec<BboxSubscription>>;
 
     fn delete_bbox_subscription(&mut self, _: &str) -> Result<()>;
 }
@@ -95,10 +95,12 @@ pub struct User {
     pub email           : String,
     pub email_confirmed : bool,
     pub role            : Role,
+    /// The preferred language of mails (e.g. `en`).
+    pub language        : Option<String>,
 }
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
-#[derive(Debug, Clone, Copy, PartialEq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, FromPrimitive, ToPrimitive)]
 pub enum Role {
     Guest = 0,
     User  = 1,
@@ -112,13 +114,40 @@ impl Default for Role {
     }
 }
 
+/// An access token that authenticates a user
+/// like the cookie of a successful login.
+/// Only the SHA-256 hash of the secret token is stored.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserToken {
+    pub id         : String,
+    pub token_hash : String,
+    pub username   : String,
+    pub created    : u64,
+    pub expires    : u64,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoginHistory {
+    pub username            : String,
+    /// The number of failed attempts since the last successful login
+    pub failed_attempts     : u32,
+    pub last_failed_attempt : Option<u64>,
+    pub last_login          : Option<u64>,
+}
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Comment {
-    pub id        : String,
-    pub created   : u64,
-    pub text      : String,
-    pub rating_id : String,
+    pub id         : String,
+    pub created    : u64,
+    pub text       : String,
+    pub rating_id  : String,
+    /// The comment this one replies to
+    pub parent_id  : Option<String>,
+    /// The username of the author (if not anonymous)
+    pub created_by : Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -135,13 +164,60 @@ pub enum RatingContext {
 #[cfg_attr(rustfmt, rustfmt_skip)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Rating {
-    pub id       : String,
-    pub entry_id : String,
-    pub created  : u64,
-    pub title    : String,
-    pub value    : i8,
-    pub context  : RatingContext,
-    pub source   : Option<String>,
+    pub id         : String,
+    pub entry_id   : String,
+    pub created    : u64,
+    /// The time of the last change (if the rating has been changed)
+    pub updated    : Option<u64>,
+    pub title      : String,
+    pub value      : i8,
+    pub context    : RatingContext,
+    pub source     : Option<String>,
+    /// The username of the author (if not anonymous)
+    pub created_by : Option<String>,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AvgRating {
+    pub value : f64,
+    pub count : u64,
+}
+
+/// The aggregated ratings of an entry
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AvgRatings {
+    pub entry_id     : String,
+    pub total        : f64,
+    pub diversity    : AvgRating,
+    pub renewable    : AvgRating,
+    pub fairness     : AvgRating,
+    pub humanity     : AvgRating,
+    pub transparency : AvgRating,
+    pub solidarity   : AvgRating,
+}
+
+impl AvgRatings {
+    pub fn context(&self, context: &RatingContext) -> AvgRating {
+        match context {
+            RatingContext::Diversity => self.diversity,
+            RatingContext::Renewable => self.renewable,
+            RatingContext::Fairness => self.fairness,
+            RatingContext::Humanity => self.humanity,
+            RatingContext::Transparency => self.transparency,
+            RatingContext::Solidarity => self.solidarity,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diversity.count == 0
+            && self.renewable.count == 0
+            && self.fairness.count == 0
+            && self.humanity.count == 0
+            && self.transparency.count == 0
+            && self.solidarity.count == 0
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -156,12 +232,170 @@ pub struct Bbox {
     pub north_east: Coordinate,
 }
 
+/// The kind of change subscribers are notified about.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    NewEntry,
+    UpdatedEntry,
+    NewEvent,
+    NewRating,
+}
+
+/// When subscribers are notified about changes.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    /// A mail for each change
+    Instant,
+    /// A digest of all changes once a day
+    Daily,
+    /// A digest of all changes once a week
+    Weekly,
+}
+
+impl Default for Delivery {
+    fn default() -> Delivery {
+        Delivery::Instant
+    }
+}
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct BboxSubscription {
-    pub id       : String,
-    pub bbox     : Bbox,
-    pub username : String,
+    pub id         : String,
+    /// Subscriptions created with `subscribe_to_bbox` have no name
+    pub name       : Option<String>,
+    pub bbox       : Bbox,
+    pub username   : String,
+    /// Only entries with one of these categories (any if empty)
+    pub categories : Vec<String>,
+    /// Only entries and events with one of these tags (any if empty)
+    pub tags       : Vec<String>,
+    /// Only these kinds of changes (all if empty)
+    pub changes    : Vec<ChangeKind>,
+    pub delivery   : Delivery,
+}
+
+/// A summary of a change that is collected for the next digest.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingNotification {
+    pub id        : String,
+    pub username  : String,
+    pub delivery  : Delivery,
+    pub created   : u64,
+    pub kind      : ChangeKind,
+    /// The title of the changed entry or event
+    pub title     : String,
+    /// The ID of the changed entry or event
+    pub target_id : String,
+}
+
+/// The state of a mail within the outbox.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MailStatus {
+    /// Waiting for the next attempt to deliver it
+    Pending,
+    /// Not delivered after too many attempts
+    Failed,
+}
+
+/// A mail that is kept until it has been delivered.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxMail {
+    pub id           : String,
+    pub created      : u64,
+    pub recipients   : Vec<String>,
+    pub subject      : String,
+    /// The composed message including the headers
+    pub content      : String,
+    pub status       : MailStatus,
+    pub attempts     : u32,
+    pub next_attempt : u64,
+    pub last_error   : Option<String>,
+}
+
+/// A change that is sent to the webhooks of organizations.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub enum WebhookEvent {
+    #[serde(rename = "entry.created")]
+    EntryCreated,
+    #[serde(rename = "entry.updated")]
+    EntryUpdated,
+    #[serde(rename = "entry.archived")]
+    EntryArchived,
+    #[serde(rename = "event.created")]
+    EventCreated,
+    #[serde(rename = "event.updated")]
+    EventUpdated,
+    #[serde(rename = "event.archived")]
+    EventArchived,
+    #[serde(rename = "rating.created")]
+    RatingCreated,
+    #[serde(rename = "rating.updated")]
+    RatingUpdated,
+    #[serde(rename = "rating.archived")]
+    RatingArchived,
+}
+
+impl WebhookEvent {
+    /// The name within the payloads, e.g. `entry.created`.
+    pub fn name(self) -> &'static str {
+        use self::WebhookEvent::*;
+        match self {
+            EntryCreated => "entry.created",
+            EntryUpdated => "entry.updated",
+            EntryArchived => "entry.archived",
+            EventCreated => "event.created",
+            EventUpdated => "event.updated",
+            EventArchived => "event.archived",
+            RatingCreated => "rating.created",
+            RatingUpdated => "rating.updated",
+            RatingArchived => "rating.archived",
+        }
+    }
+}
+
+/// A URL of an organization that receives the changes.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub id      : String,
+    pub org_id  : String,
+    pub created : u64,
+    pub url     : String,
+    /// The key for signing the payloads
+    pub secret  : String,
+    /// The subscribed changes (all if empty)
+    pub events  : Vec<WebhookEvent>,
+}
+
+/// The state of a payload that has not been delivered yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebhookDeliveryStatus {
+    /// Waiting for the next attempt to deliver it
+    Pending,
+    /// Not delivered after too many attempts
+    Failed,
+}
+
+/// A payload that is kept until it has been delivered to a webhook.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookDelivery {
+    pub id           : String,
+    pub webhook_id   : String,
+    pub created      : u64,
+    pub event        : WebhookEvent,
+    /// The JSON body of the request
+    pub payload      : String,
+    pub status       : WebhookDeliveryStatus,
+    pub attempts     : u32,
+    pub next_attempt : u64,
+    pub last_error   : Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -172,6 +406,42 @@ pub struct Organization {
     pub api_token: String,
 }
 
+/// The kind of object that is reported.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTarget {
+    Entry,
+    Rating,
+    Comment,
+}
+
+/// How a report has been resolved.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportResolution {
+    /// Nothing has been changed
+    Dismissed,
+    /// The reported entry has been archived
+    Archived,
+    /// The reported rating or comment has been deleted
+    Deleted,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub id          : String,
+    pub created     : u64,
+    pub target      : ReportTarget,
+    pub target_id   : String,
+    pub reason      : String,
+    /// The username of the reporter (if not anonymous)
+    pub reported_by : Option<String>,
+    pub resolution  : Option<ReportResolution>,
+    /// The username of the scout that resolved the report
+    pub resolved_by : Option<String>,
+}
+
 #[cfg(test)]
 pub trait Builder {
     type Build;
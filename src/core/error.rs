@@ -40,6 +40,9 @@ quick_error! {
         EmailNotConfirmed {
             description("Email not confirmed")
         }
+        LoginLocked {
+            description("Too many failed login attempts")
+        }
         Forbidden{
             description("This is not allowed")
         }
@@ -55,6 +58,48 @@ quick_error! {
         CreatorEmail{
             description("Missing the email of the creator")
         }
+        SearchKind{
+            description("Unsupported kind of search results")
+        }
+        RatingContext{
+            description("Invalid rating context")
+        }
+        ParentComment{
+            description("The parent comment belongs to another rating")
+        }
+        EmptyReason{
+            description("Empty reason")
+        }
+        EmptyName{
+            description("Empty name")
+        }
+        ReportResolution{
+            description("The resolution does not fit the reported object")
+        }
+        ReportResolved{
+            description("The report has already been resolved")
+        }
+        Spam{
+            description("The submission looks like spam")
+        }
+        UnsubscribeToken{
+            description("Invalid unsubscribe token")
+        }
+        RevertToken{
+            description("Invalid revert token")
+        }
+        WebhookSecret{
+            description("Invalid webhook secret")
+        }
+        MailStatus{
+            description("Invalid mail status")
+        }
+        Language{
+            description("Unsupported language")
+        }
+        TooManySubscriptions{
+            description("Too many subscriptions")
+        }
     }
 }
 
@@ -0,0 +1,246 @@
+use crate::core::{
+    prelude::*,
+    util::{unsubscribe, validate},
+};
+use uuid::Uuid;
+
+/// The maximum number of subscriptions per user.
+pub const MAX_BBOX_SUBSCRIPTIONS: usize = 20;
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewBboxSubscription {
+    pub name           : String,
+    pub south_west_lat : f64,
+    pub south_west_lng : f64,
+    pub north_east_lat : f64,
+    pub north_east_lng : f64,
+    #[serde(default)]
+    pub categories     : Vec<String>,
+    #[serde(default)]
+    pub tags           : Vec<String>,
+    #[serde(default)]
+    pub changes        : Vec<ChangeKind>,
+    #[serde(default)]
+    pub delivery       : Delivery,
+}
+
+/// A change that subscribers of the area might be notified about.
+#[derive(Debug, Clone)]
+pub struct Change<'a> {
+    pub kind: ChangeKind,
+    /// The ID of the changed entry or event
+    pub id: &'a str,
+    /// The title of the changed entry or event
+    pub title: &'a str,
+    pub categories: &'a [String],
+    pub tags: &'a [String],
+}
+
+/// Checks the filters of the subscription.
+/// Empty filters match any change.
+pub fn subscription_matches(s: &BboxSubscription, c: &Change) -> bool {
+    (s.changes.is_empty() || s.changes.contains(&c.kind))
+        && (s.categories.is_empty() || s.categories.iter().any(|x| c.categories.contains(x)))
+        && (s.tags.is_empty() || s.tags.iter().any(|x| c.tags.contains(x)))
+}
+
+/// Adds a named subscription without touching
+/// the other subscriptions of the user.
+pub fn create_bbox_subscription<D: Db>(
+    db: &mut D,
+    username: &str,
+    s: NewBboxSubscription,
+) -> Result<String> {
+    let name = s.name.trim();
+    if name.is_empty() {
+        return Err(Error::Parameter(ParameterError::EmptyName));
+    }
+    let bbox = Bbox {
+        south_west: Coordinate {
+            lat: s.south_west_lat,
+            lng: s.south_west_lng,
+        },
+        north_east: Coordinate {
+            lat: s.north_east_lat,
+            lng: s.north_east_lng,
+        },
+    };
+    validate::bbox(&bbox)?;
+    if db.bbox_subscriptions_by_username(username)?.len() >= MAX_BBOX_SUBSCRIPTIONS {
+        return Err(Error::Parameter(ParameterError::TooManySubscriptions));
+    }
+    let mut tags: Vec<_> = s.tags.into_iter().map(|t| t.replace("#", "")).collect();
+    tags.sort();
+    tags.dedup();
+    let id = Uuid::new_v4().to_simple_ref().to_string();
+    db.create_bbox_subscription(&BboxSubscription {
+        id: id.clone(),
+        name: Some(name.into()),
+        bbox,
+        username: username.into(),
+        categories: s.categories,
+        tags,
+        changes: s.changes,
+        delivery: s.delivery,
+    })?;
+    Ok(id)
+}
+
+pub fn delete_bbox_subscription<D: Db>(db: &mut D, username: &str, id: &str) -> Result<()> {
+    let s = db.get_bbox_subscription(id)?;
+    if s.username != username {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    db.delete_bbox_subscription(id)?;
+    Ok(())
+}
+
+/// Deletes the subscription of a signed unsubscribe token.
+/// Unsubscribing twice is not an error.
+pub fn unsubscribe_with_token<D: Db>(db: &mut D, secret: &str, token: &str) -> Result<()> {
+    let id = unsubscribe::verify(secret, token)
+        .ok_or_else(|| Error::Parameter(ParameterError::UnsubscribeToken))?;
+    db.delete_bbox_subscription(id)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{get_bbox_subscriptions, subscribe_to_bbox, tests::MockDb};
+    use super::*;
+
+    fn new_subscription(name: &str, lat: f64) -> NewBboxSubscription {
+        NewBboxSubscription {
+            name: name.into(),
+            south_west_lat: lat,
+            south_west_lng: 0.0,
+            north_east_lat: lat + 1.0,
+            north_east_lng: 1.0,
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            delivery: Delivery::Instant,
+        }
+    }
+
+    #[test]
+    fn create_multiple_named_subscriptions() {
+        let mut db = MockDb::new();
+        let home = create_bbox_subscription(&mut db, "foo", new_subscription("Home", 0.0)).unwrap();
+        create_bbox_subscription(&mut db, "foo", new_subscription("Work", 5.0)).unwrap();
+        assert!(create_bbox_subscription(&mut db, "foo", new_subscription(" ", 5.0)).is_err());
+        assert!(create_bbox_subscription(&mut db, "foo", new_subscription("x", 100.0)).is_err());
+
+        // the legacy subscription only replaces unnamed subscriptions
+        let bbox = [
+            Coordinate { lat: 2.0, lng: 0.0 },
+            Coordinate { lat: 3.0, lng: 1.0 },
+        ];
+        subscribe_to_bbox(&bbox, "foo", &mut db).unwrap();
+        subscribe_to_bbox(&bbox, "foo", &mut db).unwrap();
+        let subs = get_bbox_subscriptions("foo", &db).unwrap();
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs.iter().filter(|s| s.name.is_none()).count(), 1);
+        assert!(subs
+            .iter()
+            .any(|s| s.id == home && s.name == Some("Home".into())));
+    }
+
+    #[test]
+    fn delete_own_subscriptions_only() {
+        let mut db = MockDb::new();
+        let id = create_bbox_subscription(&mut db, "foo", new_subscription("Home", 0.0)).unwrap();
+        match delete_bbox_subscription(&mut db, "bar", &id) {
+            Err(Error::Parameter(ParameterError::Forbidden)) => {}
+            _ => panic!("only the owner can delete a subscription"),
+        }
+        delete_bbox_subscription(&mut db, "foo", &id).unwrap();
+        assert!(db.bbox_subscriptions.is_empty());
+        match delete_bbox_subscription(&mut db, "foo", &id) {
+            Err(Error::Repo(RepoError::NotFound)) => {}
+            _ => panic!("not found expected"),
+        }
+    }
+
+    #[test]
+    fn limit_subscriptions_per_user() {
+        let mut db = MockDb::new();
+        for i in 0..MAX_BBOX_SUBSCRIPTIONS {
+            create_bbox_subscription(&mut db, "foo", new_subscription(&i.to_string(), 0.0))
+                .unwrap();
+        }
+        match create_bbox_subscription(&mut db, "foo", new_subscription("x", 0.0)) {
+            Err(Error::Parameter(ParameterError::TooManySubscriptions)) => {}
+            _ => panic!("too many subscriptions expected"),
+        }
+        assert!(create_bbox_subscription(&mut db, "bar", new_subscription("x", 0.0)).is_ok());
+    }
+
+    #[test]
+    fn filter_subscriptions() {
+        let mut db = MockDb::new();
+        let mut s = new_subscription("Home", 0.0);
+        s.categories = vec!["c1".into()];
+        s.tags = vec!["#bio".into(), "fair".into(), "bio".into()];
+        s.changes = vec![ChangeKind::NewEntry, ChangeKind::UpdatedEntry];
+        create_bbox_subscription(&mut db, "foo", s).unwrap();
+        let s = &db.bbox_subscriptions[0];
+        assert_eq!(s.tags, vec!["bio", "fair"]);
+
+        let categories = vec!["c2".into(), "c1".into()];
+        let tags = vec!["bio".into()];
+        let change = |kind| Change {
+            kind,
+            id: "e",
+            title: "Foo",
+            categories: &categories,
+            tags: &tags,
+        };
+        assert!(subscription_matches(s, &change(ChangeKind::NewEntry)));
+        assert!(subscription_matches(s, &change(ChangeKind::UpdatedEntry)));
+        assert!(!subscription_matches(s, &change(ChangeKind::NewRating)));
+        let no_tags = Change {
+            kind: ChangeKind::NewEntry,
+            id: "e",
+            title: "Foo",
+            categories: &categories,
+            tags: &[],
+        };
+        assert!(!subscription_matches(s, &no_tags));
+        let other_category = Change {
+            kind: ChangeKind::NewEntry,
+            id: "e",
+            title: "Foo",
+            categories: &["c2".into()],
+            tags: &tags,
+        };
+        assert!(!subscription_matches(s, &other_category));
+
+        let legacy = BboxSubscription {
+            name: None,
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            ..s.clone()
+        };
+        assert!(subscription_matches(&legacy, &change(ChangeKind::NewEvent)));
+    }
+
+    #[test]
+    fn unsubscribe_without_login() {
+        let mut db = MockDb::new();
+        let id = create_bbox_subscription(&mut db, "foo", new_subscription("Home", 0.0)).unwrap();
+        create_bbox_subscription(&mut db, "foo", new_subscription("Work", 5.0)).unwrap();
+        let token = unsubscribe::token("secret", &id);
+        match unsubscribe_with_token(&mut db, "other", &token) {
+            Err(Error::Parameter(ParameterError::UnsubscribeToken)) => {}
+            _ => panic!("tokens of other secrets must be rejected"),
+        }
+        assert_eq!(db.bbox_subscriptions.len(), 2);
+        unsubscribe_with_token(&mut db, "secret", &token).unwrap();
+        assert_eq!(db.bbox_subscriptions.len(), 1);
+        assert!(db.bbox_subscriptions.iter().all(|s| s.id != id));
+        unsubscribe_with_token(&mut db, "secret", &token).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use super::{NewEntry, NewEvent};
+use crate::core::{
+    prelude::*,
+    util::{
+        geo,
+        spam::{SpamFilter, Submission, Verdict},
+        text,
+    },
+};
+
+/// Entries within this distance (in degrees) are
+/// compared with the title of a new entry.
+const DUPLICATE_TITLE_DISTANCE: f64 = 0.1;
+
+/// Rejects spam and returns `Verdict::Suspicious`
+/// if the entry should be reviewed before it is published.
+pub fn check_new_entry_for_spam<D: Db>(
+    db: &D,
+    filter: &SpamFilter,
+    e: &NewEntry,
+    recent_submissions: usize,
+) -> Result<Verdict> {
+    let title = text::fold(&e.title);
+    let bbox = Bbox {
+        south_west: Coordinate {
+            lat: e.lat - DUPLICATE_TITLE_DISTANCE,
+            lng: e.lng - DUPLICATE_TITLE_DISTANCE,
+        },
+        north_east: Coordinate {
+            lat: e.lat + DUPLICATE_TITLE_DISTANCE,
+            lng: e.lng + DUPLICATE_TITLE_DISTANCE,
+        },
+    };
+    let pending_entries = db
+        .pending_entries()?
+        .into_iter()
+        .filter(|x| geo::is_in_bbox(&x.location.lat, &x.location.lng, &bbox));
+    let duplicate_title = db
+        .get_entries_by_bbox(&bbox)?
+        .into_iter()
+        .chain(pending_entries)
+        .any(|x| text::fold(&x.title) == title);
+    let s = Submission {
+        title: &e.title,
+        description: &e.description,
+        homepage: e.homepage.as_ref().map(String::as_str),
+        recent_submissions,
+        duplicate_title,
+    };
+    match filter.check(&s) {
+        Verdict::Spam => Err(ParameterError::Spam.into()),
+        verdict => Ok(verdict),
+    }
+}
+
+/// Events can't be reviewed, so suspicious events are rejected, too.
+/// Recurring events usually share their title and are not scored.
+/// Events are only created with the API token of an organization,
+/// so bulk imports are not scored by the submission rate either.
+pub fn check_new_event_for_spam(filter: &SpamFilter, e: &NewEvent) -> Result<()> {
+    let s = Submission {
+        title: &e.title,
+        description: e.description.as_ref().map(String::as_str).unwrap_or(""),
+        homepage: e.homepage.as_ref().map(String::as_str),
+        recent_submissions: 0,
+        duplicate_title: false,
+    };
+    match filter.check(&s) {
+        Verdict::Ham => Ok(()),
+        _ => Err(ParameterError::Spam.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{create_new_entry, tests::MockDb};
+    use super::*;
+    use crate::core::util::spam::{Blacklist, DuplicateTitle, LinkCount};
+
+    fn new_entry(title: &str, description: &str) -> NewEntry {
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        NewEntry {
+            title          : title.into(),
+            description    : description.into(),
+            lat            : 0.0,
+            lng            : 0.0,
+            street         : None,
+            zip            : None,
+            city           : None,
+            country        : None,
+            email          : None,
+            telephone      : None,
+            homepage       : None,
+            categories     : vec![],
+            tags           : vec![],
+            license        : "CC0-1.0".into(),
+            image_url      : None,
+            image_link_url : None,
+        }
+    }
+
+    fn filter() -> SpamFilter {
+        SpamFilter::new(1, 2)
+            .add_rule(LinkCount { max_links: 1 })
+            .add_rule(DuplicateTitle)
+            .add_rule(Blacklist {
+                domains: vec!["spam.com".into()],
+                words: vec![],
+            })
+    }
+
+    #[test]
+    fn check_entries() {
+        let mut db = MockDb::new();
+        let e = new_entry("Café", "see https://cafe.org");
+        assert_eq!(
+            check_new_entry_for_spam(&db, &filter(), &e, 1).unwrap(),
+            Verdict::Ham
+        );
+        create_new_entry(&mut db, e).unwrap();
+
+        let mut e = new_entry("cafe", "see https://cafe.org");
+        assert_eq!(
+            check_new_entry_for_spam(&db, &filter(), &e, 1).unwrap(),
+            Verdict::Suspicious
+        );
+
+        // only entries nearby are compared
+        e.lat = 10.0;
+        assert_eq!(
+            check_new_entry_for_spam(&db, &filter(), &e, 1).unwrap(),
+            Verdict::Ham
+        );
+
+        let e = new_entry("foo", "www.spam.com www.spam.com/buy");
+        match check_new_entry_for_spam(&db, &filter(), &e, 1).err() {
+            Some(Error::Parameter(ParameterError::Spam)) => {}
+            _ => panic!("expected spam"),
+        }
+    }
+
+    #[test]
+    fn reject_suspicious_events() {
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let mut e = NewEvent {
+            title       : "foo".into(),
+            description : Some("see https://foo.org".into()),
+            start       : 0,
+            end         : None,
+            lat         : None,
+            lng         : None,
+            street      : None,
+            zip         : None,
+            city        : None,
+            country     : None,
+            email       : None,
+            telephone   : None,
+            homepage    : None,
+            tags        : None,
+            created_by  : None,
+            token       : None,
+        };
+        assert!(check_new_event_for_spam(&filter(), &e).is_ok());
+        e.homepage = Some("https://spam.com".into());
+        assert!(check_new_event_for_spam(&filter(), &e).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::core::prelude::*;
+use chrono::*;
+use uuid::Uuid;
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewComment {
+    pub text   : String,
+    /// The ID of the comment to reply to
+    pub parent : Option<String>,
+    pub user   : Option<String>,
+}
+
+/// Adds a comment to a rating, either directly
+/// or as a reply to another comment of the rating.
+pub fn comment_rating<D: Db>(db: &mut D, rating_id: &str, c: NewComment) -> Result<String> {
+    let rating = db.get_rating(rating_id)?;
+    if c.text.is_empty() {
+        return Err(Error::Parameter(ParameterError::EmptyComment));
+    }
+    if let Some(ref parent_id) = c.parent {
+        let parent = db.get_comment(parent_id)?;
+        if parent.rating_id != rating.id {
+            return Err(Error::Parameter(ParameterError::ParentComment));
+        }
+    }
+    if let Some(ref username) = c.user {
+        db.get_user(username)?;
+    }
+    let id = Uuid::new_v4().to_simple_ref().to_string();
+    db.create_comment(Comment {
+        id: id.clone(),
+        created: Utc::now().timestamp() as u64,
+        text: c.text,
+        rating_id: rating.id,
+        parent_id: c.parent,
+        created_by: c.user,
+    })?;
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn setup() -> MockDb {
+        let mut db = MockDb::new();
+        let rating = |id: &str| Rating {
+            id: id.into(),
+            entry_id: "foo".into(),
+            created: 0,
+            updated: None,
+            title: "title".into(),
+            value: 1,
+            context: RatingContext::Fairness,
+            source: None,
+            created_by: None,
+        };
+        db.ratings = vec![rating("r1"), rating("r2")];
+        db.comments = vec![Comment {
+            id: "c".into(),
+            created: 0,
+            text: "first".into(),
+            rating_id: "r1".into(),
+            parent_id: None,
+            created_by: None,
+        }];
+        db
+    }
+
+    fn comment(text: &str, parent: Option<&str>) -> NewComment {
+        NewComment {
+            text: text.into(),
+            parent: parent.map(Into::into),
+            user: None,
+        }
+    }
+
+    #[test]
+    fn reply_to_comment() {
+        let mut db = setup();
+        let id = comment_rating(&mut db, "r1", comment("reply", Some("c"))).unwrap();
+        let reply = db.comments.iter().find(|c| c.id == id).unwrap();
+        assert_eq!(reply.rating_id, "r1");
+        assert_eq!(reply.parent_id, Some("c".into()));
+    }
+
+    #[test]
+    fn comment_without_parent() {
+        let mut db = setup();
+        comment_rating(&mut db, "r2", comment("another", None)).unwrap();
+        assert_eq!(db.comments.len(), 2);
+        assert!(db.comments[1].parent_id.is_none());
+    }
+
+    #[test]
+    fn invalid_comments() {
+        let mut db = setup();
+        assert!(comment_rating(&mut db, "r1", comment("", None)).is_err());
+        assert!(comment_rating(&mut db, "r3", comment("foo", None)).is_err());
+        assert!(comment_rating(&mut db, "r1", comment("foo", Some("x"))).is_err());
+        // the parent must belong to the same rating
+        assert!(comment_rating(&mut db, "r2", comment("foo", Some("c"))).is_err());
+        let mut c = comment("foo", None);
+        c.user = Some("unknown".into());
+        assert!(comment_rating(&mut db, "r1", c).is_err());
+        assert_eq!(db.comments.len(), 1);
+    }
+}
@@ -28,6 +28,7 @@ mod tests {
             email: "a@foo.bar".into(),
             email_confirmed: false,
             role: Role::Guest,
+            language: None,
         }];
         assert!(confirm_email_address(&mut db, "1").is_ok());
         assert_eq!(db.users[0].email_confirmed, true);
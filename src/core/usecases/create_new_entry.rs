@@ -27,6 +27,21 @@ pub struct NewEntry {
 }
 
 pub fn create_new_entry<D: Db>(db: &mut D, e: NewEntry) -> Result<String> {
+    let new_entry = prepare_new_entry(db, e)?;
+    let id = new_entry.id.clone();
+    db.create_entry(new_entry)?;
+    Ok(id)
+}
+
+/// Creates a new entry that stays hidden until a scout approves it.
+pub fn create_new_entry_for_review<D: Db>(db: &mut D, e: NewEntry) -> Result<String> {
+    let new_entry = prepare_new_entry(db, e)?;
+    let id = new_entry.id.clone();
+    db.create_pending_entry(new_entry)?;
+    Ok(id)
+}
+
+fn prepare_new_entry<D: Db>(db: &mut D, e: NewEntry) -> Result<Entry> {
     let NewEntry {
         title,
         description,
@@ -62,8 +77,7 @@ pub fn create_new_entry<D: Db>(db: &mut D, e: NewEntry) -> Result<String> {
         None
     };
     let created = Utc::now().timestamp() as u64;
-    let new_id = Uuid::new_v4().to_simple_ref().to_string();
-    let id = new_id.clone();
+    let id = Uuid::new_v4().to_simple_ref().to_string();
     let homepage = e.homepage.map(|ref url| parse_url_param(url)).transpose()?;
     let image_url = e
         .image_url
@@ -96,8 +110,7 @@ pub fn create_new_entry<D: Db>(db: &mut D, e: NewEntry) -> Result<String> {
     for t in &new_entry.tags {
         db.create_tag_if_it_does_not_exist(&Tag { id: t.clone() })?;
     }
-    db.create_entry(new_entry)?;
-    Ok(new_id)
+    Ok(new_entry)
 }
 
 #[cfg(test)]
@@ -194,4 +207,33 @@ mod tests {
         assert_eq!(mock_db.entries.len(), 1);
     }
 
+    #[test]
+    fn create_entry_for_review() {
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let x = NewEntry {
+            title       : "foo".into(),
+            description : "bar".into(),
+            lat         : 0.0,
+            lng         : 0.0,
+            street      : None,
+            zip         : None,
+            city        : None,
+            country     : None,
+            email       : None,
+            telephone   : None,
+            homepage    : None,
+            categories  : vec![],
+            tags        : vec!["foo".into()],
+            license     : "CC0-1.0".into(),
+            image_url     : None,
+            image_link_url: None,
+        };
+        let mut mock_db = MockDb::new();
+        let id = create_new_entry_for_review(&mut mock_db, x).unwrap();
+        assert!(mock_db.entries.is_empty());
+        assert_eq!(mock_db.pending_entries.len(), 1);
+        assert_eq!(mock_db.pending_entries[0].id, id);
+        assert_eq!(mock_db.tags.len(), 1);
+    }
+
 }
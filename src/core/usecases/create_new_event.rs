@@ -247,6 +247,7 @@ mod tests {
                 password: "secret".into(),
                 email_confirmed: true,
                 role: Role::User,
+                language: None,
             })
             .unwrap();
         let users = mock_db.all_users().unwrap();
@@ -10,12 +10,16 @@ pub struct NewUser {
     pub username: String,
     pub password: String,
     pub email: String,
+    pub language: Option<String>,
 }
 
 pub fn create_new_user<D: UserGateway>(db: &mut D, u: NewUser) -> Result<()> {
     validate::username(&u.username)?;
     validate::password(&u.password)?;
     validate::email(&u.email)?;
+    if let Some(ref lang) = u.language {
+        validate::language(lang)?;
+    }
     if db.get_user(&u.username).is_ok() {
         return Err(Error::Parameter(ParameterError::UserExists));
     }
@@ -26,6 +30,7 @@ pub fn create_new_user<D: UserGateway>(db: &mut D, u: NewUser) -> Result<()> {
         email: u.email,
         email_confirmed: false,
         role: Role::Guest,
+        language: u.language,
     };
     debug!(
         "Creating new user: username = {}, email = {}, ",
@@ -56,6 +61,7 @@ pub fn create_user_from_email<D: Db>(db: &mut D, email: &str) -> Result<String>
                 username,
                 password,
                 email: email.into(),
+                language: None,
             };
             create_new_user(db, u)?;
             generated_username
@@ -78,17 +84,19 @@ mod tests {
             username: "foo".into(),
             password: "bar".into(),
             email: "foo@bar.de".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
         let u = NewUser {
             username: "baz".into(),
             password: "bar".into(),
             email: "baz@bar.de".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
 
-        let (foo_username, _) = get_user(&mut db, "foo", "foo").unwrap();
-        let (baz_username, _) = get_user(&mut db, "baz", "baz").unwrap();
+        let foo_username = get_user(&mut db, "foo", "foo").unwrap().username;
+        let baz_username = get_user(&mut db, "baz", "baz").unwrap().username;
         assert_eq!(foo_username, "foo");
         assert_eq!(baz_username, "baz");
     }
@@ -100,18 +108,21 @@ mod tests {
             username: "".into(),
             password: "bar".into(),
             email: "foo@baz.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_err());
         let u = NewUser {
             username: "also&invalid".into(),
             password: "bar".into(),
             email: "foo@baz.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_err());
         let u = NewUser {
             username: "thisisvalid".into(),
             password: "very_secret".into(),
             email: "foo@baz.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
     }
@@ -123,18 +134,21 @@ mod tests {
             username: "user".into(),
             password: "".into(),
             email: "foo@baz.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_err());
         let u = NewUser {
             username: "user".into(),
             password: "not valid".into(),
             email: "foo@baz.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_err());
         let u = NewUser {
             username: "user".into(),
             password: "validpass".into(),
             email: "foo@baz.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
     }
@@ -146,18 +160,21 @@ mod tests {
             username: "user".into(),
             password: "pass".into(),
             email: "".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_err());
         let u = NewUser {
             username: "user".into(),
             password: "pass".into(),
             email: "fooo@".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_err());
         let u = NewUser {
             username: "user".into(),
             password: "pass".into(),
             email: "fooo@bar.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
     }
@@ -172,11 +189,13 @@ mod tests {
             email: "baz@foo.bar".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         }];
         let u = NewUser {
             username: "foo".into(),
             password: "pass".into(),
             email: "user@server.tld".into(),
+            language: None,
         };
         match create_new_user(&mut db, u).err().unwrap() {
             Error::Parameter(err) => {
@@ -198,6 +217,7 @@ mod tests {
             username: "user".into(),
             password: "pass".into(),
             email: "foo@bar.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
         assert_eq!(db.users[0].email_confirmed, false);
@@ -210,10 +230,25 @@ mod tests {
             username: "user".into(),
             password: "pass".into(),
             email: "foo@bar.io".into(),
+            language: None,
         };
         assert!(create_new_user(&mut db, u).is_ok());
         assert!(db.users[0].password != "pass");
         assert!(bcrypt::verify("pass", &db.users[0].password));
     }
 
+    #[test]
+    fn create_user_with_language() {
+        let mut db = MockDb::new();
+        let mut u = NewUser {
+            username: "user".into(),
+            password: "pass".into(),
+            email: "foo@bar.io".into(),
+            language: Some("english".into()),
+        };
+        assert!(create_new_user(&mut db, u.clone()).is_err());
+        u.language = Some("en".into());
+        assert!(create_new_user(&mut db, u).is_ok());
+        assert_eq!(db.users[0].language, Some("en".into()));
+    }
 }
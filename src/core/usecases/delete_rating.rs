@@ -0,0 +1,152 @@
+use crate::core::prelude::*;
+
+/// Authors are allowed to delete their own
+/// contributions whereas scouts and admins
+/// are allowed to delete everything.
+fn check_deletion<D: Db>(db: &D, created_by: &Option<String>, username: &str) -> Result<()> {
+    if created_by.as_ref().map(String::as_str) == Some(username) {
+        return Ok(());
+    }
+    let user = db.get_user(username)?;
+    if user.role < Role::Scout {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    Ok(())
+}
+
+/// Deletes a rating including all of its comments.
+pub fn delete_rating<D: Db>(db: &mut D, username: &str, id: &str) -> Result<()> {
+    let rating = db.get_rating(id)?;
+    check_deletion(db, &rating.created_by, username)?;
+    db.delete_rating(id)?;
+    Ok(())
+}
+
+/// Deletes a comment including all replies to it.
+pub fn delete_comment<D: Db>(db: &mut D, username: &str, id: &str) -> Result<()> {
+    let comment = db.get_comment(id)?;
+    check_deletion(db, &comment.created_by, username)?;
+    let comments: Vec<_> = db
+        .all_comments()?
+        .into_iter()
+        .filter(|c| c.rating_id == comment.rating_id)
+        .collect();
+    let mut ids = vec![comment.id];
+    let mut i = 0;
+    while i < ids.len() {
+        for c in &comments {
+            if c.parent_id.as_ref() == Some(&ids[i]) {
+                ids.push(c.id.clone());
+            }
+        }
+        i += 1;
+    }
+    // delete the replies first
+    ids.reverse();
+    db.delete_comments(&ids)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn user(username: &str, role: Role) -> User {
+        User {
+            id: username.into(),
+            username: username.into(),
+            password: "secret".into(),
+            email: format!("{}@example.com", username),
+            email_confirmed: true,
+            role,
+            language: None,
+        }
+    }
+
+    fn setup() -> MockDb {
+        let mut db = MockDb::new();
+        db.users = vec![
+            user("alice", Role::User),
+            user("bob", Role::User),
+            user("scout", Role::Scout),
+        ];
+        db.ratings = vec![Rating {
+            id: "r".into(),
+            entry_id: "foo".into(),
+            created: 0,
+            updated: None,
+            title: "title".into(),
+            value: 1,
+            context: RatingContext::Fairness,
+            source: None,
+            created_by: Some("alice".into()),
+        }];
+        db.comments = vec![
+            Comment {
+                id: "c1".into(),
+                created: 0,
+                text: "good".into(),
+                rating_id: "r".into(),
+                parent_id: None,
+                created_by: Some("alice".into()),
+            },
+            Comment {
+                id: "c2".into(),
+                created: 0,
+                text: "bad".into(),
+                rating_id: "r".into(),
+                parent_id: None,
+                created_by: None,
+            },
+        ];
+        db
+    }
+
+    #[test]
+    fn delete_own_rating() {
+        let mut db = setup();
+        assert!(delete_rating(&mut db, "bob", "r").is_err());
+        assert_eq!(db.ratings.len(), 1);
+        delete_rating(&mut db, "alice", "r").unwrap();
+        assert!(db.ratings.is_empty());
+        assert!(db.comments.is_empty());
+    }
+
+    #[test]
+    fn delete_rating_as_scout() {
+        let mut db = setup();
+        delete_rating(&mut db, "scout", "r").unwrap();
+        assert!(db.ratings.is_empty());
+        assert!(delete_rating(&mut db, "scout", "r").is_err());
+    }
+
+    #[test]
+    fn delete_comments() {
+        let mut db = setup();
+        assert!(delete_comment(&mut db, "alice", "c2").is_err());
+        delete_comment(&mut db, "alice", "c1").unwrap();
+        delete_comment(&mut db, "scout", "c2").unwrap();
+        assert!(db.comments.is_empty());
+        assert_eq!(db.ratings.len(), 1);
+    }
+
+    #[test]
+    fn delete_comment_with_replies() {
+        let mut db = setup();
+        let reply = |id: &str, parent_id: &str| Comment {
+            id: id.into(),
+            created: 0,
+            text: "reply".into(),
+            rating_id: "r".into(),
+            parent_id: Some(parent_id.into()),
+            created_by: None,
+        };
+        db.comments.push(reply("c3", "c1"));
+        db.comments.push(reply("c4", "c3"));
+        db.comments.push(reply("c5", "c2"));
+        delete_comment(&mut db, "alice", "c1").unwrap();
+        let ids: Vec<_> = db.comments.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["c2", "c5"]);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::core::{prelude::*, util::unsubscribe};
+use chrono::*;
+
+const CONTACT_PREFIX: &str = "contact:";
+const REVERT_PREFIX: &str = "revert:";
+
+/// The number of seconds the links to revert a change are valid.
+pub const REVERT_TOKEN_LIFETIME: u64 = 14 * 24 * 60 * 60;
+
+/// A field of an entry that has been changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedField {
+    pub name: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+fn address_field(e: &Entry, f: &Fn(&Address) -> &Option<String>) -> String {
+    e.location
+        .address
+        .as_ref()
+        .and_then(|a| f(a).clone())
+        .unwrap_or_default()
+}
+
+fn contact_field(e: &Entry, f: &Fn(&Contact) -> &Option<String>) -> String {
+    e.contact
+        .as_ref()
+        .and_then(|c| f(c).clone())
+        .unwrap_or_default()
+}
+
+fn category_names(ids: &[String], categories: &[Category]) -> String {
+    ids.iter()
+        .map(|id| {
+            categories
+                .iter()
+                .find(|c| c.id == *id)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| id.clone())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Compares two versions of an entry. Categories are
+/// listed by their names.
+pub fn changed_fields(old: &Entry, new: &Entry, categories: &[Category]) -> Vec<ChangedField> {
+    let fields = vec![
+        ("title", old.title.clone(), new.title.clone()),
+        (
+            "description",
+            old.description.clone(),
+            new.description.clone(),
+        ),
+        (
+            "location",
+            format!("{}, {}", old.location.lat, old.location.lng),
+            format!("{}, {}", new.location.lat, new.location.lng),
+        ),
+        (
+            "street",
+            address_field(old, &|a| &a.street),
+            address_field(new, &|a| &a.street),
+        ),
+        (
+            "zip",
+            address_field(old, &|a| &a.zip),
+            address_field(new, &|a| &a.zip),
+        ),
+        (
+            "city",
+            address_field(old, &|a| &a.city),
+            address_field(new, &|a| &a.city),
+        ),
+        (
+            "country",
+            address_field(old, &|a| &a.country),
+            address_field(new, &|a| &a.country),
+        ),
+        (
+            "email",
+            contact_field(old, &|c| &c.email),
+            contact_field(new, &|c| &c.email),
+        ),
+        (
+            "telephone",
+            contact_field(old, &|c| &c.telephone),
+            contact_field(new, &|c| &c.telephone),
+        ),
+        (
+            "homepage",
+            old.homepage.clone().unwrap_or_default(),
+            new.homepage.clone().unwrap_or_default(),
+        ),
+        (
+            "categories",
+            category_names(&old.categories, categories),
+            category_names(&new.categories, categories),
+        ),
+        ("tags", old.tags.join(", "), new.tags.join(", ")),
+        (
+            "image_url",
+            old.image_url.clone().unwrap_or_default(),
+            new.image_url.clone().unwrap_or_default(),
+        ),
+        (
+            "image_link_url",
+            old.image_link_url.clone().unwrap_or_default(),
+            new.image_link_url.clone().unwrap_or_default(),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(name, old, new)| ChangedField { name, old, new })
+        .collect()
+}
+
+/// Returns the contact addresses of both versions of
+/// an entry that did not opt out of notifications.
+pub fn entry_contacts_to_notify(db: &Db, old: &Entry, new: &Entry) -> Result<Vec<String>> {
+    let mut emails: Vec<String> = vec![];
+    for e in &[old, new] {
+        let email = contact_field(e, &|c| &c.email);
+        let email = email.trim();
+        if email.is_empty() || emails.iter().any(|x| x.eq_ignore_ascii_case(email)) {
+            continue;
+        }
+        if !db.is_contact_opted_out(email)? {
+            emails.push(email.into());
+        }
+    }
+    Ok(emails)
+}
+
+/// Creates a token that allows the contact of an entry
+/// to opt out of all notifications about changes.
+pub fn contact_opt_out_token(secret: &str, email: &str) -> String {
+    unsubscribe::token(
+        &unsubscribe::derive_key(secret, CONTACT_PREFIX),
+        &format!("{}{}", CONTACT_PREFIX, email.to_lowercase()),
+    )
+}
+
+/// Stops notifying the contact address of a signed opt-out token.
+/// Opting out twice is not an error.
+pub fn opt_out_contact_with_token<D: Db>(db: &mut D, secret: &str, token: &str) -> Result<()> {
+    let key = unsubscribe::derive_key(secret, CONTACT_PREFIX);
+    let email = unsubscribe::verify(&key, token)
+        .filter(|p| p.starts_with(CONTACT_PREFIX))
+        .map(|p| &p[CONTACT_PREFIX.len()..])
+        .ok_or_else(|| Error::Parameter(ParameterError::UnsubscribeToken))?;
+    if !db.is_contact_opted_out(email)? {
+        db.create_contact_opt_out(email)?;
+    }
+    Ok(())
+}
+
+/// Creates a token that allows to revert the given version
+/// of an entry without logging in. It expires after
+/// `REVERT_TOKEN_LIFETIME` seconds.
+pub fn revert_token(secret: &str, entry_id: &str, version: u64) -> String {
+    let expires = Utc::now().timestamp() as u64 + REVERT_TOKEN_LIFETIME;
+    revert_token_until(secret, entry_id, version, expires)
+}
+
+fn revert_token_until(secret: &str, entry_id: &str, version: u64, expires: u64) -> String {
+    unsubscribe::token(
+        &unsubscribe::derive_key(secret, REVERT_PREFIX),
+        &format!("{}{}:{}:{}", REVERT_PREFIX, entry_id, version, expires),
+    )
+}
+
+/// Returns the ID, version and expiry of a revert token.
+fn parse_revert_payload(payload: &str) -> Option<(&str, u64, u64)> {
+    if !payload.starts_with(REVERT_PREFIX) {
+        return None;
+    }
+    let mut parts = payload[REVERT_PREFIX.len()..].rsplitn(3, ':');
+    let expires = parts.next()?.parse().ok()?;
+    let version = parts.next()?.parse().ok()?;
+    let id = parts.next()?;
+    Some((id, version, expires))
+}
+
+/// Restores the previous version of an entry as a new version.
+/// The token is only valid until it expires and as long as
+/// the reverted version is the current version of the entry.
+pub fn revert_entry_with_token<D: Db>(
+    db: &mut D,
+    secret: &str,
+    id: &str,
+    token: &str,
+) -> Result<Entry> {
+    let now = Utc::now().timestamp() as u64;
+    let key = unsubscribe::derive_key(secret, REVERT_PREFIX);
+    let version = unsubscribe::verify(&key, token)
+        .and_then(parse_revert_payload)
+        .filter(|(token_id, _, expires)| *token_id == id && now < *expires)
+        .map(|(_, version, _)| version)
+        .ok_or_else(|| Error::Parameter(ParameterError::RevertToken))?;
+    let current = db.get_entry(id)?;
+    if current.version != version || version == 0 {
+        return Err(Error::Parameter(ParameterError::RevertToken));
+    }
+    let previous = db.get_entry_version(id, version - 1)?;
+    let reverted = Entry {
+        version: current.version + 1,
+        created: Utc::now().timestamp() as u64,
+        license: current.license,
+        ..previous
+    };
+    for t in &reverted.tags {
+        db.create_tag_if_it_does_not_exist(&Tag { id: t.clone() })?;
+    }
+    db.update_entry(&reverted)?;
+    Ok(reverted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn entry(version: u64, title: &str, email: Option<&str>) -> Entry {
+        let mut e = Entry::build()
+            .id("e1")
+            .version(version)
+            .title(title)
+            .description("desc")
+            .categories(vec!["c1"])
+            .finish();
+        e.contact = Some(Contact {
+            email: email.map(Into::into),
+            telephone: None,
+        });
+        e
+    }
+
+    #[test]
+    fn list_changed_fields() {
+        let old = entry(1, "foo", Some("old@example.org"));
+        let mut new = entry(2, "bar", Some("new@example.org"));
+        new.categories = vec!["c2".into()];
+        let categories = vec![Category {
+            id: "c1".into(),
+            name: "Initiative".into(),
+            created: 0,
+            version: 0,
+        }];
+        let changes = changed_fields(&old, &new, &categories);
+        assert_eq!(
+            changes,
+            vec![
+                ChangedField {
+                    name: "title",
+                    old: "foo".into(),
+                    new: "bar".into(),
+                },
+                ChangedField {
+                    name: "email",
+                    old: "old@example.org".into(),
+                    new: "new@example.org".into(),
+                },
+                ChangedField {
+                    name: "categories",
+                    old: "Initiative".into(),
+                    new: "c2".into(),
+                },
+            ]
+        );
+        assert!(changed_fields(&old, &old, &categories).is_empty());
+    }
+
+    #[test]
+    fn notify_old_and_new_contacts_unless_they_opted_out() {
+        let mut db = MockDb::new();
+        let old = entry(1, "foo", Some("old@example.org"));
+        let new = entry(2, "foo", Some("new@example.org"));
+        assert_eq!(
+            entry_contacts_to_notify(&db, &old, &new).unwrap(),
+            vec!["old@example.org", "new@example.org"]
+        );
+        assert_eq!(
+            entry_contacts_to_notify(&db, &old, &old).unwrap(),
+            vec!["old@example.org"]
+        );
+        let token = contact_opt_out_token("secret", "Old@example.org");
+        assert!(opt_out_contact_with_token(&mut db, "other", &token).is_err());
+        opt_out_contact_with_token(&mut db, "secret", &token).unwrap();
+        opt_out_contact_with_token(&mut db, "secret", &token).unwrap();
+        assert_eq!(db.contact_opt_outs.len(), 1);
+        assert_eq!(
+            entry_contacts_to_notify(&db, &old, &new).unwrap(),
+            vec!["new@example.org"]
+        );
+        let revert = revert_token("secret", "e1", 2);
+        assert!(opt_out_contact_with_token(&mut db, "secret", &revert).is_err());
+    }
+
+    #[test]
+    fn revert_the_changed_version() {
+        let mut db = MockDb::new();
+        db.entries = vec![entry(1, "foo", None)];
+        db.update_entry(&entry(2, "spam", None)).unwrap();
+        let token = revert_token("secret", "e1", 2);
+        assert!(revert_entry_with_token(&mut db, "other", "e1", &token).is_err());
+        assert!(revert_entry_with_token(&mut db, "secret", "e2", &token).is_err());
+        let reverted = revert_entry_with_token(&mut db, "secret", "e1", &token).unwrap();
+        assert_eq!(reverted.version, 3);
+        assert_eq!(reverted.title, "foo");
+        assert_eq!(db.get_entry("e1").unwrap().title, "foo");
+        // The link can't be used twice or after other changes
+        match revert_entry_with_token(&mut db, "secret", "e1", &token) {
+            Err(Error::Parameter(ParameterError::RevertToken)) => {}
+            x => panic!("unexpected result: {:?}", x),
+        }
+        let opt_out = contact_opt_out_token("secret", "e1:2");
+        assert!(revert_entry_with_token(&mut db, "secret", "e1", &opt_out).is_err());
+        // Revert links are signed with their own key
+        assert!(unsubscribe::verify("secret", &revert_token("secret", "e1", 3)).is_none());
+    }
+
+    #[test]
+    fn reject_expired_revert_tokens() {
+        let mut db = MockDb::new();
+        db.entries = vec![entry(1, "foo", None)];
+        db.update_entry(&entry(2, "spam", None)).unwrap();
+        let now = Utc::now().timestamp() as u64;
+        let expired = revert_token_until("secret", "e1", 2, now - 1);
+        match revert_entry_with_token(&mut db, "secret", "e1", &expired) {
+            Err(Error::Parameter(ParameterError::RevertToken)) => {}
+            x => panic!("unexpected result: {:?}", x),
+        }
+        let valid = revert_token_until("secret", "e1", 2, now + 60);
+        assert!(revert_entry_with_token(&mut db, "secret", "e1", &valid).is_ok());
+    }
+}
@@ -0,0 +1,163 @@
+use super::{get_bbox_subscriptions, login_history, user_tokens};
+use crate::core::prelude::*;
+
+/// All personal data that is stored about a user.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone)]
+pub struct UserData {
+    pub user               : User,
+    pub login_history      : LoginHistory,
+    pub bbox_subscriptions : Vec<BboxSubscription>,
+    pub tokens             : Vec<UserToken>,
+    pub events             : Vec<Event>,
+    pub ratings            : Vec<Rating>,
+    pub comments           : Vec<Comment>,
+}
+
+pub fn export_user_data<D: Db>(
+    db: &D,
+    logged_in_username: &str,
+    username: &str,
+) -> Result<UserData> {
+    if logged_in_username != username {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    let is_author =
+        |created_by: &Option<String>| created_by.as_ref().map(String::as_str) == Some(username);
+    let user = db.get_user(username)?;
+    let login_history = login_history(db, username)?;
+    let bbox_subscriptions = get_bbox_subscriptions(username, db)?;
+    let tokens = user_tokens(db, username)?;
+    let events = db
+        .all_events()?
+        .into_iter()
+        .filter(|e| is_author(&e.created_by))
+        .collect();
+    let ratings = db
+        .all_ratings()?
+        .into_iter()
+        .filter(|r| is_author(&r.created_by))
+        .collect();
+    let comments = db
+        .all_comments()?
+        .into_iter()
+        .filter(|c| is_author(&c.created_by))
+        .collect();
+    Ok(UserData {
+        user,
+        login_history,
+        bbox_subscriptions,
+        tokens,
+        events,
+        ratings,
+        comments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{delete_user, tests::MockDb};
+    use super::*;
+
+    fn create_user(db: &mut MockDb, username: &str) {
+        db.create_user(User {
+            id: username.into(),
+            username: username.into(),
+            password: "secret".into(),
+            email: format!("{}@bar", username),
+            email_confirmed: true,
+            role: Role::User,
+            language: None,
+        })
+        .unwrap();
+    }
+
+    fn rating(id: &str, created_by: Option<&str>) -> Rating {
+        Rating {
+            id: id.into(),
+            entry_id: "e".into(),
+            created: 0,
+            updated: None,
+            title: "t".into(),
+            value: 1,
+            context: RatingContext::Fairness,
+            source: None,
+            created_by: created_by.map(Into::into),
+        }
+    }
+
+    fn comment(id: &str, created_by: Option<&str>) -> Comment {
+        Comment {
+            id: id.into(),
+            created: 0,
+            text: "t".into(),
+            rating_id: "r1".into(),
+            parent_id: None,
+            created_by: created_by.map(Into::into),
+        }
+    }
+
+    fn subscription(id: &str, username: &str) -> BboxSubscription {
+        BboxSubscription {
+            id: id.into(),
+            name: None,
+            bbox: Bbox {
+                south_west: Coordinate { lat: 0.0, lng: 0.0 },
+                north_east: Coordinate { lat: 1.0, lng: 1.0 },
+            },
+            username: username.into(),
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            delivery: Delivery::Instant,
+        }
+    }
+
+    fn setup() -> MockDb {
+        let mut db = MockDb::new();
+        create_user(&mut db, "foo");
+        create_user(&mut db, "bar");
+        db.create_rating(rating("r1", Some("foo"))).unwrap();
+        db.create_rating(rating("r2", Some("bar"))).unwrap();
+        db.create_rating(rating("r3", None)).unwrap();
+        db.create_comment(comment("c1", Some("foo"))).unwrap();
+        db.create_comment(comment("c2", Some("bar"))).unwrap();
+        db.create_bbox_subscription(&subscription("s1", "foo"))
+            .unwrap();
+        db.create_bbox_subscription(&subscription("s2", "bar"))
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn export_personal_data() {
+        let db = setup();
+        let data = export_user_data(&db, "foo", "foo").unwrap();
+        assert_eq!(data.user.username, "foo");
+        assert_eq!(data.bbox_subscriptions.len(), 1);
+        assert_eq!(data.bbox_subscriptions[0].id, "s1");
+        assert_eq!(data.ratings.len(), 1);
+        assert_eq!(data.ratings[0].id, "r1");
+        assert_eq!(data.comments.len(), 1);
+        assert_eq!(data.comments[0].id, "c1");
+        assert!(data.events.is_empty());
+        match export_user_data(&db, "bar", "foo") {
+            Err(Error::Parameter(ParameterError::Forbidden)) => {}
+            _ => panic!("only the user can export the data"),
+        }
+    }
+
+    #[test]
+    fn anonymise_contributions_of_deleted_users() {
+        let mut db = setup();
+        delete_user(&mut db, "foo", "foo").unwrap();
+        assert!(db.get_user("foo").is_err());
+        assert_eq!(db.bbox_subscriptions.len(), 1);
+        assert_eq!(db.bbox_subscriptions[0].username, "bar");
+        assert_eq!(db.ratings.len(), 3);
+        assert!(db.get_rating("r1").unwrap().created_by.is_none());
+        assert_eq!(db.get_rating("r2").unwrap().created_by.unwrap(), "bar");
+        assert!(db.get_comment("c1").unwrap().created_by.is_none());
+        assert_eq!(db.get_comment("c2").unwrap().created_by.unwrap(), "bar");
+    }
+}
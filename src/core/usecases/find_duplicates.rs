@@ -1,4 +1,7 @@
-use crate::core::{prelude::*, util::geo};
+use crate::core::{
+    prelude::*,
+    util::{geo, text::levenshtein_distance},
+};
 use std::{cmp::min, collections::HashSet};
 
 #[derive(Debug, PartialEq, Serialize)]
@@ -91,66 +94,12 @@ fn words_equal_except_k_words(str1: &str, str2: &str, k: u32) -> bool {
     diff <= k
 }
 
-// Levenshtein Distance more realistically captures typos (all of the following
-// operations are counted as distance 1: add one character in between, delete
-// one character, change one character)
+// Levenshtein Distance more realistically captures typos
 // but it proved to be way too slow to be run on the whole dataset
 fn levenshtein_distance_small(s: &str, t: &str, max_dist: usize) -> bool {
     levenshtein_distance(s, t) <= max_dist
 }
 
-// Algorithm from
-// https://en.wikipedia.org/wiki/Levenshtein_distance#Computing_Levenshtein_distance
-fn levenshtein_distance(s: &str, t: &str) -> usize {
-    let max_s: usize = s.len() + 1;
-    let max_t: usize = t.len() + 1;
-
-    // for all i and j, d[i,j] will hold the Levenshtein distance between
-    // the first i characters of s and the first j characters of t
-    // note that d has (m+1)*(n+1) values
-    let mut d: Vec<Vec<usize>> = vec![];
-    for _ in 0..max_s {
-        d.push(vec![0; max_t]);
-    }
-
-    // source (s) prefixes can be transformed into empty string by
-    // dropping all characters
-    for (i, item) in d.iter_mut().enumerate().take(max_s).skip(1) {
-        item[0] = i;
-    }
-
-    // target (t) prefixes can be reached from empty source prefix
-    // by inserting every character
-    for j in 1..max_t {
-        d[0][j] = j;
-    }
-
-    for j in 1..max_t {
-        for i in 1..max_s {
-            let substitution_cost = if s.chars().nth(i) == t.chars().nth(j) {
-                0
-            } else {
-                1
-            };
-            d[i][j] = min3(
-                d[i - 1][j] + 1,                     // deletion
-                d[i][j - 1] + 1,                     // insertion
-                d[i - 1][j - 1] + substitution_cost, // substitution
-            )
-        }
-    }
-
-    d[max_s - 1][max_t - 1]
-}
-
-fn min3(s: usize, t: usize, u: usize) -> usize {
-    if s <= t {
-        min(s, u)
-    } else {
-        min(t, u)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -262,14 +211,6 @@ mod tests {
         assert_eq!(None, is_duplicate(&e4, &e5));
     }
 
-    #[test]
-    fn test_min() {
-        assert_eq!(1, min3(1, 2, 3));
-        assert_eq!(2, min3(3, 2, 3));
-        assert_eq!(2, min3(3, 3, 2));
-        assert_eq!(1, min3(1, 1, 1));
-    }
-
     #[test]
     fn test_words_equal() {
         assert_eq!(true, words_equal_except_k_words("ab abc a", "ab abc b", 1));
@@ -277,12 +218,4 @@ mod tests {
         assert_eq!(true, words_equal_except_k_words("ab ac a", "abc ab ab", 2));
         assert_eq!(false, words_equal_except_k_words("a a a", "ab abc", 2));
     }
-
-    #[test]
-    fn test_levenshtein_distance() {
-        assert_eq!(3, levenshtein_distance("012a34c", "0a3c")); // delete 1,2 and 4
-        assert_eq!(1, levenshtein_distance("12345", "a12345")); // insert a
-        assert_eq!(1, levenshtein_distance("aabaa", "aacaa")); // replace b by c
-    }
-
 }
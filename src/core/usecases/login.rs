@@ -1,22 +1,66 @@
 use crate::core::prelude::*;
+use chrono::*;
 use pwhash::bcrypt;
 
+/// The number of failed login attempts after which a user is locked out.
+pub const MAX_FAILED_LOGIN_ATTEMPTS: u32 = 5;
+
+/// The number of seconds a user is locked out after too many failed attempts.
+pub const LOGIN_LOCKOUT_DURATION: u64 = 15 * 60;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Login {
     username: String,
     password: String,
 }
 
+/// Returns the time until which the user is locked out
+/// because of too many failed login attempts.
+pub fn locked_until(h: &LoginHistory) -> Option<u64> {
+    if h.failed_attempts < MAX_FAILED_LOGIN_ATTEMPTS {
+        return None;
+    }
+    h.last_failed_attempt.map(|t| t + LOGIN_LOCKOUT_DURATION)
+}
+
+pub fn login_history<D: Db>(db: &D, username: &str) -> Result<LoginHistory> {
+    match db.get_login_history(username) {
+        Ok(h) => Ok(h),
+        Err(RepoError::NotFound) => Ok(LoginHistory {
+            username: username.into(),
+            ..Default::default()
+        }),
+        Err(err) => Err(err.into()),
+    }
+}
+
 pub fn login<D: Db>(db: &mut D, login: &Login) -> Result<String> {
     match db.get_user(&login.username) {
         Ok(u) => {
+            let now = Utc::now().timestamp() as u64;
+            let mut history = login_history(db, &u.username)?;
+            match locked_until(&history) {
+                Some(t) if now < t => {
+                    return Err(Error::Parameter(ParameterError::LoginLocked));
+                }
+                // The attempts before an expired lockout are not counted again
+                Some(_) => history.failed_attempts = 0,
+                None => {}
+            }
             if bcrypt::verify(&login.password, &u.password) {
+                history.failed_attempts = 0;
                 if u.email_confirmed {
+                    history.last_login = Some(now);
+                    db.update_login_history(&history)?;
                     Ok(login.username.clone())
                 } else {
+                    db.update_login_history(&history)?;
                     Err(Error::Parameter(ParameterError::EmailNotConfirmed))
                 }
             } else {
+                history.failed_attempts += 1;
+                history.last_failed_attempt = Some(now);
+                db.update_login_history(&history)?;
                 Err(Error::Parameter(ParameterError::Credentials))
             }
         }
@@ -29,5 +73,92 @@ pub fn login<D: Db>(db: &mut D, login: &Login) -> Result<String> {
 
 #[cfg(test)]
 mod tests {
-    //TODO: write tests
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn login_as(db: &mut MockDb, password: &str) -> Result<String> {
+        login(
+            db,
+            &Login {
+                username: "foo".into(),
+                password: password.into(),
+            },
+        )
+    }
+
+    fn create_user(db: &mut MockDb) {
+        db.create_user(User {
+            id: "1".into(),
+            username: "foo".into(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: "foo@bar".into(),
+            email_confirmed: true,
+            role: Role::User,
+            language: None,
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn track_login_attempts() {
+        let mut db = MockDb::new();
+        create_user(&mut db);
+        assert!(login_as(&mut db, "wrong").is_err());
+        let h = login_history(&db, "foo").unwrap();
+        assert_eq!(h.failed_attempts, 1);
+        assert!(h.last_failed_attempt.is_some());
+        assert!(h.last_login.is_none());
+
+        assert_eq!(login_as(&mut db, "secret").unwrap(), "foo");
+        let h = login_history(&db, "foo").unwrap();
+        assert_eq!(h.failed_attempts, 0);
+        assert!(h.last_login.is_some());
+        assert!(locked_until(&h).is_none());
+    }
+
+    #[test]
+    fn lock_out_after_too_many_failed_attempts() {
+        let mut db = MockDb::new();
+        create_user(&mut db);
+        for _ in 0..MAX_FAILED_LOGIN_ATTEMPTS {
+            match login_as(&mut db, "wrong") {
+                Err(Error::Parameter(ParameterError::Credentials)) => {}
+                _ => panic!("invalid credentials expected"),
+            }
+        }
+        match login_as(&mut db, "secret") {
+            Err(Error::Parameter(ParameterError::LoginLocked)) => {}
+            _ => panic!("lockout expected"),
+        }
+        let mut h = login_history(&db, "foo").unwrap();
+        assert_eq!(h.failed_attempts, MAX_FAILED_LOGIN_ATTEMPTS);
+        assert!(locked_until(&h).is_some());
+
+        // the lockout expires
+        h.last_failed_attempt = Some(h.last_failed_attempt.unwrap() - LOGIN_LOCKOUT_DURATION);
+        db.update_login_history(&h).unwrap();
+        assert!(login_as(&mut db, "secret").is_ok());
+    }
+
+    #[test]
+    fn count_the_attempts_after_an_expired_lockout_again() {
+        let mut db = MockDb::new();
+        create_user(&mut db);
+        for _ in 0..MAX_FAILED_LOGIN_ATTEMPTS {
+            assert!(login_as(&mut db, "wrong").is_err());
+        }
+        let mut h = login_history(&db, "foo").unwrap();
+        h.last_failed_attempt = Some(h.last_failed_attempt.unwrap() - LOGIN_LOCKOUT_DURATION);
+        db.update_login_history(&h).unwrap();
+
+        // a single typo after the lockout does not lock the user out again
+        match login_as(&mut db, "wrong") {
+            Err(Error::Parameter(ParameterError::Credentials)) => {}
+            _ => panic!("invalid credentials expected"),
+        }
+        let h = login_history(&db, "foo").unwrap();
+        assert_eq!(h.failed_attempts, 1);
+        assert!(locked_until(&h).is_none());
+        assert!(login_as(&mut db, "secret").is_ok());
+    }
 }
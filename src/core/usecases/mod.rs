@@ -7,27 +7,60 @@ use uuid::Uuid;
 
 //TODO: move usecases into separate files
 
+mod bbox_subscriptions;
+mod check_spam;
+mod comment_rating;
 mod confirm_email;
 mod create_new_entry;
 mod create_new_event;
 mod create_new_user;
 mod delete_event;
+mod delete_rating;
+mod entry_contacts;
+mod export_user_data;
 mod find_duplicates;
 mod login;
+mod notifications;
+mod outbox;
 mod query_events;
 mod rate_entry;
+mod report;
+mod review_entry;
 mod search;
 #[cfg(test)]
 pub mod tests;
 mod update_entry;
 mod update_event;
+mod update_rating;
+mod user_tokens;
+mod webhooks;
 
 pub use self::{
-    confirm_email::*, create_new_entry::*, create_new_event::*, create_new_user::*,
-    delete_event::*, find_duplicates::*, login::*, query_events::*, rate_entry::*, search::*,
-    update_entry::*, update_event::*,
+    bbox_subscriptions::*, check_spam::*, comment_rating::*, confirm_email::*, create_new_entry::*,
+    create_new_event::*, create_new_user::*, delete_event::*, delete_rating::*, entry_contacts::*,
+    export_user_data::*, find_duplicates::*, login::*, notifications::*, outbox::*,
+    query_events::*, rate_entry::*, report::*, review_entry::*, search::*, update_entry::*,
+    update_event::*, update_rating::*, user_tokens::*, webhooks::*,
 };
 
+/// Only scouts and admins are allowed to moderate.
+fn check_scout<D: Db>(db: &D, username: &str) -> Result<()> {
+    let user = db.get_user(username)?;
+    if user.role < Role::Scout {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    Ok(())
+}
+
+/// Only admins are allowed to operate the server.
+pub fn check_admin<D: Db>(db: &D, username: &str) -> Result<()> {
+    let user = db.get_user(username)?;
+    if user.role < Role::Admin {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    Ok(())
+}
+
 pub fn get_ratings<D: Db>(db: &D, ids: &[String]) -> Result<Vec<Rating>> {
     Ok(db
         .all_ratings()?
@@ -96,12 +129,36 @@ pub fn get_user<D: Db>(
     db: &mut D,
     logged_in_username: &str,
     requested_username: &str,
-) -> Result<(String, String)> {
+) -> Result<User> {
     let u: User = db.get_user(requested_username)?;
     if logged_in_username != requested_username {
         return Err(Error::Parameter(ParameterError::Forbidden));
     }
-    Ok((u.username, u.email))
+    Ok(u)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserSettings {
+    /// The language of the mails the user receives
+    pub language: Option<String>,
+}
+
+pub fn update_user_settings<D: Db>(
+    db: &mut D,
+    logged_in_username: &str,
+    requested_username: &str,
+    settings: UserSettings,
+) -> Result<()> {
+    if logged_in_username != requested_username {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    if let Some(ref lang) = settings.language {
+        validate::language(lang)?;
+    }
+    let mut u = db.get_user(requested_username)?;
+    u.language = settings.language;
+    db.update_user(&u)?;
+    Ok(())
 }
 
 pub fn get_event<D: Db>(db: &D, id: &str) -> Result<Event> {
@@ -132,32 +189,41 @@ pub fn subscribe_to_bbox(coordinates: &[Coordinate], username: &str, db: &mut Db
     validate::bbox(&bbox)?;
 
     // TODO: support multiple subscriptions in KVM (frontend)
-    // In the meanwile we just replace existing subscriptions
-    // with a new one.
-    unsubscribe_all_bboxes_by_username(db, username)?;
+    // In the meanwile we just replace the existing unnamed
+    // subscription with a new one. Named subscriptions are
+    // managed with `create_bbox_subscription`.
+    let unnamed_subscriptions: Vec<_> = db
+        .bbox_subscriptions_by_username(username)?
+        .into_iter()
+        .filter(|s| s.name.is_none())
+        .map(|s| s.id)
+        .collect();
+    for s_id in unnamed_subscriptions {
+        db.delete_bbox_subscription(&s_id)?;
+    }
 
     let id = Uuid::new_v4().to_simple_ref().to_string();
     db.create_bbox_subscription(&BboxSubscription {
         id,
+        name: None,
         bbox,
         username: username.into(),
+        categories: vec![],
+        tags: vec![],
+        changes: vec![],
+        delivery: Delivery::Instant,
     })?;
     Ok(())
 }
 
 pub fn get_bbox_subscriptions(username: &str, db: &Db) -> Result<Vec<BboxSubscription>> {
-    Ok(db
-        .all_bbox_subscriptions()?
-        .into_iter()
-        .filter(|s| s.username == username)
-        .collect())
+    Ok(db.bbox_subscriptions_by_username(username)?)
 }
 
 pub fn unsubscribe_all_bboxes_by_username(db: &mut Db, username: &str) -> Result<()> {
     let user_subscriptions: Vec<_> = db
-        .all_bbox_subscriptions()?
+        .bbox_subscriptions_by_username(username)?
         .into_iter()
-        .filter(|s| s.username == username)
         .map(|s| s.id)
         .collect();
     for s_id in user_subscriptions {
@@ -176,31 +242,3 @@ pub fn bbox_subscriptions_by_coordinate(
         .filter(|s| geo::is_in_bbox(&x.lat, &x.lng, &s.bbox))
         .collect())
 }
-
-pub fn email_addresses_from_subscriptions(
-    db: &mut Db,
-    subs: &[BboxSubscription],
-) -> Result<Vec<String>> {
-    let usernames: Vec<_> = subs.iter().map(|s| &s.username).collect();
-
-    let mut addresses: Vec<_> = db
-        .all_users()?
-        .into_iter()
-        .filter(|u| usernames.iter().any(|x| **x == u.username))
-        .map(|u| u.email)
-        .collect();
-    addresses.dedup();
-    Ok(addresses)
-}
-
-pub fn email_addresses_by_coordinate(db: &mut Db, lat: &f64, lng: &f64) -> Result<Vec<String>> {
-    let subs = bbox_subscriptions_by_coordinate(
-        db,
-        &Coordinate {
-            lat: *lat,
-            lng: *lng,
-        },
-    )?;
-    let addresses = email_addresses_from_subscriptions(db, &subs)?;
-    Ok(addresses)
-}
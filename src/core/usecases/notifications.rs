@@ -0,0 +1,257 @@
+use super::{bbox_subscriptions_by_coordinate, subscription_matches, Change};
+use crate::core::prelude::*;
+use chrono::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// A user that is notified together with
+/// the subscriptions that caused the notification.
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub email: String,
+    pub language: Option<String>,
+    pub subscriptions: Vec<BboxSubscription>,
+}
+
+/// The pending notifications of a user
+/// that are sent together as one mail.
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub email: String,
+    pub language: Option<String>,
+    pub notifications: Vec<PendingNotification>,
+    /// The subscriptions of the user with the
+    /// kinds of delivery of the notifications
+    pub subscriptions: Vec<BboxSubscription>,
+}
+
+fn recipients(db: &Db, subs: Vec<BboxSubscription>) -> Result<Vec<Recipient>> {
+    if subs.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut by_user: HashMap<String, Vec<BboxSubscription>> = HashMap::new();
+    for s in subs {
+        by_user.entry(s.username.clone()).or_default().push(s);
+    }
+    // All users are loaded at once instead of one by one
+    let recipients = db
+        .all_users()?
+        .into_iter()
+        .filter_map(|u| {
+            by_user.remove(&u.username).map(|subscriptions| Recipient {
+                email: u.email,
+                language: u.language,
+                subscriptions,
+            })
+        })
+        .collect();
+    Ok(recipients)
+}
+
+/// Returns the subscribers that are notified instantly
+/// and queues a summary of the change for the digests
+/// of all other subscribers.
+pub fn notify_subscribers(db: &mut Db, x: &Coordinate, change: &Change) -> Result<Vec<Recipient>> {
+    let (instant, digests): (Vec<_>, Vec<_>) = bbox_subscriptions_by_coordinate(db, x)?
+        .into_iter()
+        .filter(|s| subscription_matches(s, change))
+        .partition(|s| s.delivery == Delivery::Instant);
+    let now = Utc::now().timestamp() as u64;
+    let mut queued: Vec<(String, Delivery)> = vec![];
+    for s in digests {
+        // Overlapping subscriptions of a user don't duplicate the notification
+        if queued
+            .iter()
+            .any(|(u, d)| *u == s.username && *d == s.delivery)
+        {
+            continue;
+        }
+        db.create_pending_notification(PendingNotification {
+            id: Uuid::new_v4().to_simple_ref().to_string(),
+            username: s.username.clone(),
+            delivery: s.delivery,
+            created: now,
+            kind: change.kind,
+            title: change.title.into(),
+            target_id: change.id.into(),
+        })?;
+        queued.push((s.username, s.delivery));
+    }
+    recipients(db, instant)
+}
+
+/// Collects the pending notifications of each user with the given
+/// kind of delivery (or of all digests) ordered by their creation.
+pub fn pending_digests<D: Db>(db: &D, delivery: Option<Delivery>) -> Result<Vec<Digest>> {
+    let mut notifications: Vec<_> = db
+        .all_pending_notifications()?
+        .into_iter()
+        .filter(|n| n.delivery != Delivery::Instant)
+        .filter(|n| delivery.map(|d| d == n.delivery).unwrap_or(true))
+        .collect();
+    notifications.sort_by_key(|n| n.created);
+    let mut by_user: HashMap<String, Vec<PendingNotification>> = HashMap::new();
+    for n in notifications {
+        by_user.entry(n.username.clone()).or_default().push(n);
+    }
+    let mut subscriptions: HashMap<String, Vec<BboxSubscription>> = HashMap::new();
+    for s in db.all_bbox_subscriptions()? {
+        if by_user.contains_key(&s.username) {
+            subscriptions.entry(s.username.clone()).or_default().push(s);
+        }
+    }
+    let mut digests = vec![];
+    for u in db.all_users()? {
+        if let Some(user_notifications) = by_user.remove(&u.username) {
+            let subscriptions = subscriptions
+                .remove(&u.username)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|s| user_notifications.iter().any(|n| n.delivery == s.delivery))
+                .collect();
+            digests.push(Digest {
+                email: u.email,
+                language: u.language,
+                notifications: user_notifications,
+                subscriptions,
+            });
+        }
+    }
+    Ok(digests)
+}
+
+/// Removes the notifications of a digest after it has been sent.
+pub fn digest_sent<D: Db>(db: &mut D, digest: &Digest) -> Result<()> {
+    for n in &digest.notifications {
+        db.delete_pending_notification(&n.id)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn create_user(db: &mut MockDb, username: &str) {
+        db.create_user(User {
+            id: username.into(),
+            username: username.into(),
+            password: "secret".into(),
+            email: format!("{}@bar", username),
+            email_confirmed: true,
+            role: Role::User,
+            language: None,
+        })
+        .unwrap();
+    }
+
+    fn subscribe(db: &mut MockDb, id: &str, username: &str, delivery: Delivery) {
+        db.create_bbox_subscription(&BboxSubscription {
+            id: id.into(),
+            name: Some(id.into()),
+            bbox: Bbox {
+                south_west: Coordinate { lat: 0.0, lng: 0.0 },
+                north_east: Coordinate {
+                    lat: 10.0,
+                    lng: 10.0,
+                },
+            },
+            username: username.into(),
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            delivery,
+        })
+        .unwrap();
+    }
+
+    fn notify(db: &mut MockDb, title: &str) -> Vec<String> {
+        let change = Change {
+            kind: ChangeKind::NewEntry,
+            id: "e",
+            title,
+            categories: &[],
+            tags: &[],
+        };
+        let x = Coordinate { lat: 5.0, lng: 5.0 };
+        notify_subscribers(db, &x, &change)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.email)
+            .collect()
+    }
+
+    #[test]
+    fn queue_notifications_for_digests() {
+        let mut db = MockDb::new();
+        create_user(&mut db, "a");
+        create_user(&mut db, "b");
+        create_user(&mut db, "c");
+        subscribe(&mut db, "1", "a", Delivery::Instant);
+        subscribe(&mut db, "2", "b", Delivery::Daily);
+        subscribe(&mut db, "3", "b", Delivery::Daily);
+        subscribe(&mut db, "4", "c", Delivery::Weekly);
+
+        assert_eq!(notify(&mut db, "first"), vec!["a@bar"]);
+        assert_eq!(notify(&mut db, "second"), vec!["a@bar"]);
+        assert_eq!(db.pending_notifications.len(), 4);
+
+        let digests = pending_digests(&db, None).unwrap();
+        assert_eq!(digests.len(), 2);
+
+        let daily = pending_digests(&db, Some(Delivery::Daily)).unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].email, "b@bar");
+        assert_eq!(daily[0].notifications.len(), 2);
+        assert_eq!(daily[0].notifications[0].title, "first");
+        assert_eq!(daily[0].notifications[0].kind, ChangeKind::NewEntry);
+        assert_eq!(daily[0].notifications[0].target_id, "e");
+        assert_eq!(daily[0].subscriptions.len(), 2);
+
+        digest_sent(&mut db, &daily[0]).unwrap();
+        assert!(pending_digests(&db, Some(Delivery::Daily))
+            .unwrap()
+            .is_empty());
+        let weekly = pending_digests(&db, Some(Delivery::Weekly)).unwrap();
+        assert_eq!(weekly.len(), 1);
+        assert_eq!(weekly[0].email, "c@bar");
+    }
+
+    #[test]
+    fn notify_each_instant_subscriber_once() {
+        let mut db = MockDb::new();
+        create_user(&mut db, "a");
+        create_user(&mut db, "b");
+        subscribe(&mut db, "1", "b", Delivery::Instant);
+        subscribe(&mut db, "2", "a", Delivery::Instant);
+        subscribe(&mut db, "3", "b", Delivery::Instant);
+        let x = Coordinate { lat: 5.0, lng: 5.0 };
+        let change = Change {
+            kind: ChangeKind::NewEntry,
+            id: "e",
+            title: "foo",
+            categories: &[],
+            tags: &[],
+        };
+        let recipients = notify_subscribers(&mut db, &x, &change).unwrap();
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[0].email, "a@bar");
+        assert_eq!(recipients[0].subscriptions.len(), 1);
+        assert_eq!(recipients[1].email, "b@bar");
+        assert_eq!(recipients[1].subscriptions.len(), 2);
+    }
+
+    #[test]
+    fn collect_digests_in_the_language_of_the_user() {
+        let mut db = MockDb::new();
+        create_user(&mut db, "a");
+        db.users[0].language = Some("en".into());
+        subscribe(&mut db, "1", "a", Delivery::Daily);
+
+        notify(&mut db, "title");
+        let digests = pending_digests(&db, None).unwrap();
+        assert_eq!(digests[0].language, Some("en".into()));
+        assert_eq!(digests[0].notifications[0].title, "title");
+    }
+}
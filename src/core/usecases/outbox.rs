@@ -0,0 +1,165 @@
+use crate::core::prelude::*;
+use chrono::*;
+use uuid::Uuid;
+
+/// Delays the retries of failed deliveries exponentially.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    /// Mails are marked as failed after this number of attempts.
+    pub max_attempts: u32,
+    /// The number of seconds before the first retry
+    /// that is doubled after every further attempt.
+    pub delay: u64,
+}
+
+impl Backoff {
+    /// The number of seconds before the next attempt.
+    pub fn delay_after(&self, attempts: u32) -> u64 {
+        let exp = attempts.saturating_sub(1).min(32);
+        self.delay.saturating_mul(1 << exp)
+    }
+}
+
+/// Stores a composed mail until it has been delivered.
+pub fn enqueue_mail(
+    db: &mut Db,
+    recipients: &[String],
+    subject: &str,
+    content: &str,
+) -> Result<String> {
+    let now = Utc::now().timestamp() as u64;
+    let id = Uuid::new_v4().to_simple_ref().to_string();
+    db.create_outbox_mail(OutboxMail {
+        id: id.clone(),
+        created: now,
+        recipients: recipients.to_vec(),
+        subject: subject.into(),
+        content: content.into(),
+        status: MailStatus::Pending,
+        attempts: 0,
+        next_attempt: now,
+        last_error: None,
+    })?;
+    Ok(id)
+}
+
+/// The pending mails that should be delivered (again) ordered by their creation.
+pub fn due_mails<D: Db>(db: &D, now: u64) -> Result<Vec<OutboxMail>> {
+    Ok(db.due_outbox_mails(now)?)
+}
+
+pub fn mail_delivered<D: Db>(db: &mut D, id: &str) -> Result<()> {
+    db.delete_outbox_mail(id)?;
+    Ok(())
+}
+
+/// Schedules the next attempt or gives up after too many attempts.
+pub fn mail_delivery_failed<D: Db>(
+    db: &mut D,
+    id: &str,
+    error: &str,
+    backoff: &Backoff,
+    now: u64,
+) -> Result<()> {
+    let mut m = db.get_outbox_mail(id)?;
+    m.attempts += 1;
+    m.last_error = Some(error.into());
+    if m.attempts >= backoff.max_attempts {
+        m.status = MailStatus::Failed;
+    } else {
+        m.next_attempt = now + backoff.delay_after(m.attempts);
+    }
+    db.update_outbox_mail(&m)?;
+    Ok(())
+}
+
+/// All mails of the outbox (with the given status) ordered by their creation.
+pub fn outbox_mails<D: Db>(db: &D, status: Option<MailStatus>) -> Result<Vec<OutboxMail>> {
+    let mut mails: Vec<_> = db
+        .all_outbox_mails()?
+        .into_iter()
+        .filter(|m| status.map(|s| s == m.status).unwrap_or(true))
+        .collect();
+    mails.sort_by_key(|m| m.created);
+    Ok(mails)
+}
+
+/// Tries to deliver the mail again as soon as possible.
+pub fn requeue_mail<D: Db>(db: &mut D, id: &str) -> Result<()> {
+    let mut m = db.get_outbox_mail(id)?;
+    m.status = MailStatus::Pending;
+    m.attempts = 0;
+    m.next_attempt = Utc::now().timestamp() as u64;
+    db.update_outbox_mail(&m)?;
+    Ok(())
+}
+
+/// Requeues all failed mails and returns their number.
+pub fn requeue_failed_mails<D: Db>(db: &mut D) -> Result<usize> {
+    let failed = outbox_mails(db, Some(MailStatus::Failed))?;
+    for m in &failed {
+        requeue_mail(db, &m.id)?;
+    }
+    Ok(failed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    const BACKOFF: Backoff = Backoff {
+        max_attempts: 3,
+        delay: 60,
+    };
+
+    #[test]
+    fn retry_with_exponential_backoff() {
+        let mut db = MockDb::new();
+        let id = enqueue_mail(&mut db, &["a@bar".into()], "subject", "mail").unwrap();
+        let now = db.outbox_mails[0].next_attempt;
+        assert_eq!(due_mails(&db, now).unwrap().len(), 1);
+
+        mail_delivery_failed(&mut db, &id, "timeout", &BACKOFF, now).unwrap();
+        assert!(due_mails(&db, now).unwrap().is_empty());
+        assert_eq!(db.outbox_mails[0].next_attempt, now + 60);
+        assert_eq!(due_mails(&db, now + 60).unwrap().len(), 1);
+
+        mail_delivery_failed(&mut db, &id, "timeout", &BACKOFF, now + 60).unwrap();
+        assert_eq!(db.outbox_mails[0].next_attempt, now + 60 + 120);
+
+        mail_delivery_failed(&mut db, &id, "refused", &BACKOFF, now + 180).unwrap();
+        let m = &db.outbox_mails[0];
+        assert_eq!(m.status, MailStatus::Failed);
+        assert_eq!(m.attempts, 3);
+        assert_eq!(m.last_error, Some("refused".into()));
+        assert!(due_mails(&db, now + 100_000).unwrap().is_empty());
+        assert_eq!(
+            outbox_mails(&db, Some(MailStatus::Failed)).unwrap().len(),
+            1
+        );
+        assert!(outbox_mails(&db, Some(MailStatus::Pending))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn requeue_failed_and_remove_delivered_mails() {
+        let mut db = MockDb::new();
+        let failed = enqueue_mail(&mut db, &["a@bar".into()], "1", "mail").unwrap();
+        let delivered = enqueue_mail(&mut db, &["b@bar".into()], "2", "mail").unwrap();
+        let now = Utc::now().timestamp() as u64;
+        for _ in 0..3 {
+            mail_delivery_failed(&mut db, &failed, "refused", &BACKOFF, now).unwrap();
+        }
+        mail_delivered(&mut db, &delivered).unwrap();
+        assert_eq!(db.outbox_mails.len(), 1);
+
+        assert_eq!(requeue_failed_mails(&mut db).unwrap(), 1);
+        let m = db.get_outbox_mail(&failed).unwrap();
+        assert_eq!(m.status, MailStatus::Pending);
+        assert_eq!(m.attempts, 0);
+        assert_eq!(due_mails(&db, now + 1).unwrap().len(), 1);
+        assert_eq!(requeue_failed_mails(&mut db).unwrap(), 0);
+    }
+}
@@ -14,7 +14,29 @@ pub struct RateEntry {
     pub user    : Option<String>,
 }
 
-pub fn rate_entry<D: Db>(db: &mut D, r: RateEntry) -> Result<()> {
+/// Each user can only rate an entry once per context,
+/// so an existing rating of the user is updated instead.
+pub fn existing_rating<D: Db>(
+    db: &D,
+    entry_id: &str,
+    context: &RatingContext,
+    user: Option<&str>,
+) -> Result<Option<Rating>> {
+    match user {
+        Some(username) => match db.get_rating_of_user(entry_id, context, username) {
+            Ok(rating) => Ok(Some(rating)),
+            Err(RepoError::NotFound) => Ok(None),
+            Err(err) => Err(err.into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Creates or updates the rating and returns its ID
+/// together with whether an existing rating has been updated.
+/// The comment of an updated rating replaces the text of the
+/// previous comment of the user instead of adding another one.
+pub fn rate_entry<D: Db>(db: &mut D, r: RateEntry) -> Result<(String, bool)> {
     let e = db.get_entry(&r.entry)?;
     if r.comment.len() < 1 {
         return Err(Error::Parameter(ParameterError::EmptyComment));
@@ -23,24 +45,62 @@ pub fn rate_entry<D: Db>(db: &mut D, r: RateEntry) -> Result<()> {
         return Err(Error::Parameter(ParameterError::RatingValue));
     }
     let now = Utc::now().timestamp() as u64;
-    let rating_id = Uuid::new_v4().to_simple_ref().to_string();
-    let comment_id = Uuid::new_v4().to_simple_ref().to_string();
-    db.create_rating(Rating {
-        id: rating_id.clone(),
-        entry_id: e.id,
-        created: now,
-        title: r.title,
-        value: r.value,
-        context: r.context,
-        source: r.source,
-    })?;
-    db.create_comment(Comment {
-        id: comment_id,
-        created: now,
-        text: r.comment,
-        rating_id,
-    })?;
-    Ok(())
+    if let Some(ref username) = r.user {
+        db.get_user(username)?;
+    }
+    let existing = existing_rating(db, &e.id, &r.context, r.user.as_ref().map(String::as_str))?;
+    let updated = existing.is_some();
+    let rating_id = match existing {
+        Some(rating) => {
+            let rating_id = rating.id.clone();
+            db.update_rating(&Rating {
+                updated: Some(now),
+                title: r.title,
+                value: r.value,
+                source: r.source,
+                ..rating
+            })?;
+            rating_id
+        }
+        None => {
+            let rating_id = Uuid::new_v4().to_simple_ref().to_string();
+            db.create_rating(Rating {
+                id: rating_id.clone(),
+                entry_id: e.id,
+                created: now,
+                updated: None,
+                title: r.title,
+                value: r.value,
+                context: r.context,
+                source: r.source,
+                created_by: r.user.clone(),
+            })?;
+            rating_id
+        }
+    };
+    let own_comment = if updated {
+        let user = &r.user;
+        db.comments_of_rating(&rating_id)?
+            .into_iter()
+            .find(|c| c.parent_id.is_none() && c.created_by == *user)
+    } else {
+        None
+    };
+    match own_comment {
+        Some(c) => db.update_comment(&Comment {
+            text: r.comment,
+            ..c
+        })?,
+        None => db.create_comment(Comment {
+            id: Uuid::new_v4().to_simple_ref().to_string(),
+            created: now,
+            text: r.comment,
+            rating_id: rating_id.clone(),
+            parent_id: None,
+            created_by: r.user,
+        })?,
+    }
+    Ok((rating_id, updated))
 }
 
 #[cfg(test)]
@@ -144,6 +204,85 @@ mod tests {
         assert_eq!(db.comments.len(), 1);
         assert_eq!(db.ratings[0].entry_id, "foo");
         assert_eq!(db.comments[0].rating_id, db.ratings[0].id);
+        assert!(db.ratings[0].created_by.is_none());
     }
 
+    #[test]
+    fn rate_as_logged_in_user() {
+        let mut db = MockDb::new();
+        db.entries = vec![Entry::build().id("foo").finish()];
+        db.users = vec![User {
+            id: "1".into(),
+            username: "alice".into(),
+            password: "secret".into(),
+            email: "alice@example.com".into(),
+            email_confirmed: true,
+            role: Role::User,
+            language: None,
+        }];
+        let rate = |value, context, user: Option<&str>| RateEntry {
+            entry: "foo".into(),
+            comment: format!("comment {}", value),
+            title: "title".into(),
+            context,
+            user: user.map(Into::into),
+            value,
+            source: None,
+        };
+        assert!(
+            existing_rating(&db, "foo", &RatingContext::Fairness, Some("alice"))
+                .unwrap()
+                .is_none()
+        );
+        let (id, updated) =
+            rate_entry(&mut db, rate(1, RatingContext::Fairness, Some("alice"))).unwrap();
+        assert!(!updated);
+        assert_eq!(db.ratings.len(), 1);
+        assert_eq!(db.ratings[0].id, id);
+        assert_eq!(db.ratings[0].created_by, Some("alice".into()));
+        assert!(db.ratings[0].updated.is_none());
+        // pretend the first rating is older
+        db.ratings[0].created -= 100;
+        let created = db.ratings[0].created;
+        assert_eq!(
+            existing_rating(&db, "foo", &RatingContext::Fairness, Some("alice"))
+                .unwrap()
+                .map(|r| r.id),
+            Some(id.clone())
+        );
+
+        // a second rating in the same context replaces the first one
+        let (updated_id, updated) =
+            rate_entry(&mut db, rate(2, RatingContext::Fairness, Some("alice"))).unwrap();
+        assert_eq!(updated_id, id);
+        assert!(updated);
+        assert_eq!(db.ratings.len(), 1);
+        assert_eq!(db.ratings[0].value, 2);
+        assert_eq!(db.ratings[0].created, created);
+        assert!(db.ratings[0].updated.is_some());
+        // and its comment replaces the previous one
+        assert_eq!(db.comments.len(), 1);
+        assert_eq!(db.comments[0].rating_id, id);
+        assert_eq!(db.comments[0].text, "comment 2");
+
+        // replies of others are kept
+        db.comments.push(Comment {
+            id: "reply".into(),
+            created: 0,
+            text: "reply".into(),
+            rating_id: id.clone(),
+            parent_id: Some(db.comments[0].id.clone()),
+            created_by: None,
+        });
+        rate_entry(&mut db, rate(1, RatingContext::Fairness, Some("alice"))).unwrap();
+        assert_eq!(db.comments.len(), 2);
+        assert_eq!(db.comments[0].text, "comment 1");
+        assert_eq!(db.comments[1].text, "reply");
+
+        rate_entry(&mut db, rate(0, RatingContext::Humanity, Some("alice"))).unwrap();
+        rate_entry(&mut db, rate(0, RatingContext::Fairness, None)).unwrap();
+        assert_eq!(db.ratings.len(), 3);
+
+        assert!(rate_entry(&mut db, rate(0, RatingContext::Fairness, Some("bob"))).is_err());
+    }
 }
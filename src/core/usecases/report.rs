@@ -0,0 +1,231 @@
+use super::{check_scout, delete_comment, delete_rating};
+use crate::core::prelude::*;
+use chrono::*;
+use uuid::Uuid;
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewReport {
+    pub target    : ReportTarget,
+    pub target_id : String,
+    pub reason    : String,
+    pub user      : Option<String>,
+}
+
+/// What a scout decided to do about a report.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportAction {
+    Dismiss,
+    /// Archive the reported entry
+    Archive,
+    /// Delete the reported rating or comment
+    Delete,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ResolveReport {
+    pub action: ReportAction,
+}
+
+/// Everyone can report an entry, rating or comment
+/// that is spam or contains incorrect data.
+pub fn create_report<D: Db>(db: &mut D, r: NewReport) -> Result<Report> {
+    match r.target {
+        ReportTarget::Entry => {
+            db.get_entry(&r.target_id)?;
+        }
+        ReportTarget::Rating => {
+            db.get_rating(&r.target_id)?;
+        }
+        ReportTarget::Comment => {
+            db.get_comment(&r.target_id)?;
+        }
+    }
+    if r.reason.trim().is_empty() {
+        return Err(Error::Parameter(ParameterError::EmptyReason));
+    }
+    if let Some(ref username) = r.user {
+        db.get_user(username)?;
+    }
+    let report = Report {
+        id: Uuid::new_v4().to_simple_ref().to_string(),
+        created: Utc::now().timestamp() as u64,
+        target: r.target,
+        target_id: r.target_id,
+        reason: r.reason,
+        reported_by: r.user,
+        resolution: None,
+        resolved_by: None,
+    };
+    db.create_report(report.clone())?;
+    Ok(report)
+}
+
+/// All scouts and admins
+/// that should be notified about new reports.
+pub fn scouts<D: Db>(db: &D) -> Result<Vec<User>> {
+    Ok(db
+        .all_users()?
+        .into_iter()
+        .filter(|u| u.role >= Role::Scout && u.email_confirmed)
+        .collect())
+}
+
+/// The unresolved reports (oldest first) that are visible to scouts and admins.
+pub fn open_reports<D: Db>(db: &D, username: &str) -> Result<Vec<Report>> {
+    check_scout(db, username)?;
+    let mut reports: Vec<_> = db
+        .all_reports()?
+        .into_iter()
+        .filter(|r| r.resolution.is_none())
+        .collect();
+    reports.sort_by_key(|r| r.created);
+    Ok(reports)
+}
+
+pub fn resolve_report<D: Db>(
+    db: &mut D,
+    username: &str,
+    id: &str,
+    action: ReportAction,
+) -> Result<()> {
+    check_scout(db, username)?;
+    let mut report = db.get_report(id)?;
+    if report.resolution.is_some() {
+        return Err(Error::Parameter(ParameterError::ReportResolved));
+    }
+    let resolution = match (action, report.target) {
+        (ReportAction::Dismiss, _) => ReportResolution::Dismissed,
+        (ReportAction::Archive, ReportTarget::Entry) => {
+            db.archive_entry(&report.target_id, Utc::now().timestamp() as u64)?;
+            ReportResolution::Archived
+        }
+        (ReportAction::Delete, ReportTarget::Rating) => {
+            delete_rating(db, username, &report.target_id)?;
+            ReportResolution::Deleted
+        }
+        (ReportAction::Delete, ReportTarget::Comment) => {
+            delete_comment(db, username, &report.target_id)?;
+            ReportResolution::Deleted
+        }
+        _ => {
+            return Err(Error::Parameter(ParameterError::ReportResolution));
+        }
+    };
+    report.resolution = Some(resolution);
+    report.resolved_by = Some(username.into());
+    db.update_report(&report)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn user(username: &str, role: Role) -> User {
+        User {
+            id: username.into(),
+            username: username.into(),
+            password: "secret".into(),
+            email: format!("{}@example.com", username),
+            email_confirmed: true,
+            role,
+            language: None,
+        }
+    }
+
+    fn setup() -> MockDb {
+        let mut db = MockDb::new();
+        db.users = vec![user("alice", Role::User), user("scout", Role::Scout)];
+        db.entries = vec![Entry::build().id("e").finish()];
+        db.ratings = vec![Rating {
+            id: "r".into(),
+            entry_id: "e".into(),
+            created: 0,
+            updated: None,
+            title: "title".into(),
+            value: 1,
+            context: RatingContext::Fairness,
+            source: None,
+            created_by: None,
+        }];
+        db.comments = vec![Comment {
+            id: "c".into(),
+            created: 0,
+            text: "spam".into(),
+            rating_id: "r".into(),
+            parent_id: None,
+            created_by: None,
+        }];
+        db
+    }
+
+    fn report(target: ReportTarget, target_id: &str) -> NewReport {
+        NewReport {
+            target,
+            target_id: target_id.into(),
+            reason: "spam".into(),
+            user: None,
+        }
+    }
+
+    #[test]
+    fn create_reports() {
+        let mut db = setup();
+        let r = create_report(&mut db, report(ReportTarget::Comment, "c")).unwrap();
+        assert_eq!(r.target_id, "c");
+        assert!(r.resolution.is_none());
+        assert_eq!(db.reports.len(), 1);
+
+        let mut r = report(ReportTarget::Entry, "e");
+        r.user = Some("alice".into());
+        let r = create_report(&mut db, r).unwrap();
+        assert_eq!(r.reported_by, Some("alice".into()));
+
+        assert!(create_report(&mut db, report(ReportTarget::Rating, "c")).is_err());
+        let mut r = report(ReportTarget::Entry, "e");
+        r.reason = " ".into();
+        assert!(create_report(&mut db, r).is_err());
+        assert_eq!(db.reports.len(), 2);
+    }
+
+    #[test]
+    fn only_scouts_see_open_reports() {
+        let mut db = setup();
+        create_report(&mut db, report(ReportTarget::Entry, "e")).unwrap();
+        assert!(open_reports(&db, "alice").is_err());
+        assert_eq!(open_reports(&db, "scout").unwrap().len(), 1);
+        let scouts = scouts(&db).unwrap();
+        assert_eq!(scouts.len(), 1);
+        assert_eq!(scouts[0].email, "scout@example.com");
+    }
+
+    #[test]
+    fn resolve_reports() {
+        let mut db = setup();
+        let entry = create_report(&mut db, report(ReportTarget::Entry, "e")).unwrap();
+        let rating = create_report(&mut db, report(ReportTarget::Rating, "r")).unwrap();
+        let comment = create_report(&mut db, report(ReportTarget::Comment, "c")).unwrap();
+
+        assert!(resolve_report(&mut db, "alice", &entry.id, ReportAction::Archive).is_err());
+        assert!(resolve_report(&mut db, "scout", &entry.id, ReportAction::Delete).is_err());
+        assert!(resolve_report(&mut db, "scout", &rating.id, ReportAction::Archive).is_err());
+
+        resolve_report(&mut db, "scout", &comment.id, ReportAction::Dismiss).unwrap();
+        assert_eq!(db.comments.len(), 1);
+        assert!(resolve_report(&mut db, "scout", &comment.id, ReportAction::Delete).is_err());
+
+        resolve_report(&mut db, "scout", &rating.id, ReportAction::Delete).unwrap();
+        assert!(db.ratings.is_empty());
+
+        resolve_report(&mut db, "scout", &entry.id, ReportAction::Archive).unwrap();
+        assert!(db.entries.is_empty());
+
+        assert!(open_reports(&db, "scout").unwrap().is_empty());
+        let r = db.reports.iter().find(|r| r.id == rating.id).unwrap();
+        assert_eq!(r.resolution, Some(ReportResolution::Deleted));
+        assert_eq!(r.resolved_by, Some("scout".into()));
+    }
+}
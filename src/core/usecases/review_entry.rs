@@ -0,0 +1,83 @@
+use super::check_scout;
+use crate::core::prelude::*;
+use chrono::*;
+
+/// The entries that are waiting for a review (oldest first).
+pub fn pending_entries<D: Db>(db: &D, username: &str) -> Result<Vec<Entry>> {
+    check_scout(db, username)?;
+    let mut entries = db.pending_entries()?;
+    entries.sort_by_key(|e| e.created);
+    Ok(entries)
+}
+
+/// Publishes a pending entry and returns it.
+pub fn approve_entry<D: Db>(db: &mut D, username: &str, id: &str) -> Result<Entry> {
+    check_scout(db, username)?;
+    db.approve_entry(id)?;
+    Ok(db.get_entry(id)?)
+}
+
+/// Rejected entries are archived instead of being published.
+pub fn reject_entry<D: Db>(db: &mut D, username: &str, id: &str) -> Result<()> {
+    check_scout(db, username)?;
+    if !db.pending_entries()?.iter().any(|e| e.id == id) {
+        return Err(RepoError::NotFound.into());
+    }
+    db.archive_entry(id, Utc::now().timestamp() as u64)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn setup() -> MockDb {
+        let mut db = MockDb::new();
+        db.users = ["alice", "scout"]
+            .iter()
+            .map(|username| User {
+                id: username.to_string(),
+                username: username.to_string(),
+                password: "secret".into(),
+                email: format!("{}@example.com", username),
+                email_confirmed: true,
+                role: if *username == "scout" {
+                    Role::Scout
+                } else {
+                    Role::User
+                },
+                language: None,
+            })
+            .collect();
+        db.entries = vec![Entry::build().id("published").finish()];
+        db.pending_entries = vec![
+            Entry::build().id("a").finish(),
+            Entry::build().id("b").finish(),
+        ];
+        db
+    }
+
+    #[test]
+    fn only_scouts_review_entries() {
+        let mut db = setup();
+        assert!(pending_entries(&db, "alice").is_err());
+        assert!(approve_entry(&mut db, "alice", "a").is_err());
+        assert!(reject_entry(&mut db, "alice", "a").is_err());
+        assert_eq!(pending_entries(&db, "scout").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn approve_and_reject_entries() {
+        let mut db = setup();
+        let e = approve_entry(&mut db, "scout", "a").unwrap();
+        assert_eq!(e.id, "a");
+        assert_eq!(db.entries.len(), 2);
+        reject_entry(&mut db, "scout", "b").unwrap();
+        assert!(pending_entries(&db, "scout").unwrap().is_empty());
+        assert_eq!(db.entries.len(), 2);
+        assert!(approve_entry(&mut db, "scout", "b").is_err());
+        // published entries can't be rejected
+        assert!(reject_entry(&mut db, "scout", "published").is_err());
+    }
+}
@@ -1,23 +1,89 @@
 use crate::core::prelude::*;
 use crate::core::util::{
     filter::{self, InBBox},
+    query,
     sort::SortByAverageRating,
 };
-use std::collections::HashMap;
+use chrono::*;
+use std::{collections::HashMap, result, str::FromStr};
 
 const MAX_INVISIBLE_RESULTS: usize = 5;
 
+/// Events that start within this period (in seconds)
+/// are considered upcoming if no other limit is given.
+pub const DEFAULT_UPCOMING_EVENTS_PERIOD: u64 = 60 * 60 * 24 * 28;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchKind {
+    Entries,
+    Events,
+}
+
+impl FromStr for SearchKind {
+    type Err = ParameterError;
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "entries" => Ok(SearchKind::Entries),
+            "events" => Ok(SearchKind::Events),
+            _ => Err(ParameterError::SearchKind),
+        }
+    }
+}
+
+/// The minimum average rating of an entry, either in
+/// a single context or in total (if no context is given).
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone)]
+pub struct MinRating {
+    pub context : Option<RatingContext>,
+    pub value   : f64,
+}
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 #[derive(Debug, Clone)]
-pub struct SearchRequest<'a> {
+pub struct SearchRequest {
     pub bbox          : Bbox,
     pub categories    : Option<Vec<String>>,
     pub text          : String,
     pub tags          : Vec<String>,
-    pub entry_ratings : &'a HashMap<String, f64>,
+    pub typo_tolerance: f32,
+    pub min_rating    : Option<MinRating>,
+    pub kinds         : Vec<SearchKind>,
+    /// Only events that start before this timestamp are returned
+    /// (defaults to `now + DEFAULT_UPCOMING_EVENTS_PERIOD`)
+    pub events_until  : Option<u64>,
+}
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, Default)]
+pub struct SearchResult {
+    pub visible   : Vec<Entry>,
+    pub invisible : Vec<Entry>,
+    pub events    : Vec<Event>,
+}
+
+pub fn search<D: Db>(db: &D, req: &SearchRequest) -> Result<SearchResult> {
+    let query = query::parse(&req.text);
+    let mut result = SearchResult::default();
+
+    if req.kinds.contains(&SearchKind::Entries) {
+        let (visible, invisible) = search_entries(db, req, &query)?;
+        result.visible = visible;
+        result.invisible = invisible;
+    }
+
+    if req.kinds.contains(&SearchKind::Events) {
+        result.events = search_events(db, req, &query)?;
+    }
+
+    Ok(result)
 }
 
-pub fn search<D: Db>(db: &D, req: &SearchRequest) -> Result<(Vec<Entry>, Vec<Entry>)> {
+fn search_entries<D: Db>(
+    db: &D,
+    req: &SearchRequest,
+    query: &query::Query,
+) -> Result<(Vec<Entry>, Vec<Entry>)> {
     let mut entries = if req.text.is_empty() && req.tags.is_empty() {
         let extended_bbox = extend_bbox(&req.bbox);
         db.get_entries_by_bbox(&extended_bbox)?
@@ -35,11 +101,36 @@ pub fn search<D: Db>(db: &D, req: &SearchRequest) -> Result<(Vec<Entry>, Vec<Ent
     let mut entries: Vec<_> = entries
         .into_iter()
         .filter(&*filter::entries_by_tags_or_search_text(
-            &req.text, &req.tags,
+            query,
+            &req.tags,
+            req.typo_tolerance,
         ))
         .collect();
 
-    entries.sort_by_avg_rating(req.entry_ratings);
+    let avg_ratings: HashMap<_, _> = db
+        .all_avg_ratings()?
+        .into_iter()
+        .map(|r| (r.entry_id.clone(), r))
+        .collect();
+
+    if let Some(ref min) = req.min_rating {
+        entries.retain(|e| match avg_ratings.get(&e.id) {
+            Some(avg) => match min.context {
+                Some(ref context) => {
+                    let avg = avg.context(context);
+                    avg.count > 0 && avg.value >= min.value
+                }
+                None => !avg.is_empty() && avg.total >= min.value,
+            },
+            None => false,
+        });
+    }
+
+    let avg_ratings: HashMap<_, _> = avg_ratings
+        .into_iter()
+        .map(|(id, avg)| (id, avg.total))
+        .collect();
+    entries.sort_by_avg_rating(&avg_ratings);
 
     let visible_results: Vec<_> = entries
         .iter()
@@ -56,6 +147,26 @@ pub fn search<D: Db>(db: &D, req: &SearchRequest) -> Result<(Vec<Entry>, Vec<Ent
     Ok((visible_results, invisible_results))
 }
 
+fn search_events<D: Db>(db: &D, req: &SearchRequest, query: &query::Query) -> Result<Vec<Event>> {
+    let now = Utc::now().timestamp() as u64;
+    let until = req
+        .events_until
+        .unwrap_or(now + DEFAULT_UPCOMING_EVENTS_PERIOD);
+    let matches = filter::events_by_tags_or_search_text(query, &req.tags, req.typo_tolerance);
+
+    let mut events: Vec<_> = db
+        .all_events()?
+        .into_iter()
+        .filter(|e| e.end.unwrap_or(e.start) >= now && e.start <= until)
+        .filter(|e| e.in_bbox(&req.bbox))
+        .filter(|e| matches(e))
+        .collect();
+
+    events.sort_by_key(|e| e.start);
+
+    Ok(events)
+}
+
 const BBOX_LAT_EXT: f64 = 0.02;
 const BBOX_LNG_EXT: f64 = 0.04;
 
@@ -82,7 +193,6 @@ mod tests {
         let (entries, ratings) = sort::tests::create_entries_with_ratings(1_000);
         db.entries = entries;
         db.ratings = ratings;
-        let entry_ratings = HashMap::new();
         let req = SearchRequest {
             bbox: Bbox {
                 south_west: Coordinate {
@@ -97,7 +207,10 @@ mod tests {
             categories: None,
             text: "".into(),
             tags: vec![],
-            entry_ratings: &entry_ratings,
+            typo_tolerance: filter::DEFAULT_TYPO_TOLERANCE,
+            min_rating: None,
+            kinds: vec![SearchKind::Entries],
+            events_until: None,
         };
 
         b.iter(|| super::search(&mut db, &req).unwrap());
@@ -110,7 +223,6 @@ mod tests {
         let (entries, ratings) = sort::tests::create_entries_with_ratings(10_000);
         db.entries = entries;
         db.ratings = ratings;
-        let entry_ratings = HashMap::new();
         let req = SearchRequest {
             bbox: Bbox {
                 south_west: Coordinate {
@@ -125,10 +237,150 @@ mod tests {
             categories: None,
             text: "".into(),
             tags: vec![],
-            entry_ratings: &entry_ratings,
+            typo_tolerance: filter::DEFAULT_TYPO_TOLERANCE,
+            min_rating: None,
+            kinds: vec![SearchKind::Entries],
+            events_until: None,
         };
 
         b.iter(|| super::search(&mut db, &req).unwrap());
     }
 
+    fn event(id: &str, title: &str, start: u64, lat: f64) -> Event {
+        Event {
+            id: id.into(),
+            title: title.into(),
+            description: None,
+            start,
+            end: None,
+            location: Some(Location {
+                lat,
+                lng: 0.0,
+                address: None,
+            }),
+            contact: None,
+            tags: vec![],
+            homepage: None,
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn search_upcoming_events() {
+        let now = Utc::now().timestamp() as u64;
+        let mut db = MockDb::new();
+        db.entries = vec![Entry::build().title("Repair Café").finish()];
+        db.events = vec![
+            event("later", "Repair Café", now + 200, 1.0),
+            event("past", "Repair Café", now - 100, 1.0),
+            event("soon", "Repair Cafe", now + 100, 1.0),
+            event("outside", "Repair Café", now + 100, 20.0),
+            event("other", "Kleidertausch", now + 100, 1.0),
+            event("too-late", "Repair Café", now + 1_000, 1.0),
+        ];
+        let mut req = SearchRequest {
+            bbox: Bbox {
+                south_west: Coordinate {
+                    lat: -10.0,
+                    lng: -10.0,
+                },
+                north_east: Coordinate {
+                    lat: 10.0,
+                    lng: 10.0,
+                },
+            },
+            categories: None,
+            text: "cafe".into(),
+            tags: vec![],
+            typo_tolerance: filter::DEFAULT_TYPO_TOLERANCE,
+            min_rating: None,
+            kinds: vec![SearchKind::Events],
+            events_until: Some(now + 500),
+        };
+        let res = search(&db, &req).unwrap();
+        assert!(res.visible.is_empty());
+        let ids: Vec<_> = res.events.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["soon", "later"]);
+
+        req.kinds = vec![SearchKind::Entries, SearchKind::Events];
+        let res = search(&db, &req).unwrap();
+        assert_eq!(res.visible.len(), 1);
+        assert_eq!(res.events.len(), 2);
+    }
+
+    #[test]
+    fn search_with_min_rating() {
+        let mut db = MockDb::new();
+        db.entries = vec![
+            Entry::build().id("a").finish(),
+            Entry::build().id("b").finish(),
+            Entry::build().id("c").finish(),
+        ];
+        let rating = |id: &str, entry_id: &str, value, context| Rating {
+            id: id.into(),
+            entry_id: entry_id.into(),
+            created: 0,
+            updated: None,
+            title: "".into(),
+            value,
+            context,
+            source: None,
+            created_by: None,
+        };
+        db.ratings = vec![
+            rating("1", "a", 2, RatingContext::Diversity),
+            rating("2", "a", -1, RatingContext::Fairness),
+            rating("3", "b", 1, RatingContext::Fairness),
+        ];
+        let mut req = SearchRequest {
+            bbox: Bbox {
+                south_west: Coordinate {
+                    lat: -10.0,
+                    lng: -10.0,
+                },
+                north_east: Coordinate {
+                    lat: 10.0,
+                    lng: 10.0,
+                },
+            },
+            categories: None,
+            text: "".into(),
+            tags: vec![],
+            typo_tolerance: filter::DEFAULT_TYPO_TOLERANCE,
+            min_rating: Some(MinRating {
+                context: Some(RatingContext::Fairness),
+                value: 0.0,
+            }),
+            kinds: vec![SearchKind::Entries],
+            events_until: None,
+        };
+        let ids =
+            |res: SearchResult| -> Vec<String> { res.visible.into_iter().map(|e| e.id).collect() };
+        assert_eq!(ids(search(&db, &req).unwrap()), vec!["b"]);
+
+        req.min_rating = Some(MinRating {
+            context: Some(RatingContext::Diversity),
+            value: 1.0,
+        });
+        assert_eq!(ids(search(&db, &req).unwrap()), vec!["a"]);
+
+        req.min_rating = Some(MinRating {
+            context: None,
+            value: 0.0,
+        });
+        assert_eq!(ids(search(&db, &req).unwrap()), vec!["a", "b"]);
+
+        req.min_rating = None;
+        assert_eq!(ids(search(&db, &req).unwrap()).len(), 3);
+    }
+
+    #[test]
+    fn parse_search_kind() {
+        assert_eq!(
+            "entries".parse::<SearchKind>().unwrap(),
+            SearchKind::Entries
+        );
+        assert_eq!("events".parse::<SearchKind>().unwrap(), SearchKind::Events);
+        assert!("foo".parse::<SearchKind>().is_err());
+    }
 }
@@ -1,5 +1,8 @@
 use super::*;
-use crate::core::{usecases, util::filter::InBBox};
+use crate::core::{
+    usecases,
+    util::{filter::InBBox, sort},
+};
 use std::result;
 
 //TODO: move tests to corresponding usecase
@@ -64,30 +67,86 @@ impl Id for Organization {
     }
 }
 
+impl Id for UserToken {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id for Report {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id for PendingNotification {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id for OutboxMail {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id for Webhook {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Id for WebhookDelivery {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
 pub struct MockDb {
     pub entries: Vec<Entry>,
+    pub pending_entries: Vec<Entry>,
     pub events: Vec<Event>,
     pub categories: Vec<Category>,
     pub tags: Vec<Tag>,
     pub users: Vec<User>,
+    pub login_histories: Vec<LoginHistory>,
+    pub user_tokens: Vec<UserToken>,
     pub ratings: Vec<Rating>,
     pub comments: Vec<Comment>,
     pub bbox_subscriptions: Vec<BboxSubscription>,
     pub orgs: Vec<Organization>,
+    pub reports: Vec<Report>,
+    pub pending_notifications: Vec<PendingNotification>,
+    pub outbox_mails: Vec<OutboxMail>,
+    pub entry_versions: Vec<Entry>,
+    pub contact_opt_outs: Vec<String>,
+    pub webhooks: Vec<Webhook>,
+    pub webhook_deliveries: Vec<WebhookDelivery>,
 }
 
 impl MockDb {
     pub fn new() -> MockDb {
         MockDb {
             entries: vec![],
+            pending_entries: vec![],
             events: vec![],
             categories: vec![],
             tags: vec![],
             users: vec![],
+            login_histories: vec![],
+            user_tokens: vec![],
             ratings: vec![],
             comments: vec![],
             bbox_subscriptions: vec![],
             orgs: vec![],
+            reports: vec![],
+            pending_notifications: vec![],
+            outbox_mails: vec![],
+            entry_versions: vec![],
+            contact_opt_outs: vec![],
+            webhooks: vec![],
+            webhook_deliveries: vec![],
         }
     }
 }
@@ -130,13 +189,34 @@ impl EntryGateway for MockDb {
     fn create_entry(&mut self, e: Entry) -> RepoResult<()> {
         create(&mut self.entries, e)
     }
+    fn create_pending_entry(&mut self, e: Entry) -> RepoResult<()> {
+        create(&mut self.pending_entries, e)
+    }
     fn get_entry(&self, id: &str) -> RepoResult<Entry> {
         get(&self.entries, id)
     }
+    fn get_entry_version(&self, id: &str, version: u64) -> RepoResult<Entry> {
+        self.entries
+            .iter()
+            .chain(&self.entry_versions)
+            .find(|e| e.id == id && e.version == version)
+            .cloned()
+            .ok_or(RepoError::NotFound)
+    }
     fn all_entries(&self) -> RepoResult<Vec<Entry>> {
         Ok(self.entries.clone())
     }
 
+    fn pending_entries(&self) -> RepoResult<Vec<Entry>> {
+        Ok(self.pending_entries.clone())
+    }
+
+    fn approve_entry(&mut self, id: &str) -> RepoResult<()> {
+        let e = get(&self.pending_entries, id)?;
+        delete(&mut self.pending_entries, id)?;
+        create(&mut self.entries, e)
+    }
+
     fn get_entries_by_bbox(&self, bbox: &Bbox) -> RepoResult<Vec<Entry>> {
         Ok(self
             .entries
@@ -146,9 +226,15 @@ impl EntryGateway for MockDb {
             .collect())
     }
     fn update_entry(&mut self, e: &Entry) -> RepoResult<()> {
+        let old = get(&self.entries, &e.id)?;
+        self.entry_versions.push(old);
         update(&mut self.entries, e)
     }
 
+    fn archive_entry(&mut self, id: &str, _: u64) -> RepoResult<()> {
+        delete(&mut self.entries, id).or_else(|_| delete(&mut self.pending_entries, id))
+    }
+
     fn import_multiple_entries(&mut self, entries: &[Entry]) -> RepoResult<()> {
         for e in entries.iter() {
             self.create_entry(e.clone())?;
@@ -203,6 +289,38 @@ impl UserGateway for MockDb {
     }
 
     fn delete_user(&mut self, u_id: &str) -> RepoResult<()> {
+        let users: Vec<_> = self
+            .users
+            .iter()
+            .filter(|u| u.id == u_id)
+            .map(|u| (u.username.clone(), u.email.clone()))
+            .collect();
+        for (username, email) in &users {
+            self.outbox_mails.retain(|m| !m.recipients.contains(email));
+            let by_user = |x: &Option<String>| x.as_ref() == Some(username);
+            self.bbox_subscriptions.retain(|s| s.username != *username);
+            self.pending_notifications
+                .retain(|n| n.username != *username);
+            self.user_tokens.retain(|t| t.username != *username);
+            self.login_histories.retain(|h| h.username != *username);
+            for e in self.events.iter_mut().filter(|e| by_user(&e.created_by)) {
+                e.created_by = None;
+            }
+            for r in self.ratings.iter_mut().filter(|r| by_user(&r.created_by)) {
+                r.created_by = None;
+            }
+            for c in self.comments.iter_mut().filter(|c| by_user(&c.created_by)) {
+                c.created_by = None;
+            }
+            for r in self.reports.iter_mut() {
+                if by_user(&r.reported_by) {
+                    r.reported_by = None;
+                }
+                if by_user(&r.resolved_by) {
+                    r.resolved_by = None;
+                }
+            }
+        }
         self.users = self
             .users
             .clone()
@@ -215,6 +333,101 @@ impl UserGateway for MockDb {
     fn update_user(&mut self, u: &User) -> RepoResult<()> {
         update(&mut self.users, u)
     }
+
+    fn get_login_history(&self, username: &str) -> RepoResult<LoginHistory> {
+        self.login_histories
+            .iter()
+            .find(|h| h.username == username)
+            .cloned()
+            .ok_or(RepoError::NotFound)
+    }
+
+    fn update_login_history(&mut self, h: &LoginHistory) -> RepoResult<()> {
+        self.login_histories.retain(|x| x.username != h.username);
+        self.login_histories.push(h.clone());
+        Ok(())
+    }
+}
+
+impl UserTokenGateway for MockDb {
+    fn create_user_token(&mut self, t: UserToken) -> RepoResult<()> {
+        create(&mut self.user_tokens, t)
+    }
+
+    fn get_user_token(&self, id: &str) -> RepoResult<UserToken> {
+        get(&self.user_tokens, id)
+    }
+
+    fn get_user_token_by_hash(&self, token_hash: &str) -> RepoResult<UserToken> {
+        self.user_tokens
+            .iter()
+            .find(|t| t.token_hash == token_hash)
+            .cloned()
+            .ok_or(RepoError::NotFound)
+    }
+
+    fn user_tokens(&self, username: &str) -> RepoResult<Vec<UserToken>> {
+        Ok(self
+            .user_tokens
+            .iter()
+            .filter(|t| t.username == username)
+            .cloned()
+            .collect())
+    }
+
+    fn delete_user_token(&mut self, id: &str) -> RepoResult<()> {
+        delete(&mut self.user_tokens, id)
+    }
+}
+
+impl RatingGateway for MockDb {
+    fn create_rating(&mut self, r: Rating) -> RepoResult<()> {
+        create(&mut self.ratings, r)
+    }
+
+    fn get_rating(&self, id: &str) -> RepoResult<Rating> {
+        get(&self.ratings, id)
+    }
+
+    fn get_rating_of_user(
+        &self,
+        entry_id: &str,
+        context: &RatingContext,
+        username: &str,
+    ) -> RepoResult<Rating> {
+        self.ratings
+            .iter()
+            .find(|r| {
+                r.entry_id == entry_id
+                    && r.context == *context
+                    && r.created_by.as_ref().map(String::as_str) == Some(username)
+            })
+            .cloned()
+            .ok_or(RepoError::NotFound)
+    }
+
+    fn all_ratings(&self) -> RepoResult<Vec<Rating>> {
+        Ok(self.ratings.clone())
+    }
+
+    fn ratings_of_entry(&self, entry_id: &str) -> RepoResult<Vec<Rating>> {
+        Ok(self
+            .ratings
+            .iter()
+            .filter(|r| r.entry_id == entry_id)
+            .cloned()
+            .collect())
+    }
+
+    fn update_rating(&mut self, r: &Rating) -> RepoResult<()> {
+        update(&mut self.ratings, r)
+    }
+
+    fn delete_rating(&mut self, id: &str) -> RepoResult<()> {
+        delete(&mut self.ratings, id)?;
+        self.comments.retain(|c| c.rating_id != id);
+        Ok(())
+    }
 }
 
 impl CommentGateway for MockDb {
@@ -222,9 +435,165 @@ impl CommentGateway for MockDb {
         create(&mut self.comments, c)
     }
 
+    fn get_comment(&self, id: &str) -> RepoResult<Comment> {
+        get(&self.comments, id)
+    }
+
     fn all_comments(&self) -> RepoResult<Vec<Comment>> {
         Ok(self.comments.clone())
     }
+
+    fn comments_of_rating(&self, rating_id: &str) -> RepoResult<Vec<Comment>> {
+        Ok(self
+            .comments
+            .iter()
+            .filter(|c| c.rating_id == rating_id)
+            .cloned()
+            .collect())
+    }
+
+    fn update_comment(&mut self, c: &Comment) -> RepoResult<()> {
+        update(&mut self.comments, c)
+    }
+
+    fn delete_comments(&mut self, ids: &[String]) -> RepoResult<()> {
+        if ids.iter().any(|id| get(&self.comments, id).is_err()) {
+            return Err(RepoError::NotFound);
+        }
+        self.comments.retain(|c| !ids.contains(&c.id));
+        Ok(())
+    }
+}
+
+impl ReportGateway for MockDb {
+    fn create_report(&mut self, r: Report) -> RepoResult<()> {
+        create(&mut self.reports, r)
+    }
+
+    fn get_report(&self, id: &str) -> RepoResult<Report> {
+        get(&self.reports, id)
+    }
+
+    fn all_reports(&self) -> RepoResult<Vec<Report>> {
+        Ok(self.reports.clone())
+    }
+
+    fn update_report(&mut self, r: &Report) -> RepoResult<()> {
+        update(&mut self.reports, r)
+    }
+}
+
+impl NotificationGateway for MockDb {
+    fn create_pending_notification(&mut self, n: PendingNotification) -> RepoResult<()> {
+        create(&mut self.pending_notifications, n)
+    }
+
+    fn all_pending_notifications(&self) -> RepoResult<Vec<PendingNotification>> {
+        Ok(self.pending_notifications.clone())
+    }
+
+    fn delete_pending_notification(&mut self, id: &str) -> RepoResult<()> {
+        delete(&mut self.pending_notifications, id)
+    }
+
+    fn create_contact_opt_out(&mut self, email: &str) -> RepoResult<()> {
+        if self.is_contact_opted_out(email)? {
+            return Err(RepoError::AlreadyExists);
+        }
+        self.contact_opt_outs.push(email.to_lowercase());
+        Ok(())
+    }
+
+    fn is_contact_opted_out(&self, email: &str) -> RepoResult<bool> {
+        Ok(self
+            .contact_opt_outs
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(email)))
+    }
+}
+
+impl OutboxGateway for MockDb {
+    fn create_outbox_mail(&mut self, m: OutboxMail) -> RepoResult<()> {
+        create(&mut self.outbox_mails, m)
+    }
+
+    fn get_outbox_mail(&self, id: &str) -> RepoResult<OutboxMail> {
+        get(&self.outbox_mails, id)
+    }
+
+    fn all_outbox_mails(&self) -> RepoResult<Vec<OutboxMail>> {
+        Ok(self.outbox_mails.clone())
+    }
+
+    fn due_outbox_mails(&self, now: u64) -> RepoResult<Vec<OutboxMail>> {
+        let mut mails: Vec<_> = self
+            .outbox_mails
+            .iter()
+            .filter(|m| m.status == MailStatus::Pending && m.next_attempt <= now)
+            .cloned()
+            .collect();
+        mails.sort_by_key(|m| m.created);
+        Ok(mails)
+    }
+
+    fn update_outbox_mail(&mut self, m: &OutboxMail) -> RepoResult<()> {
+        update(&mut self.outbox_mails, m)
+    }
+
+    fn delete_outbox_mail(&mut self, id: &str) -> RepoResult<()> {
+        delete(&mut self.outbox_mails, id)
+    }
+}
+
+impl WebhookGateway for MockDb {
+    fn create_webhook(&mut self, w: Webhook) -> RepoResult<()> {
+        create(&mut self.webhooks, w)
+    }
+
+    fn get_webhook(&self, id: &str) -> RepoResult<Webhook> {
+        get(&self.webhooks, id)
+    }
+
+    fn all_webhooks(&self) -> RepoResult<Vec<Webhook>> {
+        Ok(self.webhooks.clone())
+    }
+
+    fn delete_webhook(&mut self, id: &str) -> RepoResult<()> {
+        delete(&mut self.webhooks, id)?;
+        self.webhook_deliveries.retain(|d| d.webhook_id != id);
+        Ok(())
+    }
+
+    fn create_webhook_delivery(&mut self, d: WebhookDelivery) -> RepoResult<()> {
+        create(&mut self.webhook_deliveries, d)
+    }
+
+    fn get_webhook_delivery(&self, id: &str) -> RepoResult<WebhookDelivery> {
+        get(&self.webhook_deliveries, id)
+    }
+
+    fn all_webhook_deliveries(&self) -> RepoResult<Vec<WebhookDelivery>> {
+        Ok(self.webhook_deliveries.clone())
+    }
+
+    fn due_webhook_deliveries(&self, now: u64) -> RepoResult<Vec<WebhookDelivery>> {
+        let mut deliveries: Vec<_> = self
+            .webhook_deliveries
+            .iter()
+            .filter(|d| d.status == WebhookDeliveryStatus::Pending && d.next_attempt <= now)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|d| d.created);
+        Ok(deliveries)
+    }
+
+    fn update_webhook_delivery(&mut self, d: &WebhookDelivery) -> RepoResult<()> {
+        update(&mut self.webhook_deliveries, d)
+    }
+
+    fn delete_webhook_delivery(&mut self, id: &str) -> RepoResult<()> {
+        delete(&mut self.webhook_deliveries, id)
+    }
 }
 
 impl OrganizationGateway for MockDb {
@@ -248,6 +617,21 @@ impl OrganizationGateway for MockDb {
     }
 }
 
+impl AvgRatingGateway for MockDb {
+    fn get_avg_ratings(&self, entry_id: &str) -> RepoResult<AvgRatings> {
+        Ok(sort::avg_ratings(entry_id, &self.ratings))
+    }
+    fn all_avg_ratings(&self) -> RepoResult<Vec<AvgRatings>> {
+        let mut entry_ids: Vec<_> = self.ratings.iter().map(|r| r.entry_id.as_str()).collect();
+        entry_ids.sort();
+        entry_ids.dedup();
+        Ok(entry_ids
+            .into_iter()
+            .map(|id| sort::avg_ratings(id, &self.ratings))
+            .collect())
+    }
+}
+
 impl Db for MockDb {
     fn create_tag_if_it_does_not_exist(&mut self, e: &Tag) -> RepoResult<()> {
         if let Err(err) = create(&mut self.tags, e.clone()) {
@@ -273,10 +657,6 @@ impl Db for MockDb {
         Ok(())
     }
 
-    fn create_rating(&mut self, r: Rating) -> RepoResult<()> {
-        create(&mut self.ratings, r)
-    }
-
     fn create_bbox_subscription(&mut self, s: &BboxSubscription) -> RepoResult<()> {
         create(&mut self.bbox_subscriptions, s.clone())
     }
@@ -289,14 +669,23 @@ impl Db for MockDb {
         Ok(self.tags.clone())
     }
 
-    fn all_ratings(&self) -> RepoResult<Vec<Rating>> {
-        Ok(self.ratings.clone())
-    }
-
     fn all_bbox_subscriptions(&self) -> RepoResult<Vec<BboxSubscription>> {
         Ok(self.bbox_subscriptions.clone())
     }
 
+    fn get_bbox_subscription(&self, id: &str) -> RepoResult<BboxSubscription> {
+        get(&self.bbox_subscriptions, id)
+    }
+
+    fn bbox_subscriptions_by_username(&self, username: &str) -> RepoResult<Vec<BboxSubscription>> {
+        Ok(self
+            .bbox_subscriptions
+            .iter()
+            .filter(|s| s.username == username)
+            .cloned()
+            .collect())
+    }
+
     fn delete_bbox_subscription(&mut self, s_id: &str) -> RepoResult<()> {
         self.bbox_subscriptions = self
             .bbox_subscriptions
@@ -319,6 +708,7 @@ fn receive_different_user() {
             email: "a@foo.bar".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         },
         User {
             id: "2".into(),
@@ -327,12 +717,37 @@ fn receive_different_user() {
             email: "b@foo.bar".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         },
     ];
     assert!(get_user(&mut db, "a", "b").is_err());
     assert!(get_user(&mut db, "a", "a").is_ok());
 }
 
+#[test]
+fn update_user_settings() {
+    let mut db = MockDb::new();
+    db.users = vec![User {
+        id: "1".into(),
+        username: "a".into(),
+        password: "a".into(),
+        email: "a@foo.bar".into(),
+        email_confirmed: true,
+        role: Role::Guest,
+        language: None,
+    }];
+    let settings = |lang: &str| usecases::UserSettings {
+        language: Some(lang.into()),
+    };
+    assert!(usecases::update_user_settings(&mut db, "b", "a", settings("en")).is_err());
+    assert!(usecases::update_user_settings(&mut db, "a", "a", settings("E N")).is_err());
+    usecases::update_user_settings(&mut db, "a", "a", settings("en")).unwrap();
+    assert_eq!(db.users[0].language, Some("en".into()));
+    let reset = usecases::UserSettings { language: None };
+    usecases::update_user_settings(&mut db, "a", "a", reset).unwrap();
+    assert_eq!(db.users[0].language, None);
+}
+
 #[test]
 fn create_bbox_subscription() {
     let mut db = MockDb::new();
@@ -356,6 +771,7 @@ fn create_bbox_subscription() {
             email: "abc@abc.de".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         })
         .is_ok());
     assert!(usecases::subscribe_to_bbox(
@@ -404,13 +820,19 @@ fn modify_bbox_subscription() {
             email: "abc@abc.de".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         })
         .is_ok());
 
     let bbox_subscription = BboxSubscription {
         id: "123".into(),
+        name: None,
         bbox: bbox_old,
         username: "a".into(),
+        categories: vec![],
+        tags: vec![],
+        changes: vec![],
+        delivery: Delivery::Instant,
     };
     db.create_bbox_subscription(&bbox_subscription.clone())
         .unwrap();
@@ -468,12 +890,18 @@ fn get_bbox_subscriptions() {
             email: "abc@abc.de".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         })
         .is_ok());
     let bbox_subscription = BboxSubscription {
         id: "1".into(),
+        name: None,
         bbox: bbox1,
         username: "a".into(),
+        categories: vec![],
+        tags: vec![],
+        changes: vec![],
+        delivery: Delivery::Instant,
     };
     assert!(db
         .create_bbox_subscription(&bbox_subscription.clone())
@@ -488,12 +916,18 @@ fn get_bbox_subscriptions() {
             email: "abc@abc.de".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         })
         .is_ok());
     let bbox_subscription2 = BboxSubscription {
         id: "2".into(),
+        name: None,
         bbox: bbox2,
         username: "b".into(),
+        categories: vec![],
+        tags: vec![],
+        changes: vec![],
+        delivery: Delivery::Instant,
     };
     assert!(db
         .create_bbox_subscription(&bbox_subscription2.clone())
@@ -504,7 +938,7 @@ fn get_bbox_subscriptions() {
 }
 
 #[test]
-fn email_addresses_by_coordinate() {
+fn notify_subscribers() {
     let mut db = MockDb::new();
     let bbox_new = Bbox {
         north_east: Coordinate {
@@ -523,6 +957,7 @@ fn email_addresses_by_coordinate() {
         email: "abc@abc.de".into(),
         email_confirmed: true,
         role: Role::Guest,
+        language: None,
     })
     .unwrap();
 
@@ -533,13 +968,31 @@ fn email_addresses_by_coordinate() {
     )
     .unwrap();
 
-    let email_addresses = usecases::email_addresses_by_coordinate(&mut db, &5.0, &5.0).unwrap();
-    assert_eq!(email_addresses.len(), 1);
-    assert_eq!(email_addresses[0], "abc@abc.de");
+    let change = usecases::Change {
+        kind: ChangeKind::NewEntry,
+        id: "e",
+        title: "Foo",
+        categories: &[],
+        tags: &[],
+    };
+    let x = Coordinate { lat: 5.0, lng: 5.0 };
+    let recipients = usecases::notify_subscribers(&mut db, &x, &change).unwrap();
+    assert_eq!(recipients.len(), 1);
+    assert_eq!(recipients[0].email, "abc@abc.de");
+    assert_eq!(recipients[0].subscriptions[0].username, "a");
+
+    let x = Coordinate {
+        lat: 20.0,
+        lng: 20.0,
+    };
+    let no_email_addresses = usecases::notify_subscribers(&mut db, &x, &change).unwrap();
+    assert_eq!(no_email_addresses.len(), 0);
 
-    let no_email_addresses =
-        usecases::email_addresses_by_coordinate(&mut db, &20.0, &20.0).unwrap();
+    db.bbox_subscriptions[0].changes = vec![ChangeKind::UpdatedEntry];
+    let x = Coordinate { lat: 5.0, lng: 5.0 };
+    let no_email_addresses = usecases::notify_subscribers(&mut db, &x, &change).unwrap();
     assert_eq!(no_email_addresses.len(), 0);
+    assert!(db.pending_notifications.is_empty());
 }
 
 #[test]
@@ -555,6 +1008,7 @@ fn delete_user() {
             email: "abc@abc.de".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         })
         .is_ok());
     let username = "b".to_string();
@@ -567,12 +1021,18 @@ fn delete_user() {
             email: "abcd@abcd.de".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         })
         .is_ok());
     assert_eq!(db.users.len(), 2);
+    for to in &["abc@abc.de", "abcd@abcd.de"] {
+        usecases::enqueue_mail(&mut db, &[to.to_string()], "subject", "mail").unwrap();
+    }
 
     assert!(usecases::delete_user(&mut db, "1", "1").is_ok());
     assert_eq!(db.users.len(), 1);
+    assert_eq!(db.outbox_mails.len(), 1);
+    assert_eq!(db.outbox_mails[0].recipients, vec!["abcd@abcd.de"]);
 }
 
 #[test]
@@ -585,6 +1045,7 @@ fn receive_event_with_creators_email() {
         email: "abc@abc.de".into(),
         email_confirmed: true,
         role: Role::Guest,
+        language: None,
     })
     .unwrap();
     db.create_event(Event {
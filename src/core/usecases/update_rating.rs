@@ -0,0 +1,120 @@
+use crate::core::prelude::*;
+use chrono::*;
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct UpdateRating {
+    pub title  : String,
+    pub value  : i8,
+    pub source : Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UpdateComment {
+    pub text: String,
+}
+
+fn check_author(created_by: &Option<String>, username: &str) -> Result<()> {
+    if created_by.as_ref().map(String::as_str) != Some(username) {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    Ok(())
+}
+
+/// Only the author is allowed to change a rating.
+pub fn update_rating<D: Db>(db: &mut D, username: &str, id: &str, u: UpdateRating) -> Result<()> {
+    let rating = db.get_rating(id)?;
+    check_author(&rating.created_by, username)?;
+    if u.value > 2 || u.value < -1 {
+        return Err(Error::Parameter(ParameterError::RatingValue));
+    }
+    db.update_rating(&Rating {
+        updated: Some(Utc::now().timestamp() as u64),
+        title: u.title,
+        value: u.value,
+        source: u.source,
+        ..rating
+    })?;
+    Ok(())
+}
+
+/// Only the author is allowed to change a comment.
+pub fn update_comment<D: Db>(db: &mut D, username: &str, id: &str, u: UpdateComment) -> Result<()> {
+    let comment = db.get_comment(id)?;
+    check_author(&comment.created_by, username)?;
+    if u.text.is_empty() {
+        return Err(Error::Parameter(ParameterError::EmptyComment));
+    }
+    db.update_comment(&Comment {
+        text: u.text,
+        ..comment
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn rating(id: &str, created_by: Option<&str>) -> Rating {
+        Rating {
+            id: id.into(),
+            entry_id: "foo".into(),
+            created: 0,
+            updated: None,
+            title: "title".into(),
+            value: 1,
+            context: RatingContext::Fairness,
+            source: None,
+            created_by: created_by.map(Into::into),
+        }
+    }
+
+    fn update(value: i8) -> UpdateRating {
+        UpdateRating {
+            title: "new title".into(),
+            value,
+            source: Some("source".into()),
+        }
+    }
+
+    #[test]
+    fn update_own_rating() {
+        let mut db = MockDb::new();
+        db.ratings = vec![rating("1", Some("alice"))];
+        update_rating(&mut db, "alice", "1", update(2)).unwrap();
+        assert_eq!(db.ratings[0].value, 2);
+        assert_eq!(db.ratings[0].title, "new title");
+        assert_eq!(db.ratings[0].context, RatingContext::Fairness);
+        assert!(update_rating(&mut db, "alice", "1", update(3)).is_err());
+        assert!(update_rating(&mut db, "alice", "2", update(0)).is_err());
+    }
+
+    #[test]
+    fn update_rating_of_others() {
+        let mut db = MockDb::new();
+        db.ratings = vec![rating("1", Some("alice")), rating("2", None)];
+        assert!(update_rating(&mut db, "bob", "1", update(0)).is_err());
+        assert!(update_rating(&mut db, "bob", "2", update(0)).is_err());
+        assert_eq!(db.ratings[0].value, 1);
+    }
+
+    #[test]
+    fn update_own_comment() {
+        let mut db = MockDb::new();
+        db.comments = vec![Comment {
+            id: "1".into(),
+            created: 0,
+            text: "old".into(),
+            rating_id: "r".into(),
+            parent_id: None,
+            created_by: Some("alice".into()),
+        }];
+        let text = |t: &str| UpdateComment { text: t.into() };
+        assert!(update_comment(&mut db, "bob", "1", text("new")).is_err());
+        assert!(update_comment(&mut db, "alice", "1", text("")).is_err());
+        update_comment(&mut db, "alice", "1", text("new")).unwrap();
+        assert_eq!(db.comments[0].text, "new");
+    }
+}
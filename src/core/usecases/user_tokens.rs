@@ -0,0 +1,131 @@
+use super::{login, Login};
+use crate::core::prelude::*;
+use chrono::*;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Access tokens expire after 90 days.
+pub const USER_TOKEN_LIFETIME: u64 = 90 * 24 * 60 * 60;
+
+/// The hex encoded SHA-256 hash that is stored instead of the token.
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Issues a new access token if the credentials are valid.
+/// The secret token is returned once and only its hash is stored.
+pub fn create_user_token<D: Db>(db: &mut D, credentials: &Login) -> Result<(UserToken, String)> {
+    let username = login(db, credentials)?;
+    let token = Uuid::new_v4().to_simple_ref().to_string();
+    let created = Utc::now().timestamp() as u64;
+    let t = UserToken {
+        id: Uuid::new_v4().to_simple_ref().to_string(),
+        token_hash: hash_token(&token),
+        username,
+        created,
+        expires: created + USER_TOKEN_LIFETIME,
+    };
+    db.create_user_token(t.clone())?;
+    Ok((t, token))
+}
+
+pub fn user_tokens<D: Db>(db: &D, username: &str) -> Result<Vec<UserToken>> {
+    let mut tokens = db.user_tokens(username)?;
+    tokens.sort_by_key(|t| t.created);
+    Ok(tokens)
+}
+
+pub fn revoke_user_token<D: Db>(db: &mut D, username: &str, id: &str) -> Result<()> {
+    let t = db.get_user_token(id)?;
+    if t.username != username {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    db.delete_user_token(id)?;
+    Ok(())
+}
+
+/// Returns the name of the user the token belongs to.
+pub fn authenticate_user_token<D: Db>(db: &D, token: &str) -> Result<String> {
+    authenticate_user_token_at(db, token, Utc::now().timestamp() as u64)
+}
+
+fn authenticate_user_token_at<D: Db>(db: &D, token: &str, now: u64) -> Result<String> {
+    match db.get_user_token_by_hash(&hash_token(token)) {
+        Ok(ref t) if t.expires <= now => Err(Error::Parameter(ParameterError::Unauthorized)),
+        Ok(t) => Ok(t.username),
+        Err(RepoError::NotFound) => Err(Error::Parameter(ParameterError::Unauthorized)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+    use pwhash::bcrypt;
+    use serde_json;
+
+    fn create_user(db: &mut MockDb, username: &str) {
+        db.create_user(User {
+            id: username.into(),
+            username: username.into(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: format!("{}@bar", username),
+            email_confirmed: true,
+            role: Role::User,
+            language: None,
+        })
+        .unwrap();
+    }
+
+    fn credentials(username: &str, password: &str) -> Login {
+        serde_json::from_str(&format!(
+            r#"{{"username":"{}","password":"{}"}}"#,
+            username, password
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn issue_and_revoke_tokens() {
+        let mut db = MockDb::new();
+        create_user(&mut db, "foo");
+        create_user(&mut db, "bar");
+        assert!(create_user_token(&mut db, &credentials("foo", "wrong")).is_err());
+        let (t1, token1) = create_user_token(&mut db, &credentials("foo", "secret")).unwrap();
+        let (_, token2) = create_user_token(&mut db, &credentials("foo", "secret")).unwrap();
+        assert_ne!(token1, token2);
+        assert_eq!(user_tokens(&db, "foo").unwrap().len(), 2);
+        assert!(user_tokens(&db, "bar").unwrap().is_empty());
+        assert_eq!(authenticate_user_token(&db, &token1).unwrap(), "foo");
+        assert!(db.user_tokens.iter().all(|t| t.token_hash != token1));
+        assert!(authenticate_user_token(&db, &t1.token_hash).is_err());
+
+        match revoke_user_token(&mut db, "bar", &t1.id) {
+            Err(Error::Parameter(ParameterError::Forbidden)) => {}
+            _ => panic!("only the owner can revoke a token"),
+        }
+        revoke_user_token(&mut db, "foo", &t1.id).unwrap();
+        match authenticate_user_token(&db, &token1) {
+            Err(Error::Parameter(ParameterError::Unauthorized)) => {}
+            _ => panic!("revoked token must be rejected"),
+        }
+        assert_eq!(authenticate_user_token(&db, &token2).unwrap(), "foo");
+    }
+
+    #[test]
+    fn reject_expired_tokens() {
+        let mut db = MockDb::new();
+        create_user(&mut db, "foo");
+        let (t, token) = create_user_token(&mut db, &credentials("foo", "secret")).unwrap();
+        assert_eq!(t.expires, t.created + USER_TOKEN_LIFETIME);
+        assert!(authenticate_user_token_at(&db, &token, t.expires - 1).is_ok());
+        match authenticate_user_token_at(&db, &token, t.expires) {
+            Err(Error::Parameter(ParameterError::Unauthorized)) => {}
+            _ => panic!("expired token must be rejected"),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::core::{prelude::*, usecases::Backoff};
+use chrono::*;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+};
+use url::{Host, Url};
+use uuid::Uuid;
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewWebhook {
+    pub url    : String,
+    /// A random secret is created if it is missing.
+    pub secret : Option<String>,
+    #[serde(default)]
+    pub events : Vec<WebhookEvent>,
+}
+
+fn authorize_org<D: Db>(db: &D, token: &str) -> Result<Organization> {
+    db.get_org_by_api_token(token).map_err(|e| match e {
+        RepoError::NotFound => Error::Parameter(ParameterError::Unauthorized),
+        _ => Error::Repo(e),
+    })
+}
+
+fn is_public_ipv4(ip: &Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // shared address space (RFC 6598)
+        || (a == 100 && b & 0xc0 == 64)
+        // IETF protocol assignments (RFC 6890)
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking (RFC 2544)
+        || (a == 198 && b & 0xfe == 18)
+        || a >= 240)
+}
+
+/// Whether the address can be reached from the internet, i.e.
+/// it is neither a loopback, link-local nor private address.
+/// Webhooks must not be used to reach internal services.
+pub fn is_public_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            // IPv4-mapped and IPv4-compatible addresses (including `::1`)
+            if let Some(ip) = ip.to_ipv4() {
+                return is_public_ipv4(&ip);
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local (RFC 4193)
+                || first & 0xfe00 == 0xfc00
+                // link-local
+                || first & 0xffc0 == 0xfe80
+                // documentation (RFC 3849)
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// Rejects URLs of hosts that resolve to internal addresses.
+/// Hosts that cannot be resolved (yet) are accepted, because
+/// the addresses are checked again before every delivery.
+fn check_public_host(url: &Url, resolve: &Fn(&str, u16) -> Vec<IpAddr>) -> Result<()> {
+    let addresses = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(Host::Domain(domain)) => resolve(domain, url.port_or_known_default().unwrap_or(80)),
+        None => {
+            return Err(Error::Parameter(ParameterError::Url));
+        }
+    };
+    if addresses.iter().all(is_public_address) {
+        Ok(())
+    } else {
+        Err(Error::Parameter(ParameterError::Url))
+    }
+}
+
+/// Registers a URL of the organization that receives the changes.
+/// The host is resolved with `resolve` and must not have internal addresses.
+pub fn create_webhook<D: Db>(
+    db: &mut D,
+    token: &str,
+    w: NewWebhook,
+    resolve: &Fn(&str, u16) -> Vec<IpAddr>,
+) -> Result<Webhook> {
+    let org = authorize_org(db, token)?;
+    let url = Url::parse(w.url.trim()).map_err(|_| ParameterError::Url)?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(Error::Parameter(ParameterError::Url));
+    }
+    check_public_host(&url, resolve)?;
+    let secret = match w.secret {
+        Some(secret) => {
+            if secret.trim().is_empty() {
+                return Err(Error::Parameter(ParameterError::WebhookSecret));
+            }
+            secret
+        }
+        None => Uuid::new_v4().to_simple_ref().to_string(),
+    };
+    let webhook = Webhook {
+        id: Uuid::new_v4().to_simple_ref().to_string(),
+        org_id: org.id,
+        created: Utc::now().timestamp() as u64,
+        url: url.into_string(),
+        secret,
+        events: w.events,
+    };
+    db.create_webhook(webhook.clone())?;
+    Ok(webhook)
+}
+
+/// The webhooks of the organization ordered by their creation.
+pub fn webhooks_of_org<D: Db>(db: &D, token: &str) -> Result<Vec<Webhook>> {
+    let org = authorize_org(db, token)?;
+    let mut webhooks: Vec<_> = db
+        .all_webhooks()?
+        .into_iter()
+        .filter(|w| w.org_id == org.id)
+        .collect();
+    webhooks.sort_by_key(|w| w.created);
+    Ok(webhooks)
+}
+
+/// Deletes the webhook including its undelivered payloads.
+pub fn delete_webhook<D: Db>(db: &mut D, token: &str, id: &str) -> Result<()> {
+    let org = authorize_org(db, token)?;
+    let webhook = db.get_webhook(id)?;
+    if webhook.org_id != org.id {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    db.delete_webhook(id)?;
+    Ok(())
+}
+
+/// Stores the payload for all webhooks that subscribed
+/// to the change and returns their number.
+pub fn enqueue_webhook_deliveries(
+    db: &mut Db,
+    event: WebhookEvent,
+    payload: &str,
+) -> Result<usize> {
+    let now = Utc::now().timestamp() as u64;
+    let webhooks: Vec<_> = db
+        .all_webhooks()?
+        .into_iter()
+        .filter(|w| w.events.is_empty() || w.events.contains(&event))
+        .collect();
+    for w in &webhooks {
+        db.create_webhook_delivery(WebhookDelivery {
+            id: Uuid::new_v4().to_simple_ref().to_string(),
+            webhook_id: w.id.clone(),
+            created: now,
+            event,
+            payload: payload.into(),
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: now,
+            last_error: None,
+        })?;
+    }
+    Ok(webhooks.len())
+}
+
+/// The pending payloads that should be delivered (again)
+/// together with their webhook ordered by their creation.
+pub fn due_webhook_deliveries<D: Db>(db: &D, now: u64) -> Result<Vec<(Webhook, WebhookDelivery)>> {
+    let mut webhooks = HashMap::new();
+    let mut due = vec![];
+    for d in db.due_webhook_deliveries(now)? {
+        if !webhooks.contains_key(&d.webhook_id) {
+            match db.get_webhook(&d.webhook_id) {
+                Ok(w) => {
+                    webhooks.insert(d.webhook_id.clone(), w);
+                }
+                Err(RepoError::NotFound) => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        due.push((webhooks[&d.webhook_id].clone(), d));
+    }
+    Ok(due)
+}
+
+pub fn webhook_delivered<D: Db>(db: &mut D, id: &str) -> Result<()> {
+    db.delete_webhook_delivery(id)?;
+    Ok(())
+}
+
+/// Schedules the next attempt or gives up after too many attempts.
+pub fn webhook_delivery_failed<D: Db>(
+    db: &mut D,
+    id: &str,
+    error: &str,
+    backoff: &Backoff,
+    now: u64,
+) -> Result<()> {
+    let mut d = db.get_webhook_delivery(id)?;
+    d.attempts += 1;
+    d.last_error = Some(error.into());
+    if d.attempts >= backoff.max_attempts {
+        d.status = WebhookDeliveryStatus::Failed;
+    } else {
+        d.next_attempt = now + backoff.delay_after(d.attempts);
+    }
+    db.update_webhook_delivery(&d)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    const BACKOFF: Backoff = Backoff {
+        max_attempts: 2,
+        delay: 60,
+    };
+
+    fn org(id: &str, token: &str) -> Organization {
+        Organization {
+            id: id.into(),
+            name: id.into(),
+            owned_tags: vec![],
+            api_token: token.into(),
+        }
+    }
+
+    /// Resolves the hosts without DNS.
+    fn resolve(host: &str, _: u16) -> Vec<IpAddr> {
+        match host {
+            "intranet.example.org" => vec!["10.0.0.1".parse().unwrap()],
+            "mixed.example.org" => {
+                vec!["93.184.216.34".parse().unwrap(), "fd00::1".parse().unwrap()]
+            }
+            "unknown.example.org" => vec![],
+            _ => vec!["93.184.216.34".parse().unwrap()],
+        }
+    }
+
+    fn new_webhook(url: &str, events: Vec<WebhookEvent>) -> NewWebhook {
+        NewWebhook {
+            url: url.into(),
+            secret: None,
+            events,
+        }
+    }
+
+    #[test]
+    fn register_webhooks_of_organizations() {
+        let mut db = MockDb::new();
+        db.orgs = vec![org("a", "token-a"), org("b", "token-b")];
+        let hook = new_webhook("https://example.org/hook", vec![]);
+        assert!(create_webhook(&mut db, "invalid", hook.clone(), &resolve).is_err());
+        assert!(create_webhook(
+            &mut db,
+            "token-a",
+            new_webhook("ftp://example.org", vec![]),
+            &resolve
+        )
+        .is_err());
+        assert!(create_webhook(
+            &mut db,
+            "token-a",
+            new_webhook("example.org", vec![]),
+            &resolve
+        )
+        .is_err());
+        let mut with_empty_secret = hook.clone();
+        with_empty_secret.secret = Some(" ".into());
+        assert!(create_webhook(&mut db, "token-a", with_empty_secret, &resolve).is_err());
+
+        let w = create_webhook(&mut db, "token-a", hook, &resolve).unwrap();
+        assert_eq!(w.org_id, "a");
+        assert!(!w.secret.is_empty());
+        assert_eq!(webhooks_of_org(&db, "token-a").unwrap(), vec![w.clone()]);
+        assert!(webhooks_of_org(&db, "token-b").unwrap().is_empty());
+
+        match delete_webhook(&mut db, "token-b", &w.id) {
+            Err(Error::Parameter(ParameterError::Forbidden)) => {}
+            x => panic!("unexpected result: {:?}", x),
+        }
+        enqueue_webhook_deliveries(&mut db, WebhookEvent::EntryCreated, "{}").unwrap();
+        assert_eq!(db.webhook_deliveries.len(), 1);
+        delete_webhook(&mut db, "token-a", &w.id).unwrap();
+        assert!(db.webhooks.is_empty());
+        assert!(db.webhook_deliveries.is_empty());
+    }
+
+    #[test]
+    fn distinguish_public_addresses() {
+        for ip in &[
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:2800:220:1::248",
+            "::ffff:93.184.216.34",
+        ] {
+            assert!(is_public_address(&ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd12:3456::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_address(&ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn reject_webhooks_of_internal_hosts() {
+        let mut db = MockDb::new();
+        db.orgs = vec![org("a", "token")];
+        for url in &[
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "https://192.168.0.1/hook",
+            "http://[::1]/hook",
+            "http://intranet.example.org/hook",
+            "http://mixed.example.org/hook",
+        ] {
+            match create_webhook(&mut db, "token", new_webhook(url, vec![]), &resolve) {
+                Err(Error::Parameter(ParameterError::Url)) => {}
+                x => panic!("unexpected result for {}: {:?}", url, x),
+            }
+        }
+        assert!(db.webhooks.is_empty());
+        for url in &[
+            "https://example.org/hook",
+            "http://unknown.example.org/hook",
+        ] {
+            assert!(create_webhook(&mut db, "token", new_webhook(url, vec![]), &resolve).is_ok());
+        }
+    }
+
+    #[test]
+    fn deliver_subscribed_events_with_retries() {
+        let mut db = MockDb::new();
+        db.orgs = vec![org("a", "token")];
+        let all = create_webhook(
+            &mut db,
+            "token",
+            new_webhook("http://a.org", vec![]),
+            &resolve,
+        )
+        .unwrap();
+        let ratings = create_webhook(
+            &mut db,
+            "token",
+            new_webhook("http://b.org", vec![WebhookEvent::RatingCreated]),
+            &resolve,
+        )
+        .unwrap();
+        assert_eq!(
+            enqueue_webhook_deliveries(&mut db, WebhookEvent::EntryUpdated, "{\"a\":1}").unwrap(),
+            1
+        );
+        assert_eq!(
+            enqueue_webhook_deliveries(&mut db, WebhookEvent::RatingCreated, "{\"b\":2}").unwrap(),
+            2
+        );
+        let now = db.webhook_deliveries[0].next_attempt;
+        let due = due_webhook_deliveries(&db, now).unwrap();
+        assert_eq!(due.len(), 3);
+        let (w, d) = due
+            .iter()
+            .find(|(w, _)| w.id == ratings.id)
+            .cloned()
+            .unwrap();
+        assert_eq!(w.url, "http://b.org/");
+        assert_eq!(d.event, WebhookEvent::RatingCreated);
+        assert_eq!(d.payload, "{\"b\":2}");
+
+        webhook_delivered(&mut db, &d.id).unwrap();
+        let failed = &due.iter().find(|(w, _)| w.id == all.id).unwrap().1.id;
+        webhook_delivery_failed(&mut db, failed, "timeout", &BACKOFF, now).unwrap();
+        assert_eq!(due_webhook_deliveries(&db, now).unwrap().len(), 1);
+        assert_eq!(due_webhook_deliveries(&db, now + 60).unwrap().len(), 2);
+        webhook_delivery_failed(&mut db, failed, "refused", &BACKOFF, now + 60).unwrap();
+        let d = db.get_webhook_delivery(failed).unwrap();
+        assert_eq!(d.status, WebhookDeliveryStatus::Failed);
+        assert_eq!(d.last_error, Some("refused".into()));
+        assert_eq!(due_webhook_deliveries(&db, now + 100_000).unwrap().len(), 1);
+    }
+}
@@ -1,5 +1,9 @@
 use super::super::entities::*;
-use super::geo::is_in_bbox;
+use super::{
+    geo::is_in_bbox,
+    query::{Field, Query, Term},
+    text,
+};
 
 pub trait InBBox {
     fn in_bbox(&self, bb: &Bbox) -> bool;
@@ -11,64 +15,191 @@ impl InBBox for Entry {
     }
 }
 
+impl InBBox for Event {
+    fn in_bbox(&self, bb: &Bbox) -> bool {
+        self.location
+            .as_ref()
+            .map(|l| is_in_bbox(&l.lat, &l.lng, bb))
+            .unwrap_or(false)
+    }
+}
+
 pub fn entries_by_category_ids<'a>(ids: &'a [String]) -> impl Fn(&Entry) -> bool + 'a {
     move |e| ids.iter().any(|c| e.categories.iter().any(|x| x == c))
 }
 
+/// The share of characters that may differ between a search word
+/// and a word of an entry to still count as a match, e.g. `0.2`
+/// tolerates one typo within a word of five characters.
+/// A tolerance of `0.0` disables fuzzy matching.
+pub const DEFAULT_TYPO_TOLERANCE: f32 = 0.2;
+
 pub fn entries_by_tags_or_search_text<'a>(
-    text: &'a str,
+    query: &'a Query,
     tags: &'a [String],
+    typo_tolerance: f32,
 ) -> Box<Fn(&Entry) -> bool + 'a> {
+    by_tags_or_search_text(query, tags, typo_tolerance)
+}
+
+pub fn events_by_tags_or_search_text<'a>(
+    query: &'a Query,
+    tags: &'a [String],
+    typo_tolerance: f32,
+) -> Box<Fn(&Event) -> bool + 'a> {
+    by_tags_or_search_text(query, tags, typo_tolerance)
+}
+
+fn by_tags_or_search_text<'a, T>(
+    query: &'a Query,
+    tags: &'a [String],
+    typo_tolerance: f32,
+) -> Box<Fn(&T) -> bool + 'a>
+where
+    for<'t> FoldedTexts: From<&'t T>,
+{
     if !tags.is_empty() {
-        Box::new(entries_by_tags_and_search_text(text, tags))
+        Box::new(by_tags_and_search_text(query, tags, typo_tolerance))
     } else {
-        Box::new(entries_by_search_text(text))
+        Box::new(by_search_text(query, typo_tolerance))
     }
 }
 
-fn entries_by_search_text<'a>(text: &'a str) -> impl Fn(&Entry) -> bool + 'a {
-    let words = to_words(text);
-    move |entry| {
-        ((!text.is_empty()
-            && words.iter().any(|word| {
-                entry.title.to_lowercase().contains(word)
-                    || entry.description.to_lowercase().contains(word)
-                    || entry.tags.iter().any(|tag| tag == word)
-            }))
-            || text.is_empty())
+fn by_search_text<'a, T>(query: &'a Query, typo_tolerance: f32) -> impl Fn(&T) -> bool + 'a
+where
+    for<'t> FoldedTexts: From<&'t T>,
+{
+    move |x| {
+        let x = FoldedTexts::from(x);
+        (query.is_empty() || matches_query(&x, query, typo_tolerance)) && !is_excluded(&x, query)
     }
 }
 
-fn entries_by_tags_and_search_text<'a>(
-    text: &'a str,
+fn by_tags_and_search_text<'a, T>(
+    query: &'a Query,
     tags: &'a [String],
-) -> impl Fn(&Entry) -> bool + 'a {
-    let words = to_words(text);
-    move |entry| {
-        tags.iter()
-            .map(|t| t.to_lowercase())
-            .all(|tag| entry.tags.iter().any(|t| *t == tag))
-            || ((!text.is_empty()
-                && words.iter().any(|word| {
-                    entry.title.to_lowercase().contains(word)
-                        || entry.description.to_lowercase().contains(word)
-                        || entry.tags.iter().any(|tag| tag == word)
-                }))
-                || (text.is_empty() && tags[0] == ""))
+    typo_tolerance: f32,
+) -> impl Fn(&T) -> bool + 'a
+where
+    for<'t> FoldedTexts: From<&'t T>,
+{
+    let tags: Vec<_> = tags.iter().map(|t| text::fold(t)).collect();
+    move |x| {
+        let x = FoldedTexts::from(x);
+        (tags.iter().all(|tag| x.tags.contains(tag))
+            || (!query.is_empty() && matches_query(&x, query, typo_tolerance))
+            || (query.is_empty() && tags[0] == ""))
+            && !is_excluded(&x, query)
+    }
+}
+
+/// The searchable texts of an entry or event in folded form
+struct FoldedTexts {
+    title: String,
+    description: String,
+    tags: Vec<String>,
+    city: String,
+    zip: String,
+}
+
+impl FoldedTexts {
+    fn new(title: &str, description: &str, tags: &[String], address: Option<&Address>) -> Self {
+        let fold_address =
+            |field: Option<&String>| field.map(|x| text::fold(x)).unwrap_or_default();
+        FoldedTexts {
+            title: text::fold(title),
+            description: text::fold(description),
+            tags: tags.iter().map(|t| text::fold(t)).collect(),
+            city: fold_address(address.and_then(|a| a.city.as_ref())),
+            zip: fold_address(address.and_then(|a| a.zip.as_ref())),
+        }
     }
 }
 
-fn to_words(txt: &str) -> Vec<String> {
-    txt.to_lowercase()
-        .split(',')
-        .map(|x| x.to_string())
-        .collect()
+impl<'a> From<&'a Entry> for FoldedTexts {
+    fn from(e: &Entry) -> Self {
+        FoldedTexts::new(
+            &e.title,
+            &e.description,
+            &e.tags,
+            e.location.address.as_ref(),
+        )
+    }
+}
+
+impl<'a> From<&'a Event> for FoldedTexts {
+    fn from(e: &Event) -> Self {
+        FoldedTexts::new(
+            &e.title,
+            e.description.as_ref().map(String::as_str).unwrap_or(""),
+            &e.tags,
+            e.location.as_ref().and_then(|l| l.address.as_ref()),
+        )
+    }
+}
+
+fn matches_query(entry: &FoldedTexts, query: &Query, typo_tolerance: f32) -> bool {
+    query.any_of.iter().any(|terms| {
+        terms
+            .iter()
+            .all(|term| matches_term(entry, term, typo_tolerance))
+    })
+}
+
+fn is_excluded(entry: &FoldedTexts, query: &Query) -> bool {
+    // Excluded terms are never matched fuzzily
+    query
+        .none_of
+        .iter()
+        .any(|term| matches_term(entry, term, 0.0))
+}
+
+fn matches_term(entry: &FoldedTexts, term: &Term, typo_tolerance: f32) -> bool {
+    let txt = &term.text;
+    let exact_match = match term.field {
+        Field::Any => {
+            entry.title.contains(txt) || entry.description.contains(txt) || entry.tags.contains(txt)
+        }
+        Field::Title => entry.title.contains(txt),
+        Field::City => entry.city.contains(txt),
+        Field::Zip => entry.zip.starts_with(txt.as_str()),
+        Field::Tag => entry.tags.contains(txt),
+    };
+    if exact_match || term.phrase || term.field == Field::Zip {
+        return exact_match;
+    }
+
+    let max_typos = text::max_typos(txt, typo_tolerance);
+    if max_typos == 0 {
+        return false;
+    }
+    let words_of = |s: &'_ str| -> Vec<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    };
+    let candidates: Vec<String> = match term.field {
+        Field::Any => words_of(&entry.title)
+            .into_iter()
+            .chain(words_of(&entry.description))
+            .chain(entry.tags.iter().cloned())
+            .collect(),
+        Field::Title => words_of(&entry.title),
+        Field::City => words_of(&entry.city),
+        Field::Tag => entry.tags.clone(),
+        Field::Zip => vec![],
+    };
+    candidates
+        .iter()
+        .any(|w| text::is_similar(txt, w, max_typos))
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::core::util::query;
 
     #[test]
     fn is_in_bounding_box() {
@@ -203,37 +334,53 @@ mod tests {
         let tags2 = vec!["tag1".into(), "tag2".into()];
         let tags3 = vec!["tag2".into()];
         let no_tags = vec![];
-        let aaa = "aaa";
-        let blabla = "blabla";
-        let other = "other";
-        let tag1 = "tag1";
-        let no_string = "";
+        let aaa = query::parse("aaa");
+        let blabla = query::parse("blabla");
+        let other = query::parse("other");
+        let tag1 = query::parse("tag1");
+        let no_string = query::parse("");
 
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&no_string, &no_tags))
+            .filter(&*entries_by_tags_or_search_text(
+                &no_string,
+                &no_tags,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 5);
 
         let x: Vec<_> = entries_without_tags
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&other, &tags1))
+            .filter(&*entries_by_tags_or_search_text(
+                &other,
+                &tags1,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 0);
 
         let x: Vec<_> = entries_without_tags
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&other, &tags2))
+            .filter(&*entries_by_tags_or_search_text(
+                &other,
+                &tags2,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 0);
 
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&other, &tags2))
+            .filter(&*entries_by_tags_or_search_text(
+                &other,
+                &tags2,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 1);
         assert_eq!(x[0].id, "d");
@@ -241,7 +388,11 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&other, &tags3))
+            .filter(&*entries_by_tags_or_search_text(
+                &other,
+                &tags3,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 2);
         assert_eq!(x[0].id, "c");
@@ -250,7 +401,11 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&no_string, &tags1))
+            .filter(&*entries_by_tags_or_search_text(
+                &no_string,
+                &tags1,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 2);
         assert_eq!(x[0].id, "b");
@@ -259,7 +414,11 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&aaa, &no_tags))
+            .filter(&*entries_by_tags_or_search_text(
+                &aaa,
+                &no_tags,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 1);
         assert_eq!(x[0].id, "a");
@@ -267,7 +426,11 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&aaa, &tags2))
+            .filter(&*entries_by_tags_or_search_text(
+                &aaa,
+                &tags2,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 2);
         assert_eq!(x[0].id, "a");
@@ -276,7 +439,11 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&blabla, &tags3))
+            .filter(&*entries_by_tags_or_search_text(
+                &blabla,
+                &tags3,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 3);
         assert_eq!(x[0].id, "b");
@@ -286,7 +453,11 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&tag1, &no_tags))
+            .filter(&*entries_by_tags_or_search_text(
+                &tag1,
+                &no_tags,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 3);
         assert_eq!(x[0].id, "b");
@@ -296,10 +467,53 @@ mod tests {
         let x: Vec<_> = entries
             .iter()
             .cloned()
-            .filter(&*entries_by_tags_or_search_text(&other, &tags1))
+            .filter(&*entries_by_tags_or_search_text(
+                &other,
+                &tags1,
+                DEFAULT_TYPO_TOLERANCE,
+            ))
             .collect();
         assert_eq!(x.len(), 2);
         assert_eq!(x[0].id, "b");
         assert_eq!(x[1].id, "d");
     }
+
+    #[test]
+    fn filter_by_query() {
+        let mut berlin = Entry::build()
+            .id("a")
+            .title("Unverpackt Laden")
+            .description("Café und Bäckerei")
+            .tags(vec!["zerowaste"])
+            .finish();
+        berlin.location.address = Some(Address::build().city("Berlin").zip("10115").finish());
+        let mut munich = Entry::build()
+            .id("b")
+            .title("Bäckerei Kette")
+            .description("laden")
+            .tags(vec!["kette"])
+            .finish();
+        munich.location.address = Some(Address::build().city("München").zip("80331").finish());
+        let entries = vec![berlin, munich];
+        let no_tags = vec![];
+        let search = |q: &str| -> Vec<String> {
+            let q = query::parse(q);
+            let matches = entries_by_tags_or_search_text(&q, &no_tags, DEFAULT_TYPO_TOLERANCE);
+            entries
+                .iter()
+                .filter(|e| matches(e))
+                .map(|e| e.id.clone())
+                .collect()
+        };
+        assert_eq!(search("backerei"), vec!["a", "b"]);
+        assert_eq!(search("backerei -kette"), vec!["a"]);
+        assert_eq!(search("-tag:kette"), vec!["a"]);
+        assert_eq!(search("title:laden"), vec!["a"]);
+        assert_eq!(search(r#""unverpackt laden""#), vec!["a"]);
+        assert_eq!(search(r#""laden unverpackt""#), Vec::<String>::new());
+        assert_eq!(search("city:munchen OR zip:101"), vec!["a", "b"]);
+        assert_eq!(search("laden AND city:munchen"), vec!["b"]);
+        assert_eq!(search("laden AND cafe"), vec!["a"]);
+        assert_eq!(search("tag:zerowaste AND zip:80"), Vec::<String>::new());
+    }
 }
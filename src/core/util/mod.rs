@@ -1,5 +1,9 @@
 pub mod filter;
 pub mod geo;
 pub mod parse;
+pub mod query;
 pub mod sort;
+pub mod spam;
+pub mod text;
+pub mod unsubscribe;
 pub mod validate;
@@ -0,0 +1,270 @@
+use super::text;
+
+/// The part of an entry that a search term refers to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Any,
+    Title,
+    City,
+    Zip,
+    Tag,
+}
+
+const FIELD_PREFIXES: [(&str, Field); 4] = [
+    ("title:", Field::Title),
+    ("city:", Field::City),
+    ("zip:", Field::Zip),
+    ("tag:", Field::Tag),
+];
+
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub field  : Field,
+    /// The folded text of the term
+    pub text   : String,
+    /// Quoted phrases are matched exactly
+    pub phrase : bool,
+}
+
+/// A parsed search query.
+///
+/// An entry matches the query if it matches all terms of at
+/// least one group and none of the excluded terms.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub any_of  : Vec<Vec<Term>>,
+    pub none_of : Vec<Term>,
+}
+
+impl Query {
+    /// Returns true if the query has no positive terms.
+    pub fn is_empty(&self) -> bool {
+        self.any_of.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Term { negated: bool, term: Term },
+}
+
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+fn tokenize(txt: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut rest = txt;
+    loop {
+        rest = rest.trim_start_matches(is_separator);
+        if rest.is_empty() {
+            break;
+        }
+        let negated = rest.starts_with('-');
+        if negated {
+            rest = &rest[1..];
+        }
+        let mut field = Field::Any;
+        for (prefix, f) in &FIELD_PREFIXES {
+            if rest.len() >= prefix.len()
+                && rest.is_char_boundary(prefix.len())
+                && rest[..prefix.len()].eq_ignore_ascii_case(prefix)
+            {
+                field = *f;
+                rest = &rest[prefix.len()..];
+                break;
+            }
+        }
+        let phrase = rest.starts_with('"');
+        let raw = if phrase {
+            rest = &rest[1..];
+            let end = rest.find('"').unwrap_or_else(|| rest.len());
+            let raw = &rest[..end];
+            rest = &rest[(end + 1).min(rest.len())..];
+            raw
+        } else {
+            let end = rest.find(is_separator).unwrap_or_else(|| rest.len());
+            let raw = &rest[..end];
+            rest = &rest[end..];
+            raw
+        };
+        if !negated && !phrase && field == Field::Any {
+            match raw {
+                "AND" => {
+                    tokens.push(Token::And);
+                    continue;
+                }
+                "OR" => {
+                    tokens.push(Token::Or);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        let text = text::fold(raw.trim());
+        if text.is_empty() {
+            continue;
+        }
+        let term = Term {
+            field,
+            text,
+            phrase,
+        };
+        tokens.push(Token::Term { negated, term });
+    }
+    tokens
+}
+
+/// Parses a search query.
+///
+/// Terms are separated by whitespace or commas and are combined with `OR`
+/// unless they are explicitly joined by `AND` (which binds stronger).
+/// Excluded terms apply to the whole query, so `a AND -b c` joins `a`
+/// and `c` with `OR` while `a AND -b AND c` joins them with `AND`.
+/// A term can be a `"quoted phrase"`, can be excluded with a leading `-`
+/// and can be restricted to a field by one of the prefixes
+/// `title:`, `city:`, `zip:` or `tag:`,
+/// e.g. `title:"unverpackt laden" AND city:berlin -kette`.
+pub fn parse(txt: &str) -> Query {
+    let mut query = Query::default();
+    let mut and = false;
+    for token in tokenize(txt) {
+        match token {
+            Token::And => {
+                and = !query.any_of.is_empty();
+            }
+            Token::Or => {
+                and = false;
+            }
+            Token::Term {
+                negated: true,
+                term,
+            } => {
+                query.none_of.push(term);
+                and = false;
+            }
+            Token::Term {
+                negated: false,
+                term,
+            } => {
+                if and {
+                    query.any_of.last_mut().unwrap().push(term);
+                } else {
+                    query.any_of.push(vec![term]);
+                }
+                and = false;
+            }
+        }
+    }
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(field: Field, text: &str, phrase: bool) -> Term {
+        Term {
+            field,
+            text: text.into(),
+            phrase,
+        }
+    }
+
+    #[test]
+    fn parse_words() {
+        let q = parse("Foo, bar  Café");
+        assert_eq!(
+            q.any_of,
+            vec![
+                vec![term(Field::Any, "foo", false)],
+                vec![term(Field::Any, "bar", false)],
+                vec![term(Field::Any, "cafe", false)],
+            ]
+        );
+        assert!(q.none_of.is_empty());
+        assert!(parse("").is_empty());
+        assert!(parse(" , ").is_empty());
+    }
+
+    #[test]
+    fn parse_phrases() {
+        let q = parse(r#""Unverpackt Laden" bar "baz"#);
+        assert_eq!(
+            q.any_of,
+            vec![
+                vec![term(Field::Any, "unverpackt laden", true)],
+                vec![term(Field::Any, "bar", false)],
+                vec![term(Field::Any, "baz", true)],
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_exclusions() {
+        let q = parse(r#"foo -bar -"baz qux" - bla-blubb"#);
+        assert_eq!(
+            q.any_of,
+            vec![
+                vec![term(Field::Any, "foo", false)],
+                vec![term(Field::Any, "bla-blubb", false)],
+            ]
+        );
+        assert_eq!(
+            q.none_of,
+            vec![
+                term(Field::Any, "bar", false),
+                term(Field::Any, "baz qux", true),
+            ]
+        );
+        assert!(parse("-foo").is_empty());
+    }
+
+    #[test]
+    fn parse_and_or() {
+        let q = parse("a AND b c OR d AND -e AND f");
+        assert_eq!(
+            q.any_of,
+            vec![
+                vec![term(Field::Any, "a", false), term(Field::Any, "b", false)],
+                vec![term(Field::Any, "c", false)],
+                vec![term(Field::Any, "d", false), term(Field::Any, "f", false)],
+            ]
+        );
+        assert_eq!(q.none_of, vec![term(Field::Any, "e", false)]);
+        // an excluded term ends the group unless it is followed by `AND`
+        let q = parse("foo AND -bar baz");
+        assert_eq!(
+            q.any_of,
+            vec![
+                vec![term(Field::Any, "foo", false)],
+                vec![term(Field::Any, "baz", false)],
+            ]
+        );
+        assert_eq!(q.none_of, vec![term(Field::Any, "bar", false)]);
+        // operators without operands are ignored
+        let q = parse("AND a OR");
+        assert_eq!(q.any_of, vec![vec![term(Field::Any, "a", false)]]);
+        // lowercase operators are ordinary words
+        assert_eq!(parse("a and b").any_of.len(), 3);
+    }
+
+    #[test]
+    fn parse_fields() {
+        let q = parse(r#"title:"Foo Bar" CITY:Berlin zip:10 -tag:kette foo:bar"#);
+        assert_eq!(
+            q.any_of,
+            vec![
+                vec![term(Field::Title, "foo bar", true)],
+                vec![term(Field::City, "berlin", false)],
+                vec![term(Field::Zip, "10", false)],
+                vec![term(Field::Any, "foo:bar", false)],
+            ]
+        );
+        assert_eq!(q.none_of, vec![term(Field::Tag, "kette", false)]);
+    }
+}
@@ -45,33 +45,46 @@ pub trait Rated {
 
 impl Rated for Entry {
     fn avg_rating(&self, ratings: &[Rating]) -> f64 {
-        use self::RatingContext::*;
-
-        let ratings_for_entry: Vec<&Rating> =
-            ratings.iter().filter(|r| r.entry_id == self.id).collect();
-
-        let avg_ratings = vec![
-            avg_rating_for_context(&ratings_for_entry, &Diversity),
-            avg_rating_for_context(&ratings_for_entry, &Renewable),
-            avg_rating_for_context(&ratings_for_entry, &Fairness),
-            avg_rating_for_context(&ratings_for_entry, &Humanity),
-            avg_rating_for_context(&ratings_for_entry, &Transparency),
-            avg_rating_for_context(&ratings_for_entry, &Solidarity),
-        ];
-
-        let sum = avg_ratings
-            .iter()
-            .fold(0.0, |acc, &r| acc + r.unwrap_or(0.0));
-        let num_rated_contexts = avg_ratings
-            .iter()
-            .fold(0, |acc, &r| acc + if r.is_some() { 1 } else { 0 });
+        avg_ratings(&self.id, ratings).total
+    }
+}
 
-        if num_rated_contexts > 0 {
-            sum / 6.0
-        } else {
-            0.0
-        }
+/// Calculates the average ratings of an entry.
+/// Ratings of other entries are ignored.
+pub fn avg_ratings(entry_id: &str, ratings: &[Rating]) -> AvgRatings {
+    use self::RatingContext::*;
+
+    let ratings: Vec<&Rating> = ratings.iter().filter(|r| r.entry_id == entry_id).collect();
+
+    let avg_rating = |context| AvgRating {
+        value: avg_rating_for_context(&ratings, &context).unwrap_or(0.0),
+        count: ratings.iter().filter(|r| r.context == context).count() as u64,
+    };
+
+    let mut avg = AvgRatings {
+        entry_id: entry_id.into(),
+        total: 0.0,
+        diversity: avg_rating(Diversity),
+        renewable: avg_rating(Renewable),
+        fairness: avg_rating(Fairness),
+        humanity: avg_rating(Humanity),
+        transparency: avg_rating(Transparency),
+        solidarity: avg_rating(Solidarity),
+    };
+
+    let contexts = [
+        avg.diversity,
+        avg.renewable,
+        avg.fairness,
+        avg.humanity,
+        avg.transparency,
+        avg.solidarity,
+    ];
+    let sum = contexts.iter().fold(0.0, |acc, r| acc + r.value);
+    if contexts.iter().any(|r| r.count > 0) {
+        avg.total = sum / 6.0;
     }
+    avg
 }
 
 fn avg_rating_for_context(ratings: &[&Rating], context: &RatingContext) -> Option<f64> {
@@ -131,10 +144,12 @@ pub mod tests {
             id: id.into(),
             entry_id: entry_id.into(),
             created: 0,
+            updated: None,
             title: "blubb".into(),
             value: value.into(),
             context: context,
             source: Some("blabla".into()),
+            created_by: None,
         }
     }
 
@@ -176,6 +191,47 @@ pub mod tests {
         assert_eq!(entry2.avg_rating(&ratings), 0.0);
     }
 
+    #[test]
+    fn test_average_ratings_per_context() {
+        let ratings = vec![
+            new_rating("1", "a", 0, RatingContext::Diversity),
+            new_rating("2", "a", 10, RatingContext::Renewable),
+            new_rating("3", "a", 7, RatingContext::Fairness),
+            new_rating("4", "a", 9, RatingContext::Fairness),
+            new_rating("5", "b", -3, RatingContext::Diversity),
+        ];
+
+        let avg = avg_ratings("a", &ratings);
+        assert_eq!(avg.entry_id, "a");
+        assert_eq!(avg.total, 3.0);
+        assert_eq!(
+            avg.diversity,
+            AvgRating {
+                value: 0.0,
+                count: 1
+            }
+        );
+        assert_eq!(
+            avg.renewable,
+            AvgRating {
+                value: 10.0,
+                count: 1
+            }
+        );
+        assert_eq!(
+            avg.fairness,
+            AvgRating {
+                value: 8.0,
+                count: 2
+            }
+        );
+        assert_eq!(avg.humanity, AvgRating::default());
+
+        let avg = avg_ratings("c", &ratings);
+        assert_eq!(avg.total, 0.0);
+        assert_eq!(avg.diversity.count, 0);
+    }
+
     #[test]
     fn test_sort_by_avg_rating() {
         let mut entries = vec![
@@ -300,10 +356,12 @@ pub mod tests {
                 id: Uuid::new_v4().to_simple_ref().to_string(),
                 entry_id: id.into(),
                 created: 0,
+                updated: None,
                 title: "".into(),
                 value: 2,
                 context: RatingContext::Diversity,
                 source: None,
+                created_by: None,
             })
             .collect()
     }
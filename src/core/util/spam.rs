@@ -0,0 +1,309 @@
+use super::{parse::parse_lazy_url, text};
+use std::{collections::HashMap, net::IpAddr};
+
+/// The parts of a submitted entry or event that are checked for spam.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+#[derive(Debug, Clone, Default)]
+pub struct Submission<'a> {
+    pub title              : &'a str,
+    pub description        : &'a str,
+    pub homepage           : Option<&'a str>,
+    /// The number of submissions of the same client
+    /// within the last hour (including this one)
+    pub recent_submissions : usize,
+    /// Another entry with the same title already exists
+    pub duplicate_title    : bool,
+}
+
+/// A heuristic that rates how suspicious a submission is.
+pub trait SpamRule {
+    /// Returns the number of violations of the rule,
+    /// i.e. `0` for an inconspicuous submission.
+    fn score(&self, s: &Submission) -> u32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Ham,
+    /// The submission should be reviewed before it is published.
+    Suspicious,
+    /// The submission should be rejected.
+    Spam,
+}
+
+/// Sums up the scores of all rules and
+/// compares the total with the thresholds.
+pub struct SpamFilter {
+    rules: Vec<Box<dyn SpamRule + Send + Sync>>,
+    review_score: u32,
+    reject_score: u32,
+}
+
+impl SpamFilter {
+    pub fn new(review_score: u32, reject_score: u32) -> Self {
+        SpamFilter {
+            rules: vec![],
+            review_score,
+            reject_score,
+        }
+    }
+
+    pub fn add_rule<R: SpamRule + Send + Sync + 'static>(mut self, rule: R) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn score(&self, s: &Submission) -> u32 {
+        self.rules.iter().map(|r| r.score(s)).sum()
+    }
+
+    pub fn check(&self, s: &Submission) -> Verdict {
+        let score = self.score(s);
+        if score == 0 {
+            Verdict::Ham
+        } else if score >= self.reject_score {
+            Verdict::Spam
+        } else if score >= self.review_score {
+            Verdict::Suspicious
+        } else {
+            Verdict::Ham
+        }
+    }
+}
+
+impl Default for SpamFilter {
+    /// A filter without any rules that accepts everything.
+    fn default() -> Self {
+        SpamFilter::new(1, 1)
+    }
+}
+
+fn is_link(word: &str) -> bool {
+    word.contains("://") || word.starts_with("www.")
+}
+
+/// Scores every link in the title or description
+/// that exceeds the allowed number of links.
+#[derive(Debug, Clone)]
+pub struct LinkCount {
+    pub max_links: usize,
+}
+
+impl SpamRule for LinkCount {
+    fn score(&self, s: &Submission) -> u32 {
+        let links = text::words(s.title)
+            .into_iter()
+            .chain(text::words(s.description))
+            .filter(|w| is_link(w))
+            .count();
+        links.saturating_sub(self.max_links) as u32
+    }
+}
+
+/// A word without the surrounding punctuation, e.g. `(foo).` becomes `foo`.
+fn trim_punctuation(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+/// The host of a link that might lack the scheme.
+fn host(link: &str) -> Option<String> {
+    let url = parse_lazy_url(trim_punctuation(link)).ok()?;
+    url.host_str()
+        .map(|h| h.trim_end_matches('.').to_lowercase())
+}
+
+/// Whether the host is the domain or one of its subdomains.
+fn is_in_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Scores every blacklisted domain that is the host (or one of its parent
+/// domains) of the homepage or a link and every blacklisted word of the
+/// title or description.
+#[derive(Debug, Clone, Default)]
+pub struct Blacklist {
+    pub domains: Vec<String>,
+    pub words: Vec<String>,
+}
+
+impl SpamRule for Blacklist {
+    fn score(&self, s: &Submission) -> u32 {
+        let words: Vec<_> = text::words(s.title)
+            .into_iter()
+            .chain(text::words(s.description))
+            .collect();
+        let hosts: Vec<_> = words
+            .iter()
+            .filter(|w| is_link(w))
+            .cloned()
+            .chain(s.homepage.map(text::fold))
+            .filter_map(|l| host(&l))
+            .collect();
+        let domains = self
+            .domains
+            .iter()
+            .map(|d| text::fold(d.trim().trim_matches('.')))
+            .filter(|d| hosts.iter().any(|h| is_in_domain(h, d)))
+            .count();
+        let words: Vec<_> = words.iter().map(|w| trim_punctuation(w)).collect();
+        let words = self
+            .words
+            .iter()
+            .map(|w| text::fold(w))
+            .filter(|w| words.contains(&w.as_str()))
+            .count();
+        (domains + words) as u32
+    }
+}
+
+/// Scores every submission of a client that
+/// exceeds the allowed number of submissions per hour.
+#[derive(Debug, Clone)]
+pub struct SubmissionRate {
+    pub max_per_hour: usize,
+}
+
+impl SpamRule for SubmissionRate {
+    fn score(&self, s: &Submission) -> u32 {
+        s.recent_submissions.saturating_sub(self.max_per_hour) as u32
+    }
+}
+
+/// Scores a submission with the title of an existing entry.
+#[derive(Debug, Clone)]
+pub struct DuplicateTitle;
+
+impl SpamRule for DuplicateTitle {
+    fn score(&self, s: &Submission) -> u32 {
+        if s.duplicate_title {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+const HOUR: u64 = 60 * 60;
+
+/// Remembers the times of recent submissions per IP address.
+#[derive(Debug, Default)]
+pub struct SubmissionLog {
+    submissions: HashMap<IpAddr, Vec<u64>>,
+}
+
+impl SubmissionLog {
+    /// Records a submission and returns the number of submissions
+    /// from the same IP address within the last hour.
+    pub fn record(&mut self, ip: IpAddr, now: u64) -> usize {
+        let since = now.saturating_sub(HOUR);
+        for times in self.submissions.values_mut() {
+            times.retain(|t| *t > since);
+        }
+        self.submissions.retain(|_, times| !times.is_empty());
+        let times = self.submissions.entry(ip).or_insert_with(|| vec![]);
+        times.push(now);
+        times.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission<'a>(title: &'a str, description: &'a str) -> Submission<'a> {
+        Submission {
+            title,
+            description,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn count_links() {
+        let rule = LinkCount { max_links: 1 };
+        assert_eq!(rule.score(&submission("foo", "bar")), 0);
+        assert_eq!(rule.score(&submission("foo", "see https://foo.org")), 0);
+        let s = submission("www.foo.org", "http://a.org, https://b.org");
+        assert_eq!(rule.score(&s), 2);
+    }
+
+    #[test]
+    fn blacklisted_domains_and_words() {
+        let rule = Blacklist {
+            domains: vec!["spam.com".into()],
+            words: vec!["Casino".into(), "pills".into()],
+        };
+        assert_eq!(rule.score(&submission("foo", "spam is bad")), 0);
+        assert_eq!(rule.score(&submission("Online casino", "www.spam.com")), 2);
+        let mut s = submission("foo", "cheap PILLS");
+        s.homepage = Some("https://shop.spam.com/");
+        assert_eq!(rule.score(&s), 2);
+    }
+
+    #[test]
+    fn match_blacklisted_domains_by_their_host() {
+        let rule = Blacklist {
+            domains: vec!["spam.com".into()],
+            words: vec![],
+        };
+        assert_eq!(rule.score(&submission("foo", "see www.notspam.com")), 0);
+        assert_eq!(
+            rule.score(&submission("foo", "https://foo.org/?ref=spam.com")),
+            0
+        );
+        assert_eq!(
+            rule.score(&submission("foo", "https://foo.org/spam.com/")),
+            0
+        );
+        assert_eq!(
+            rule.score(&submission("foo", "(see https://www.spam.com).")),
+            1
+        );
+        assert_eq!(rule.score(&submission("foo", "https://SPAM.com/x")), 1);
+        let mut s = submission("foo", "bar");
+        s.homepage = Some("notspam.com");
+        assert_eq!(rule.score(&s), 0);
+        s.homepage = Some("spam.com");
+        assert_eq!(rule.score(&s), 1);
+    }
+
+    #[test]
+    fn match_blacklisted_words_without_punctuation() {
+        let rule = Blacklist {
+            domains: vec![],
+            words: vec!["viagra".into()],
+        };
+        assert_eq!(rule.score(&submission("foo", "Buy viagra.")), 1);
+        assert_eq!(rule.score(&submission("foo", "(viagra)")), 1);
+        assert_eq!(rule.score(&submission("Viagra!", "bar")), 1);
+        assert_eq!(rule.score(&submission("foo", "viagras")), 0);
+    }
+
+    #[test]
+    fn check_submissions() {
+        let filter = SpamFilter::new(1, 3)
+            .add_rule(LinkCount { max_links: 0 })
+            .add_rule(SubmissionRate { max_per_hour: 2 })
+            .add_rule(DuplicateTitle);
+        let mut s = submission("foo", "bar");
+        s.recent_submissions = 2;
+        assert_eq!(filter.check(&s), Verdict::Ham);
+        s.duplicate_title = true;
+        assert_eq!(filter.check(&s), Verdict::Suspicious);
+        s.recent_submissions = 4;
+        assert_eq!(filter.check(&s), Verdict::Spam);
+        assert_eq!(SpamFilter::default().check(&s), Verdict::Ham);
+    }
+
+    #[test]
+    fn count_recent_submissions() {
+        let mut log = SubmissionLog::default();
+        let a = "127.0.0.1".parse().unwrap();
+        let b = "::1".parse().unwrap();
+        assert_eq!(log.record(a, 1000), 1);
+        assert_eq!(log.record(a, 2000), 2);
+        assert_eq!(log.record(b, 2000), 1);
+        assert_eq!(log.record(a, 1000 + HOUR), 2);
+        assert_eq!(log.record(b, 2000 + HOUR), 1);
+    }
+}
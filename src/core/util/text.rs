@@ -0,0 +1,162 @@
+use std::cmp::min;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Folds a text into a canonical form that is used to compare
+/// search words with indexed texts: Everything is lowercase,
+/// diacritics are removed and ligatures are expanded,
+/// e.g. "Café" becomes "cafe" and "Straße" becomes "strasse".
+pub fn fold(txt: &str) -> String {
+    txt.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .fold(String::with_capacity(txt.len()), |mut folded, c| {
+            match c {
+                'ß' => folded.push_str("ss"),
+                'æ' => folded.push_str("ae"),
+                'œ' => folded.push_str("oe"),
+                'ø' => folded.push('o'),
+                'ł' => folded.push('l'),
+                'đ' => folded.push('d'),
+                _ => folded.push(c),
+            }
+            folded
+        })
+}
+
+/// Splits a text into folded words that are
+/// separated by whitespace or commas.
+pub fn words(txt: &str) -> Vec<String> {
+    fold(txt)
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// The number of typos that are tolerated in a word,
+/// i.e. the given share of its characters (rounded down).
+pub fn max_typos(word: &str, tolerance: f32) -> usize {
+    (word.chars().count() as f32 * tolerance) as usize
+}
+
+/// Returns true if the Levenshtein distance of
+/// `s` and `t` is not larger than `max_dist`.
+pub fn is_similar(s: &str, t: &str, max_dist: usize) -> bool {
+    let len_s = s.chars().count();
+    let len_t = t.chars().count();
+    // the distance is at least the difference of the lengths
+    if len_s.max(len_t) - len_s.min(len_t) > max_dist {
+        return false;
+    }
+    levenshtein_distance(s, t) <= max_dist
+}
+
+// Levenshtein Distance more realistically captures typos (all of the following
+// operations are counted as distance 1: add one character in between, delete
+// one character, change one character)
+//
+// Algorithm from
+// https://en.wikipedia.org/wiki/Levenshtein_distance#Computing_Levenshtein_distance
+pub fn levenshtein_distance(s: &str, t: &str) -> usize {
+    let s: Vec<char> = s.chars().collect();
+    let t: Vec<char> = t.chars().collect();
+    let max_s: usize = s.len() + 1;
+    let max_t: usize = t.len() + 1;
+
+    // for all i and j, d[i,j] will hold the Levenshtein distance between
+    // the first i characters of s and the first j characters of t
+    // note that d has (m+1)*(n+1) values
+    let mut d: Vec<Vec<usize>> = vec![];
+    for _ in 0..max_s {
+        d.push(vec![0; max_t]);
+    }
+
+    // source (s) prefixes can be transformed into empty string by
+    // dropping all characters
+    for (i, item) in d.iter_mut().enumerate().take(max_s).skip(1) {
+        item[0] = i;
+    }
+
+    // target (t) prefixes can be reached from empty source prefix
+    // by inserting every character
+    for j in 1..max_t {
+        d[0][j] = j;
+    }
+
+    for j in 1..max_t {
+        for i in 1..max_s {
+            let substitution_cost = if s[i - 1] == t[j - 1] { 0 } else { 1 };
+            d[i][j] = min3(
+                d[i - 1][j] + 1,                     // deletion
+                d[i][j - 1] + 1,                     // insertion
+                d[i - 1][j - 1] + substitution_cost, // substitution
+            )
+        }
+    }
+
+    d[max_s - 1][max_t - 1]
+}
+
+fn min3(s: usize, t: usize, u: usize) -> usize {
+    if s <= t {
+        min(s, u)
+    } else {
+        min(t, u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_diacritics_and_ligatures() {
+        assert_eq!(fold("Café"), "cafe");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("STRASSE"), "strasse");
+        assert_eq!(fold("Ærø Œuvre Łódź"), "aero oeuvre lodz");
+        assert_eq!(fold("Müsli-Bar"), "musli-bar");
+    }
+
+    #[test]
+    fn split_into_words() {
+        assert_eq!(
+            words("Foo, bar\tBAZ  qux"),
+            vec!["foo", "bar", "baz", "qux"]
+        );
+        assert_eq!(words("bla-blubb"), vec!["bla-blubb"]);
+        assert!(words(" , ").is_empty());
+    }
+
+    #[test]
+    fn tolerated_typos() {
+        assert_eq!(max_typos("foo", 0.2), 0);
+        assert_eq!(max_typos("fooba", 0.2), 1);
+        assert_eq!(max_typos("bäckerei", 0.2), 1);
+        assert_eq!(max_typos("foobarbaz", 0.0), 0);
+    }
+
+    #[test]
+    fn similar_words() {
+        assert!(is_similar("backerei", "baeckerei", 1));
+        assert!(!is_similar("backerei", "baeckereien", 1));
+        assert!(!is_similar("xafe", "cafe", 0));
+    }
+
+    #[test]
+    fn test_min() {
+        assert_eq!(1, min3(1, 2, 3));
+        assert_eq!(2, min3(3, 2, 3));
+        assert_eq!(2, min3(3, 3, 2));
+        assert_eq!(1, min3(1, 1, 1));
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(3, levenshtein_distance("012a34c", "0a3c")); // delete 1,2 and 4
+        assert_eq!(1, levenshtein_distance("12345", "a12345")); // insert a
+        assert_eq!(1, levenshtein_distance("aabaa", "aacaa")); // replace b by c
+        assert_eq!(1, levenshtein_distance("xafe", "cafe")); // replace x by c
+        assert_eq!(1, levenshtein_distance("café", "cafe")); // replace é by e
+    }
+}
@@ -0,0 +1,73 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+fn signature(secret: &str, subscription_id: &str) -> Hmac<Sha256> {
+    let mut hmac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    hmac.input(subscription_id.as_bytes());
+    hmac
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Creates a token that allows to delete the subscription
+/// without logging in. It consists of the ID of the
+/// subscription and its signature (`<id>.<signature>`).
+pub fn token(secret: &str, subscription_id: &str) -> String {
+    let hex = encode_hex(&signature(secret, subscription_id).result().code());
+    format!("{}.{}", subscription_id, hex)
+}
+
+/// Derives a separate key for each kind of token from the
+/// secret, so a token of one kind is never valid as another.
+pub fn derive_key(secret: &str, purpose: &str) -> String {
+    encode_hex(&signature(secret, purpose).result().code())
+}
+
+/// Returns the ID of the subscription if the signature of the token is valid.
+pub fn verify<'a>(secret: &str, token: &'a str) -> Option<&'a str> {
+    let mut parts = token.rsplitn(2, '.');
+    let hex = parts.next()?;
+    let subscription_id = parts.next()?;
+    let s = decode_hex(hex)?;
+    // The signature is compared in constant time
+    if signature(secret, subscription_id).verify(&s).is_ok() {
+        Some(subscription_id)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_signed_tokens() {
+        let t = token("secret", "abc");
+        assert!(t.starts_with("abc."));
+        assert_eq!(verify("secret", &t), Some("abc"));
+        assert_eq!(verify("other", &t), None);
+        assert_eq!(verify("secret", &t.replace("abc", "abd")), None);
+        assert_eq!(verify("secret", "abc"), None);
+        assert_eq!(verify("secret", "abc.xyz"), None);
+        assert_eq!(verify("secret", ""), None);
+        let key = derive_key("secret", "revert");
+        assert_ne!(key, derive_key("secret", "contact"));
+        assert_ne!(key, derive_key("other", "revert"));
+        assert_eq!(verify(&key, &token(&key, "abc")), Some("abc"));
+        assert_eq!(verify("secret", &token(&key, "abc")), None);
+    }
+}
@@ -5,6 +5,7 @@ use url::Url;
 
 lazy_static! {
     static ref USERNAME_REGEX: Regex = Regex::new(r"^[a-z0-9]{1,30}$").unwrap();
+    static ref LANGUAGE_REGEX: Regex = Regex::new(r"^[a-z]{2,3}$").unwrap();
 }
 
 pub trait Validate {
@@ -63,6 +64,14 @@ pub fn password(pw: &str) -> Result<(), ParameterError> {
     Ok(())
 }
 
+/// Accepts ISO 639 language codes like `de` or `en`.
+pub fn language(lang: &str) -> Result<(), ParameterError> {
+    if !LANGUAGE_REGEX.is_match(lang) {
+        return Err(ParameterError::Language);
+    }
+    Ok(())
+}
+
 impl Validate for Entry {
     fn validate(&self) -> Result<(), ParameterError> {
         //TODO: check title
@@ -129,6 +138,15 @@ fn email_test() {
     assert!(email("foo@bar.tld").is_ok());
 }
 
+#[test]
+fn language_test() {
+    assert!(language("de").is_ok());
+    assert!(language("en").is_ok());
+    assert!(language("EN").is_err());
+    assert!(language("de-DE").is_err());
+    assert!(language("").is_err());
+}
+
 #[test]
 fn homepage_test() {
     assert!(homepage("https://openfairdb.org").is_ok());
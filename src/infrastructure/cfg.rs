@@ -0,0 +1,532 @@
+use super::error::AppError;
+use crate::adapters::templates::Templates;
+use crate::core::{
+    usecases::{self, Backoff},
+    util::{
+        filter::DEFAULT_TYPO_TOLERANCE,
+        spam::{Blacklist, DuplicateTitle, LinkCount, SpamFilter, SubmissionRate},
+        unsubscribe,
+    },
+};
+use std::{
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+/// The application settings that can be
+/// adjusted within a TOML file (e.g. `config.toml`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Cfg {
+    pub search: SearchCfg,
+    pub ratings: RatingsCfg,
+    pub entries: EntriesCfg,
+    pub spam: SpamCfg,
+    pub rate_limit: RateLimitCfg,
+    pub notifications: NotificationsCfg,
+    pub outbox: OutboxCfg,
+    pub mail: MailCfg,
+    pub webhooks: WebhooksCfg,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SearchCfg {
+    /// The share of characters of a search word that may differ.
+    pub typo_tolerance: f32,
+}
+
+impl Default for SearchCfg {
+    fn default() -> Self {
+        SearchCfg {
+            typo_tolerance: DEFAULT_TYPO_TOLERANCE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RatingsCfg {
+    /// Reject anonymous ratings.
+    pub require_login: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EntriesCfg {
+    /// Publish new entries only after a scout approved them.
+    pub require_review: bool,
+}
+
+/// Every violation of a spam rule scores one point.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SpamCfg {
+    /// The number of links that are allowed in the title and description.
+    pub max_links: usize,
+    pub blacklisted_domains: Vec<String>,
+    pub blacklisted_words: Vec<String>,
+    /// The number of submissions per hour and IP address.
+    pub max_submissions_per_hour: usize,
+    /// Entries with this score are published after a review.
+    pub review_score: u32,
+    /// Submissions with this score are rejected.
+    pub reject_score: u32,
+}
+
+impl Default for SpamCfg {
+    fn default() -> Self {
+        SpamCfg {
+            max_links: 2,
+            blacklisted_domains: vec![],
+            blacklisted_words: vec![],
+            max_submissions_per_hour: 10,
+            review_score: 1,
+            reject_score: 3,
+        }
+    }
+}
+
+impl SpamCfg {
+    pub fn filter(&self) -> SpamFilter {
+        SpamFilter::new(self.review_score, self.reject_score)
+            .add_rule(LinkCount {
+                max_links: self.max_links,
+            })
+            .add_rule(Blacklist {
+                domains: self.blacklisted_domains.clone(),
+                words: self.blacklisted_words.clone(),
+            })
+            .add_rule(SubmissionRate {
+                max_per_hour: self.max_submissions_per_hour,
+            })
+            .add_rule(DuplicateTitle)
+    }
+}
+
+/// A token bucket that holds up to `burst` requests
+/// and is refilled with `per-minute` requests per minute.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RateLimitCfg {
+    /// All `POST`, `PUT`, `PATCH` and `DELETE` requests per IP address.
+    pub writes: RateLimit,
+    /// `POST /login` per IP address and per username.
+    pub login: RateLimit,
+    /// The reverse proxies whose `X-Real-IP` header is trusted.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimitCfg {
+    fn default() -> Self {
+        RateLimitCfg {
+            writes: RateLimit {
+                burst: 10,
+                per_minute: 2,
+            },
+            login: RateLimit {
+                burst: 5,
+                per_minute: 1,
+            },
+            trusted_proxies: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotificationsCfg {
+    /// The public URL of the API that is used within the links that act
+    /// on behalf of the recipient (unsubscribe, opt-out and revert).
+    /// All other links of the mails point to the `site_url` of the `[mail]` section.
+    pub api_url: String,
+    /// The key for signing the links within notification mails. It has
+    /// to be the same for the server and the CLI. Without it the mails
+    /// contain no such links and their routes are disabled.
+    pub unsubscribe_secret: String,
+    /// Notify the contact address of an entry about changes
+    /// (unless it opted out).
+    pub notify_entry_contacts: bool,
+}
+
+impl Default for NotificationsCfg {
+    fn default() -> Self {
+        NotificationsCfg {
+            api_url: "https://api.ofdb.io/v0".into(),
+            unsubscribe_secret: String::new(),
+            notify_entry_contacts: false,
+        }
+    }
+}
+
+/// The minimum length of the `unsubscribe-secret`.
+pub const MIN_SECRET_LENGTH: usize = 16;
+
+impl NotificationsCfg {
+    /// Whether the links to unsubscribe, opt out and revert
+    /// changes are sent and accepted.
+    pub fn signed_links(&self) -> bool {
+        !self.unsubscribe_secret.trim().is_empty()
+    }
+
+    /// Fails if the key for signing the links is too short or if it is
+    /// missing although the contacts of entries should be notified.
+    pub fn check_secret(&self) -> io::Result<()> {
+        if !self.signed_links() {
+            if cfg!(feature = "email") && self.notify_entry_contacts {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The `unsubscribe-secret` of the `[notifications]` section \
+                     is required to notify the contacts of entries",
+                ));
+            }
+            return Ok(());
+        }
+        if self.unsubscribe_secret.trim().len() < MIN_SECRET_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "The `unsubscribe-secret` of the `[notifications]` section \
+                     needs at least {} characters",
+                    MIN_SECRET_LENGTH
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn unsubscribe_url(&self, subscription_id: &str) -> String {
+        format!(
+            "{}/unsubscribe/{}",
+            self.api_url.trim_end_matches('/'),
+            unsubscribe::token(&self.unsubscribe_secret, subscription_id)
+        )
+    }
+
+    pub fn contact_opt_out_url(&self, email: &str) -> String {
+        format!(
+            "{}/contact-opt-out/{}",
+            self.api_url.trim_end_matches('/'),
+            usecases::contact_opt_out_token(&self.unsubscribe_secret, email)
+        )
+    }
+
+    pub fn revert_entry_url(&self, entry_id: &str, version: u64) -> String {
+        format!(
+            "{}/entries/{}/revert/{}",
+            self.api_url.trim_end_matches('/'),
+            entry_id,
+            usecases::revert_token(&self.unsubscribe_secret, entry_id, version)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct OutboxCfg {
+    /// Mails are marked as failed after this number of attempts.
+    pub max_attempts: u32,
+    /// Seconds before the first retry (doubled after every attempt).
+    pub retry_delay: u64,
+    /// Seconds between the checks for mails to deliver.
+    pub poll_interval: u64,
+}
+
+impl Default for OutboxCfg {
+    fn default() -> Self {
+        OutboxCfg {
+            max_attempts: 8,
+            retry_delay: 60,
+            poll_interval: 10,
+        }
+    }
+}
+
+impl OutboxCfg {
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            max_attempts: self.max_attempts,
+            delay: self.retry_delay,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WebhooksCfg {
+    /// Payloads are marked as failed after this number of attempts.
+    pub max_attempts: u32,
+    /// Seconds before the first retry (doubled after every attempt).
+    pub retry_delay: u64,
+    /// Seconds between the checks for payloads to deliver.
+    pub poll_interval: u64,
+    /// Seconds to wait for the connection to and the response of a webhook.
+    pub timeout: u64,
+}
+
+impl Default for WebhooksCfg {
+    fn default() -> Self {
+        WebhooksCfg {
+            max_attempts: 8,
+            retry_delay: 60,
+            poll_interval: 10,
+            timeout: 10,
+        }
+    }
+}
+
+impl WebhooksCfg {
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            max_attempts: self.max_attempts,
+            delay: self.retry_delay,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MailTransport {
+    Sendmail,
+    Smtp,
+    Directory,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MailCfg {
+    /// The name of the site within the mails and the sender.
+    pub site_name: String,
+    /// The URL of the site that is used within the links to entries and events.
+    /// Links that call the API use the `api_url` of the `[notifications]` section.
+    pub site_url: String,
+    pub sender_address: String,
+    /// The language of users that didn't choose one.
+    pub default_language: String,
+    /// A directory with templates that override the built-in ones.
+    pub templates: Option<PathBuf>,
+    /// How outgoing mails are delivered.
+    pub transport: MailTransport,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// Require an encrypted connection to the SMTP server (STARTTLS).
+    /// Otherwise the connection is only encrypted if the server supports it.
+    pub smtp_starttls: bool,
+    /// Connect with implicit TLS (usually on port 465) instead of STARTTLS.
+    pub smtp_tls: bool,
+    /// The directory the `.eml` files are written into.
+    pub directory: PathBuf,
+}
+
+impl Default for MailCfg {
+    fn default() -> Self {
+        MailCfg {
+            site_name: "Karte von morgen".into(),
+            site_url: "https://kartevonmorgen.org".into(),
+            sender_address: "no-reply@kartevonmorgen.org".into(),
+            default_language: "de".into(),
+            templates: None,
+            transport: MailTransport::Sendmail,
+            smtp_host: "localhost".into(),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_starttls: true,
+            smtp_tls: false,
+            directory: "mails".into(),
+        }
+    }
+}
+
+impl MailCfg {
+    /// The `From` header of all mails
+    pub fn from(&self) -> String {
+        format!("\"{}\" <{}>", self.site_name, self.sender_address)
+    }
+
+    /// Fails if the SMTP credentials might be sent over an unencrypted connection.
+    pub fn check_smtp(&self) -> io::Result<()> {
+        let encrypted = self.smtp_starttls || self.smtp_tls;
+        if self.transport == MailTransport::Smtp && self.smtp_username.is_some() && !encrypted {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The `smtp-username` of the `[mail]` section requires \
+                 `smtp-starttls` or `smtp-tls`",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn templates(&self) -> io::Result<Templates> {
+        let mut templates = Templates::new(&self.default_language, &self.site_name, &self.site_url);
+        if let Some(ref dir) = self.templates {
+            templates.load_dir(dir)?;
+        }
+        Ok(templates)
+    }
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Cfg, AppError> {
+    let txt = fs::read_to_string(path)?;
+    Ok(toml::from_str(&txt)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_search_settings() {
+        let cfg: Cfg = toml::from_str("[search]\ntypo-tolerance = 0.5").unwrap();
+        assert_eq!(cfg.search.typo_tolerance, 0.5);
+        let cfg: Cfg = toml::from_str("[notification]\nsend-to = []").unwrap();
+        assert_eq!(cfg.search.typo_tolerance, DEFAULT_TYPO_TOLERANCE);
+    }
+
+    #[test]
+    fn parse_rating_settings() {
+        let cfg: Cfg = toml::from_str("[ratings]\nrequire-login = true").unwrap();
+        assert!(cfg.ratings.require_login);
+        assert!(!Cfg::default().ratings.require_login);
+    }
+
+    #[test]
+    fn parse_entry_settings() {
+        let cfg: Cfg = toml::from_str("[entries]\nrequire-review = true").unwrap();
+        assert!(cfg.entries.require_review);
+        assert!(!Cfg::default().entries.require_review);
+    }
+
+    #[test]
+    fn parse_spam_settings() {
+        let cfg: Cfg = toml::from_str(
+            "[spam]\nmax-links = 0\nblacklisted-words = [\"casino\"]\nreject-score = 5",
+        )
+        .unwrap();
+        assert_eq!(cfg.spam.max_links, 0);
+        assert_eq!(cfg.spam.blacklisted_words, vec!["casino"]);
+        assert!(cfg.spam.blacklisted_domains.is_empty());
+        assert_eq!(cfg.spam.max_submissions_per_hour, 10);
+        assert_eq!(cfg.spam.reject_score, 5);
+    }
+
+    #[test]
+    fn parse_rate_limit_settings() {
+        let cfg: Cfg =
+            toml::from_str("[rate-limit]\nlogin = { burst = 3, per-minute = 6 }").unwrap();
+        assert_eq!(cfg.rate_limit.login.burst, 3);
+        assert_eq!(cfg.rate_limit.login.per_minute, 6);
+        assert_eq!(cfg.rate_limit.writes.burst, 10);
+        assert!(cfg.rate_limit.trusted_proxies.is_empty());
+        let cfg: Cfg =
+            toml::from_str("[rate-limit]\ntrusted-proxies = [\"127.0.0.1\", \"::1\"]").unwrap();
+        assert_eq!(
+            cfg.rate_limit.trusted_proxies,
+            vec![
+                "127.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_outbox_settings() {
+        let cfg: Cfg = toml::from_str("[outbox]\nmax-attempts = 3").unwrap();
+        let backoff = cfg.outbox.backoff();
+        assert_eq!(backoff.max_attempts, 3);
+        assert_eq!(backoff.delay, 60);
+    }
+
+    #[test]
+    fn parse_webhook_settings() {
+        let cfg: Cfg = toml::from_str("[webhooks]\nretry-delay = 30\ntimeout = 5").unwrap();
+        let backoff = cfg.webhooks.backoff();
+        assert_eq!(backoff.max_attempts, 8);
+        assert_eq!(backoff.delay, 30);
+        assert_eq!(cfg.webhooks.timeout, 5);
+        assert_eq!(cfg.webhooks.poll_interval, 10);
+    }
+
+    #[test]
+    fn parse_mail_settings() {
+        let cfg: Cfg = toml::from_str(
+            "[mail]\ntransport = \"smtp\"\nsmtp-host = \"mail.example.org\"\nsmtp-username = \"ofdb\"",
+        )
+        .unwrap();
+        assert_eq!(cfg.mail.transport, MailTransport::Smtp);
+        assert_eq!(cfg.mail.smtp_host, "mail.example.org");
+        assert_eq!(cfg.mail.smtp_port, 587);
+        assert_eq!(cfg.mail.smtp_username, Some("ofdb".into()));
+        assert!(cfg.mail.smtp_starttls);
+        assert!(!cfg.mail.smtp_tls);
+        assert!(cfg.mail.check_smtp().is_ok());
+        let cfg: Cfg = toml::from_str(
+            "[mail]\ntransport = \"smtp\"\nsmtp-username = \"ofdb\"\nsmtp-starttls = false",
+        )
+        .unwrap();
+        assert!(cfg.mail.check_smtp().is_err());
+        let cfg: Cfg = toml::from_str(
+            "[mail]\ntransport = \"smtp\"\nsmtp-port = 465\nsmtp-username = \"ofdb\"\n\
+             smtp-starttls = false\nsmtp-tls = true",
+        )
+        .unwrap();
+        assert!(cfg.mail.check_smtp().is_ok());
+        let cfg: Cfg = toml::from_str("[mail]\ntransport = \"directory\"").unwrap();
+        assert_eq!(cfg.mail.transport, MailTransport::Directory);
+        assert_eq!(Cfg::default().mail.transport, MailTransport::Sendmail);
+        assert_eq!(
+            Cfg::default().mail.from(),
+            "\"Karte von morgen\" <no-reply@kartevonmorgen.org>"
+        );
+        let cfg: Cfg = toml::from_str(
+            "[mail]\nsite-name = \"Map\"\nsite-url = \"https://example.org\"\ndefault-language = \"en\"",
+        )
+        .unwrap();
+        let (subject, _) = cfg.mail.templates().unwrap().render(None, "digest", &[]);
+        assert_eq!(subject, "Map - Digest");
+    }
+
+    #[test]
+    fn parse_notification_settings() {
+        let cfg: Cfg = toml::from_str(
+            "[notifications]\napi-url = \"http://localhost:6767/\"\nunsubscribe-secret = \"foo\"",
+        )
+        .unwrap();
+        let url = cfg.notifications.unsubscribe_url("abc");
+        assert!(url.starts_with("http://localhost:6767/unsubscribe/abc."));
+        assert_eq!(
+            unsubscribe::verify("foo", &url["http://localhost:6767/unsubscribe/".len()..]),
+            Some("abc")
+        );
+        assert!(!cfg.notifications.notify_entry_contacts);
+        assert!(cfg
+            .notifications
+            .revert_entry_url("e1", 2)
+            .starts_with("http://localhost:6767/entries/e1/revert/revert:e1:2:"));
+        // the secret is too short
+        assert!(cfg.notifications.check_secret().is_err());
+        // without a secret the links are disabled
+        let mut cfg = Cfg::default();
+        assert!(!cfg.notifications.signed_links());
+        assert!(cfg.notifications.check_secret().is_ok());
+        cfg.notifications.notify_entry_contacts = true;
+        assert_eq!(
+            cfg.notifications.check_secret().is_err(),
+            cfg!(feature = "email")
+        );
+        let cfg: Cfg =
+            toml::from_str("[notifications]\nunsubscribe-secret = \"0123456789abcdef\"").unwrap();
+        assert!(cfg.notifications.signed_links());
+        assert!(cfg.notifications.check_secret().is_ok());
+    }
+}
@@ -1,5 +1,5 @@
 use super::{models, schema};
-use crate::core::prelude::*;
+use crate::core::{prelude::*, util::sort};
 use diesel::{
     self,
     prelude::*,
@@ -11,7 +11,7 @@ use std::result;
 type Result<T> = result::Result<T, RepoError>;
 
 fn unset_current_on_all_entries(
-    con: &&mut SqliteConnection,
+    con: &SqliteConnection,
     id: &str,
 ) -> result::Result<usize, diesel::result::Error> {
     use self::schema::entries::dsl;
@@ -21,47 +21,81 @@ fn unset_current_on_all_entries(
             .filter(dsl::current.eq(true)),
     )
     .set(dsl::current.eq(false))
-    .execute(*con)
+    .execute(con)
+}
+
+fn insert_entry(
+    con: &SqliteConnection,
+    e: Entry,
+    pending: bool,
+) -> result::Result<(), diesel::result::Error> {
+    let cat_rels: Vec<_> = e
+        .categories
+        .iter()
+        .cloned()
+        .map(|category_id| models::EntryCategoryRelation {
+            entry_id: e.id.clone(),
+            entry_version: e.version as i64,
+            category_id,
+        })
+        .collect();
+    let tag_rels: Vec<_> = e
+        .tags
+        .iter()
+        .cloned()
+        .map(|tag_id| models::EntryTagRelation {
+            entry_id: e.id.clone(),
+            entry_version: e.version as i64,
+            tag_id,
+        })
+        .collect();
+    let new_entry = models::Entry {
+        pending,
+        ..models::Entry::from(e)
+    };
+    con.transaction::<_, diesel::result::Error, _>(|| {
+        unset_current_on_all_entries(con, &new_entry.id)?;
+        diesel::insert_into(schema::entries::table)
+            .values(&new_entry)
+            .execute(con)?;
+        diesel::insert_into(schema::entry_category_relations::table)
+            //WHERE NOT EXISTS
+            .values(&cat_rels)
+            .execute(con)?;
+        diesel::insert_into(schema::entry_tag_relations::table)
+            //WHERE NOT EXISTS
+            .values(&tag_rels)
+            .execute(con)?;
+        Ok(())
+    })
+}
+
+fn update_avg_ratings(
+    con: &SqliteConnection,
+    entry_id: &str,
+) -> result::Result<(), diesel::result::Error> {
+    use self::schema::ratings::dsl;
+    let ratings: Vec<Rating> = dsl::ratings
+        .filter(dsl::entry_id.eq(entry_id))
+        .load::<models::Rating>(con)?
+        .into_iter()
+        .map(Rating::from)
+        .collect();
+    let avg = models::AvgRatings::from(sort::avg_ratings(entry_id, &ratings));
+    diesel::replace_into(schema::entry_ratings::table)
+        .values(&avg)
+        .execute(con)?;
+    Ok(())
 }
 
 impl EntryGateway for SqliteConnection {
     fn create_entry(&mut self, e: Entry) -> Result<()> {
-        let cat_rels: Vec<_> = e
-            .categories
-            .iter()
-            .cloned()
-            .map(|category_id| models::EntryCategoryRelation {
-                entry_id: e.id.clone(),
-                entry_version: e.version as i64,
-                category_id,
-            })
-            .collect();
-        let tag_rels: Vec<_> = e
-            .tags
-            .iter()
-            .cloned()
-            .map(|tag_id| models::EntryTagRelation {
-                entry_id: e.id.clone(),
-                entry_version: e.version as i64,
-                tag_id,
-            })
-            .collect();
-        let new_entry = models::Entry::from(e);
-        self.transaction::<_, diesel::result::Error, _>(|| {
-            unset_current_on_all_entries(&self, &new_entry.id)?;
-            diesel::insert_into(schema::entries::table)
-                .values(&new_entry)
-                .execute(self)?;
-            diesel::insert_into(schema::entry_category_relations::table)
-                //WHERE NOT EXISTS
-                .values(&cat_rels)
-                .execute(self)?;
-            diesel::insert_into(schema::entry_tag_relations::table)
-                //WHERE NOT EXISTS
-                .values(&tag_rels)
-                .execute(self)?;
-            Ok(())
-        })?;
+        insert_entry(self, e, false)?;
+        Ok(())
+    }
+
+    fn create_pending_entry(&mut self, e: Entry) -> Result<()> {
+        insert_entry(self, e, true)?;
         Ok(())
     }
 
@@ -93,6 +127,8 @@ impl EntryGateway for SqliteConnection {
         } = e_dsl::entries
             .filter(e_dsl::id.eq(e_id))
             .filter(e_dsl::current.eq(true))
+            .filter(e_dsl::archived.is_null())
+            .filter(e_dsl::pending.eq(false))
             .first(self)?;
 
         let categories = e_c_dsl::entry_category_relations
@@ -138,6 +174,26 @@ impl EntryGateway for SqliteConnection {
         })
     }
 
+    fn get_entry_version(&self, e_id: &str, e_version: u64) -> Result<Entry> {
+        use self::schema::{
+            entries::dsl as e_dsl, entry_category_relations::dsl as e_c_dsl,
+            entry_tag_relations::dsl as e_t_dsl,
+        };
+        let e: models::Entry = e_dsl::entries
+            .filter(e_dsl::id.eq(e_id))
+            .filter(e_dsl::version.eq(e_version as i64))
+            .first(self)?;
+        let cat_rels = e_c_dsl::entry_category_relations
+            .filter(e_c_dsl::entry_id.eq(e_id))
+            .filter(e_c_dsl::entry_version.eq(e_version as i64))
+            .load(self)?;
+        let tag_rels = e_t_dsl::entry_tag_relations
+            .filter(e_t_dsl::entry_id.eq(e_id))
+            .filter(e_t_dsl::entry_version.eq(e_version as i64))
+            .load(self)?;
+        Ok((e, &cat_rels, &tag_rels).into())
+    }
+
     fn get_entries_by_bbox(&self, bbox: &Bbox) -> Result<Vec<Entry>> {
         use self::schema::{
             entries::dsl as e_dsl, entry_category_relations::dsl as e_c_dsl,
@@ -149,6 +205,8 @@ impl EntryGateway for SqliteConnection {
         } = bbox;
         let entries: Vec<models::Entry> = e_dsl::entries
             .filter(e_dsl::current.eq(true))
+            .filter(e_dsl::archived.is_null())
+            .filter(e_dsl::pending.eq(false))
             .filter(e_dsl::lat.between(south_west.lat, north_east.lat))
             .filter(e_dsl::lng.between(south_west.lng, north_east.lng))
             .load(self)?;
@@ -165,8 +223,29 @@ impl EntryGateway for SqliteConnection {
             entries::dsl as e_dsl, entry_category_relations::dsl as e_c_dsl,
             entry_tag_relations::dsl as e_t_dsl,
         };
-        let entries: Vec<models::Entry> =
-            e_dsl::entries.filter(e_dsl::current.eq(true)).load(self)?;
+        let entries: Vec<models::Entry> = e_dsl::entries
+            .filter(e_dsl::current.eq(true))
+            .filter(e_dsl::archived.is_null())
+            .filter(e_dsl::pending.eq(false))
+            .load(self)?;
+        let cat_rels = e_c_dsl::entry_category_relations.load(self)?;
+        let tag_rels = e_t_dsl::entry_tag_relations.load(self)?;
+        Ok(entries
+            .into_iter()
+            .map(|e| (e, &cat_rels, &tag_rels).into())
+            .collect())
+    }
+
+    fn pending_entries(&self) -> Result<Vec<Entry>> {
+        use self::schema::{
+            entries::dsl as e_dsl, entry_category_relations::dsl as e_c_dsl,
+            entry_tag_relations::dsl as e_t_dsl,
+        };
+        let entries: Vec<models::Entry> = e_dsl::entries
+            .filter(e_dsl::current.eq(true))
+            .filter(e_dsl::archived.is_null())
+            .filter(e_dsl::pending.eq(true))
+            .load(self)?;
         let cat_rels = e_c_dsl::entry_category_relations.load(self)?;
         let tag_rels = e_t_dsl::entry_tag_relations.load(self)?;
         Ok(entries
@@ -175,6 +254,23 @@ impl EntryGateway for SqliteConnection {
             .collect())
     }
 
+    fn approve_entry(&mut self, id: &str) -> Result<()> {
+        use self::schema::entries::dsl;
+        let count = diesel::update(
+            dsl::entries
+                .filter(dsl::id.eq(id))
+                .filter(dsl::current.eq(true))
+                .filter(dsl::archived.is_null())
+                .filter(dsl::pending.eq(true)),
+        )
+        .set(dsl::pending.eq(false))
+        .execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
     fn update_entry(&mut self, entry: &Entry) -> Result<()> {
         let e = models::Entry::from(entry.clone());
 
@@ -218,6 +314,22 @@ impl EntryGateway for SqliteConnection {
         Ok(())
     }
 
+    fn archive_entry(&mut self, id: &str, archived: u64) -> Result<()> {
+        use self::schema::entries::dsl;
+        let count = diesel::update(
+            dsl::entries
+                .filter(dsl::id.eq(id))
+                .filter(dsl::current.eq(true))
+                .filter(dsl::archived.is_null()),
+        )
+        .set(dsl::archived.eq(Some(archived as i64)))
+        .execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
     fn import_multiple_entries(&mut self, new_entries: &[Entry]) -> Result<()> {
         let imports: Vec<_> = new_entries
             .into_iter()
@@ -453,8 +565,190 @@ impl UserGateway for SqliteConnection {
             .collect())
     }
     fn delete_user(&mut self, user_name: &str) -> Result<()> {
-        use self::schema::users::dsl::*;
-        diesel::delete(users.find(user_name)).execute(self)?;
+        use self::schema::{
+            bbox_subscriptions::dsl as s_dsl, comments::dsl as c_dsl, events::dsl as e_dsl,
+            outbox_mails::dsl as o_dsl, pending_notifications::dsl as n_dsl, ratings::dsl as r_dsl,
+            reports::dsl as rep_dsl, user_tokens::dsl as t_dsl, users::dsl as u_dsl,
+        };
+        let none: Option<String> = None;
+        self.transaction::<_, diesel::result::Error, _>(|| {
+            // The queued mails contain the address and data of the user
+            let email = u_dsl::users
+                .find(user_name)
+                .select(u_dsl::email)
+                .first::<String>(self)
+                .optional()?;
+            if let Some(email) = email {
+                let mails: Vec<String> = o_dsl::outbox_mails
+                    .filter(o_dsl::recipients.like(format!("%{}%", email)))
+                    .load::<models::OutboxMail>(self)?
+                    .into_iter()
+                    .filter(|m| m.recipients.split(',').any(|r| r == email))
+                    .map(|m| m.id)
+                    .collect();
+                diesel::delete(o_dsl::outbox_mails.filter(o_dsl::id.eq_any(mails)))
+                    .execute(self)?;
+            }
+            diesel::delete(s_dsl::bbox_subscriptions.filter(s_dsl::username.eq(user_name)))
+                .execute(self)?;
+            diesel::delete(n_dsl::pending_notifications.filter(n_dsl::username.eq(user_name)))
+                .execute(self)?;
+            diesel::delete(t_dsl::user_tokens.filter(t_dsl::username.eq(user_name)))
+                .execute(self)?;
+            diesel::delete(schema::login_histories::table.find(user_name)).execute(self)?;
+            // The contributions of the user are kept anonymously
+            diesel::update(e_dsl::events.filter(e_dsl::created_by.eq(user_name)))
+                .set(e_dsl::created_by.eq(&none))
+                .execute(self)?;
+            diesel::update(r_dsl::ratings.filter(r_dsl::created_by.eq(user_name)))
+                .set(r_dsl::created_by.eq(&none))
+                .execute(self)?;
+            diesel::update(c_dsl::comments.filter(c_dsl::created_by.eq(user_name)))
+                .set(c_dsl::created_by.eq(&none))
+                .execute(self)?;
+            diesel::update(rep_dsl::reports.filter(rep_dsl::reported_by.eq(user_name)))
+                .set(rep_dsl::reported_by.eq(&none))
+                .execute(self)?;
+            diesel::update(rep_dsl::reports.filter(rep_dsl::resolved_by.eq(user_name)))
+                .set(rep_dsl::resolved_by.eq(&none))
+                .execute(self)?;
+            diesel::delete(schema::users::table.find(user_name)).execute(self)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+    fn get_login_history(&self, user_name: &str) -> Result<LoginHistory> {
+        use self::schema::login_histories::dsl::*;
+        Ok(login_histories
+            .find(user_name)
+            .first::<models::LoginHistory>(self)
+            .map(LoginHistory::from)?)
+    }
+    fn update_login_history(&mut self, h: &LoginHistory) -> Result<()> {
+        diesel::replace_into(schema::login_histories::table)
+            .values(&models::LoginHistory::from(h.clone()))
+            .execute(self)?;
+        Ok(())
+    }
+}
+
+impl UserTokenGateway for SqliteConnection {
+    fn create_user_token(&mut self, t: UserToken) -> Result<()> {
+        diesel::insert_into(schema::user_tokens::table)
+            .values(&models::UserToken::from(t))
+            .execute(self)?;
+        Ok(())
+    }
+    fn get_user_token(&self, t_id: &str) -> Result<UserToken> {
+        use self::schema::user_tokens::dsl::*;
+        Ok(user_tokens
+            .find(t_id)
+            .first::<models::UserToken>(self)
+            .map(UserToken::from)?)
+    }
+    fn get_user_token_by_hash(&self, hash: &str) -> Result<UserToken> {
+        use self::schema::user_tokens::dsl::*;
+        Ok(user_tokens
+            .filter(token_hash.eq(hash))
+            .first::<models::UserToken>(self)
+            .map(UserToken::from)?)
+    }
+    fn user_tokens(&self, user_name: &str) -> Result<Vec<UserToken>> {
+        use self::schema::user_tokens::dsl;
+        Ok(dsl::user_tokens
+            .filter(dsl::username.eq(user_name))
+            .load::<models::UserToken>(self)?
+            .into_iter()
+            .map(UserToken::from)
+            .collect())
+    }
+    fn delete_user_token(&mut self, t_id: &str) -> Result<()> {
+        use self::schema::user_tokens::dsl::*;
+        let count = diesel::delete(user_tokens.find(t_id)).execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+impl RatingGateway for SqliteConnection {
+    fn create_rating(&mut self, r: Rating) -> Result<()> {
+        let entry_id = r.entry_id.clone();
+        self.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::insert_into(schema::ratings::table)
+                .values(&models::Rating::from(r))
+                .execute(self)?;
+            update_avg_ratings(self, &entry_id)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+    fn get_rating(&self, r_id: &str) -> Result<Rating> {
+        use self::schema::ratings::dsl::*;
+        Ok(ratings
+            .find(r_id)
+            .first::<models::Rating>(self)
+            .map(Rating::from)?)
+    }
+    fn get_rating_of_user(
+        &self,
+        e_id: &str,
+        ctx: &RatingContext,
+        username: &str,
+    ) -> Result<Rating> {
+        use self::schema::ratings::dsl::*;
+        Ok(ratings
+            .filter(entry_id.eq(e_id))
+            .filter(context.eq(String::from(ctx.clone())))
+            .filter(created_by.eq(username))
+            .first::<models::Rating>(self)
+            .map(Rating::from)?)
+    }
+    fn all_ratings(&self) -> Result<Vec<Rating>> {
+        use self::schema::ratings::dsl::*;
+        Ok(ratings
+            .load::<models::Rating>(self)?
+            .into_iter()
+            .map(Rating::from)
+            .collect())
+    }
+    fn ratings_of_entry(&self, e_id: &str) -> Result<Vec<Rating>> {
+        use self::schema::ratings::dsl::*;
+        Ok(ratings
+            .filter(entry_id.eq(e_id))
+            .load::<models::Rating>(self)?
+            .into_iter()
+            .map(Rating::from)
+            .collect())
+    }
+    fn update_rating(&mut self, r: &Rating) -> Result<()> {
+        let r = models::Rating::from(r.clone());
+        self.transaction::<_, diesel::result::Error, _>(|| {
+            use self::schema::ratings::dsl;
+            let count = diesel::update(dsl::ratings.filter(dsl::id.eq(&r.id)))
+                .set(&r)
+                .execute(self)?;
+            if count == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+            update_avg_ratings(self, &r.entry_id)?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+    fn delete_rating(&mut self, id: &str) -> Result<()> {
+        self.transaction::<_, diesel::result::Error, _>(|| {
+            use self::schema::{comments::dsl as c_dsl, ratings::dsl as r_dsl};
+            let entry_id = r_dsl::ratings
+                .find(id)
+                .select(r_dsl::entry_id)
+                .first::<String>(self)?;
+            diesel::delete(c_dsl::comments.filter(c_dsl::rating_id.eq(id))).execute(self)?;
+            diesel::delete(r_dsl::ratings.find(id)).execute(self)?;
+            update_avg_ratings(self, &entry_id)?;
+            Ok(())
+        })?;
         Ok(())
     }
 }
@@ -466,6 +760,13 @@ impl CommentGateway for SqliteConnection {
             .execute(self)?;
         Ok(())
     }
+    fn get_comment(&self, c_id: &str) -> Result<Comment> {
+        use self::schema::comments::dsl::*;
+        Ok(comments
+            .find(c_id)
+            .first::<models::Comment>(self)
+            .map(Comment::from)?)
+    }
     fn all_comments(&self) -> Result<Vec<Comment>> {
         use self::schema::comments::dsl::*;
         Ok(comments
@@ -474,6 +775,39 @@ impl CommentGateway for SqliteConnection {
             .map(Comment::from)
             .collect())
     }
+    fn comments_of_rating(&self, r_id: &str) -> Result<Vec<Comment>> {
+        use self::schema::comments::dsl::*;
+        Ok(comments
+            .filter(rating_id.eq(r_id))
+            .load::<models::Comment>(self)?
+            .into_iter()
+            .map(Comment::from)
+            .collect())
+    }
+    fn update_comment(&mut self, c: &Comment) -> Result<()> {
+        use self::schema::comments::dsl;
+        let comment = models::Comment::from(c.clone());
+        let count = diesel::update(dsl::comments.find(&c.id))
+            .set(&comment)
+            .execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+    fn delete_comments(&mut self, ids: &[String]) -> Result<()> {
+        use self::schema::comments::dsl;
+        self.transaction::<_, diesel::result::Error, _>(|| {
+            for id in ids {
+                let count = diesel::delete(dsl::comments.find(id)).execute(self)?;
+                if count == 0 {
+                    return Err(diesel::result::Error::NotFound);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
 }
 
 impl Db for SqliteConnection {
@@ -523,12 +857,6 @@ impl Db for SqliteConnection {
         }
         Ok(())
     }
-    fn create_rating(&mut self, r: Rating) -> Result<()> {
-        diesel::insert_into(schema::ratings::table)
-            .values(&models::Rating::from(r))
-            .execute(self)?;
-        Ok(())
-    }
     fn create_bbox_subscription(&mut self, sub: &BboxSubscription) -> Result<()> {
         diesel::insert_into(schema::bbox_subscriptions::table)
             .values(&models::BboxSubscription::from(sub.clone()))
@@ -543,6 +871,22 @@ impl Db for SqliteConnection {
             .map(BboxSubscription::from)
             .collect())
     }
+    fn get_bbox_subscription(&self, id: &str) -> Result<BboxSubscription> {
+        use self::schema::bbox_subscriptions::dsl;
+        Ok(dsl::bbox_subscriptions
+            .find(id)
+            .first::<models::BboxSubscription>(self)
+            .map(BboxSubscription::from)?)
+    }
+    fn bbox_subscriptions_by_username(&self, user_name: &str) -> Result<Vec<BboxSubscription>> {
+        use self::schema::bbox_subscriptions::dsl;
+        Ok(dsl::bbox_subscriptions
+            .filter(dsl::username.eq(user_name))
+            .load::<models::BboxSubscription>(self)?
+            .into_iter()
+            .map(BboxSubscription::from)
+            .collect())
+    }
     fn delete_bbox_subscription(&mut self, id: &str) -> Result<()> {
         use self::schema::bbox_subscriptions::dsl;
         diesel::delete(dsl::bbox_subscriptions.find(id)).execute(self)?;
@@ -564,14 +908,249 @@ impl Db for SqliteConnection {
             .map(Tag::from)
             .collect())
     }
-    fn all_ratings(&self) -> Result<Vec<Rating>> {
-        use self::schema::ratings::dsl::*;
-        Ok(ratings
-            .load::<models::Rating>(self)?
+}
+
+impl AvgRatingGateway for SqliteConnection {
+    fn get_avg_ratings(&self, e_id: &str) -> Result<AvgRatings> {
+        use self::schema::entry_ratings::dsl;
+        let avg = dsl::entry_ratings
+            .find(e_id)
+            .first::<models::AvgRatings>(self)
+            .optional()?
+            .map(AvgRatings::from)
+            .unwrap_or_else(|| AvgRatings {
+                entry_id: e_id.into(),
+                ..Default::default()
+            });
+        Ok(avg)
+    }
+    fn all_avg_ratings(&self) -> Result<Vec<AvgRatings>> {
+        use self::schema::entry_ratings::dsl;
+        Ok(dsl::entry_ratings
+            .load::<models::AvgRatings>(self)?
             .into_iter()
-            .map(Rating::from)
+            .map(AvgRatings::from)
+            .collect())
+    }
+}
+
+impl ReportGateway for SqliteConnection {
+    fn create_report(&mut self, r: Report) -> Result<()> {
+        diesel::insert_into(schema::reports::table)
+            .values(&models::Report::from(r))
+            .execute(self)?;
+        Ok(())
+    }
+    fn get_report(&self, r_id: &str) -> Result<Report> {
+        use self::schema::reports::dsl::*;
+        Ok(reports
+            .find(r_id)
+            .first::<models::Report>(self)
+            .map(Report::from)?)
+    }
+    fn all_reports(&self) -> Result<Vec<Report>> {
+        use self::schema::reports::dsl::*;
+        Ok(reports
+            .load::<models::Report>(self)?
+            .into_iter()
+            .map(Report::from)
+            .collect())
+    }
+    fn update_report(&mut self, r: &Report) -> Result<()> {
+        use self::schema::reports::dsl;
+        let report = models::Report::from(r.clone());
+        let count = diesel::update(dsl::reports.find(&r.id))
+            .set(&report)
+            .execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+impl NotificationGateway for SqliteConnection {
+    fn create_pending_notification(&mut self, n: PendingNotification) -> Result<()> {
+        diesel::insert_into(schema::pending_notifications::table)
+            .values(&models::PendingNotification::from(n))
+            .execute(self)?;
+        Ok(())
+    }
+    fn all_pending_notifications(&self) -> Result<Vec<PendingNotification>> {
+        use self::schema::pending_notifications::dsl::*;
+        Ok(pending_notifications
+            .load::<models::PendingNotification>(self)?
+            .into_iter()
+            .map(PendingNotification::from)
+            .collect())
+    }
+    fn delete_pending_notification(&mut self, n_id: &str) -> Result<()> {
+        use self::schema::pending_notifications::dsl::*;
+        let count = diesel::delete(pending_notifications.find(n_id)).execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+    fn create_contact_opt_out(&mut self, email: &str) -> Result<()> {
+        diesel::insert_into(schema::contact_opt_outs::table)
+            .values(&models::ContactOptOut {
+                email: email.to_lowercase(),
+                created: chrono::Utc::now().timestamp(),
+            })
+            .execute(self)?;
+        Ok(())
+    }
+    fn is_contact_opted_out(&self, e: &str) -> Result<bool> {
+        use self::schema::contact_opt_outs::dsl::*;
+        let count: i64 = contact_opt_outs
+            .filter(email.eq(e.to_lowercase()))
+            .count()
+            .get_result(self)?;
+        Ok(count > 0)
+    }
+}
+
+impl OutboxGateway for SqliteConnection {
+    fn create_outbox_mail(&mut self, m: OutboxMail) -> Result<()> {
+        diesel::insert_into(schema::outbox_mails::table)
+            .values(&models::OutboxMail::from(m))
+            .execute(self)?;
+        Ok(())
+    }
+    fn get_outbox_mail(&self, m_id: &str) -> Result<OutboxMail> {
+        use self::schema::outbox_mails::dsl::*;
+        Ok(outbox_mails
+            .find(m_id)
+            .first::<models::OutboxMail>(self)
+            .map(OutboxMail::from)?)
+    }
+    fn all_outbox_mails(&self) -> Result<Vec<OutboxMail>> {
+        use self::schema::outbox_mails::dsl::*;
+        Ok(outbox_mails
+            .load::<models::OutboxMail>(self)?
+            .into_iter()
+            .map(OutboxMail::from)
+            .collect())
+    }
+    fn due_outbox_mails(&self, now: u64) -> Result<Vec<OutboxMail>> {
+        use self::schema::outbox_mails::dsl::*;
+        Ok(outbox_mails
+            .filter(status.eq(String::from(MailStatus::Pending)))
+            .filter(next_attempt.le(now as i64))
+            .order_by(created)
+            .load::<models::OutboxMail>(self)?
+            .into_iter()
+            .map(OutboxMail::from)
+            .collect())
+    }
+    fn update_outbox_mail(&mut self, m: &OutboxMail) -> Result<()> {
+        use self::schema::outbox_mails::dsl;
+        let mail = models::OutboxMail::from(m.clone());
+        let count = diesel::update(dsl::outbox_mails.find(&m.id))
+            .set(&mail)
+            .execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+    fn delete_outbox_mail(&mut self, m_id: &str) -> Result<()> {
+        use self::schema::outbox_mails::dsl::*;
+        let count = diesel::delete(outbox_mails.find(m_id)).execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+impl WebhookGateway for SqliteConnection {
+    fn create_webhook(&mut self, w: Webhook) -> Result<()> {
+        diesel::insert_into(schema::webhooks::table)
+            .values(&models::Webhook::from(w))
+            .execute(self)?;
+        Ok(())
+    }
+    fn get_webhook(&self, w_id: &str) -> Result<Webhook> {
+        use self::schema::webhooks::dsl::*;
+        Ok(webhooks
+            .find(w_id)
+            .first::<models::Webhook>(self)
+            .map(Webhook::from)?)
+    }
+    fn all_webhooks(&self) -> Result<Vec<Webhook>> {
+        use self::schema::webhooks::dsl::*;
+        Ok(webhooks
+            .load::<models::Webhook>(self)?
+            .into_iter()
+            .map(Webhook::from)
+            .collect())
+    }
+    fn delete_webhook(&mut self, w_id: &str) -> Result<()> {
+        use self::schema::{webhook_deliveries::dsl as d_dsl, webhooks::dsl};
+        self.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(d_dsl::webhook_deliveries.filter(d_dsl::webhook_id.eq(w_id)))
+                .execute(self)?;
+            let count = diesel::delete(dsl::webhooks.find(w_id)).execute(self)?;
+            if count == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+    fn create_webhook_delivery(&mut self, d: WebhookDelivery) -> Result<()> {
+        diesel::insert_into(schema::webhook_deliveries::table)
+            .values(&models::WebhookDelivery::from(d))
+            .execute(self)?;
+        Ok(())
+    }
+    fn get_webhook_delivery(&self, d_id: &str) -> Result<WebhookDelivery> {
+        use self::schema::webhook_deliveries::dsl::*;
+        Ok(webhook_deliveries
+            .find(d_id)
+            .first::<models::WebhookDelivery>(self)
+            .map(WebhookDelivery::from)?)
+    }
+    fn all_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>> {
+        use self::schema::webhook_deliveries::dsl::*;
+        Ok(webhook_deliveries
+            .load::<models::WebhookDelivery>(self)?
+            .into_iter()
+            .map(WebhookDelivery::from)
             .collect())
     }
+    fn due_webhook_deliveries(&self, now: u64) -> Result<Vec<WebhookDelivery>> {
+        use self::schema::webhook_deliveries::dsl::*;
+        Ok(webhook_deliveries
+            .filter(status.eq(String::from(WebhookDeliveryStatus::Pending)))
+            .filter(next_attempt.le(now as i64))
+            .order_by(created)
+            .load::<models::WebhookDelivery>(self)?
+            .into_iter()
+            .map(WebhookDelivery::from)
+            .collect())
+    }
+    fn update_webhook_delivery(&mut self, d: &WebhookDelivery) -> Result<()> {
+        use self::schema::webhook_deliveries::dsl;
+        let delivery = models::WebhookDelivery::from(d.clone());
+        let count = diesel::update(dsl::webhook_deliveries.find(&d.id))
+            .set(&delivery)
+            .execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+    fn delete_webhook_delivery(&mut self, d_id: &str) -> Result<()> {
+        use self::schema::webhook_deliveries::dsl::*;
+        let count = diesel::delete(webhook_deliveries.find(d_id)).execute(self)?;
+        if count == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
 }
 
 impl OrganizationGateway for SqliteConnection {
@@ -22,6 +22,8 @@ pub struct Entry {
     pub license: Option<String>,
     pub image_url: Option<String>,
     pub image_link_url: Option<String>,
+    pub archived: Option<i64>,
+    pub pending: bool,
 }
 
 #[derive(Queryable, Insertable, AsChangeset)]
@@ -104,6 +106,7 @@ pub struct Tag {
 #[derive(Identifiable, Queryable, Insertable, AsChangeset)]
 #[table_name = "users"]
 #[primary_key(username)]
+#[changeset_options(treat_none_as_null = "true")]
 pub struct User {
     pub id: String, // TOTO: remove
     pub username: String,
@@ -111,18 +114,21 @@ pub struct User {
     pub email: String,
     pub email_confirmed: bool,
     pub role: i16,
+    pub language: Option<String>,
 }
 
-#[derive(Queryable, Insertable)]
+#[derive(Queryable, Insertable, AsChangeset)]
 #[table_name = "comments"]
 pub struct Comment {
     pub id: String,
     pub created: i64,
     pub text: String,
     pub rating_id: String,
+    pub created_by: Option<String>,
+    pub parent_id: Option<String>,
 }
 
-#[derive(Queryable, Insertable, Associations)]
+#[derive(Queryable, Insertable, AsChangeset, Associations)]
 #[table_name = "ratings"]
 #[belongs_to(Entry, foreign_key = "entry_id")]
 pub struct Rating {
@@ -133,6 +139,27 @@ pub struct Rating {
     pub context: String,
     pub source: Option<String>,
     pub entry_id: String,
+    pub created_by: Option<String>,
+    pub updated: Option<i64>,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "entry_ratings"]
+pub struct AvgRatings {
+    pub entry_id: String,
+    pub total: f64,
+    pub diversity: f64,
+    pub diversity_count: i64,
+    pub renewable: f64,
+    pub renewable_count: i64,
+    pub fairness: f64,
+    pub fairness_count: i64,
+    pub humanity: f64,
+    pub humanity_count: i64,
+    pub transparency: f64,
+    pub transparency_count: i64,
+    pub solidarity: f64,
+    pub solidarity_count: i64,
 }
 
 #[derive(Queryable, Insertable, Associations)]
@@ -145,4 +172,105 @@ pub struct BboxSubscription {
     pub north_east_lat: f64,
     pub north_east_lng: f64,
     pub username: String,
+    pub name: Option<String>,
+    /// Comma separated category IDs
+    pub categories: String,
+    /// Comma separated tags
+    pub tags: String,
+    /// Comma separated kinds of changes
+    pub changes: String,
+    pub delivery: String,
+}
+
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "reports"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct Report {
+    pub id: String,
+    pub created: i64,
+    pub target: String,
+    pub target_id: String,
+    pub reason: String,
+    pub reported_by: Option<String>,
+    pub resolution: Option<String>,
+    pub resolved_by: Option<String>,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "login_histories"]
+pub struct LoginHistory {
+    pub username: String,
+    pub failed_attempts: i32,
+    pub last_failed_attempt: Option<i64>,
+    pub last_login: Option<i64>,
+}
+
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "outbox_mails"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct OutboxMail {
+    pub id: String,
+    pub created: i64,
+    pub recipients: String,
+    pub subject: String,
+    pub content: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt: i64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "webhooks"]
+pub struct Webhook {
+    pub id: String,
+    pub org_id: String,
+    pub created: i64,
+    pub url: String,
+    pub secret: String,
+    pub events: String,
+}
+
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "webhook_deliveries"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub created: i64,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt: i64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "contact_opt_outs"]
+pub struct ContactOptOut {
+    pub email: String,
+    pub created: i64,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "pending_notifications"]
+pub struct PendingNotification {
+    pub id: String,
+    pub username: String,
+    pub delivery: String,
+    pub created: i64,
+    pub kind: String,
+    pub title: String,
+    pub target_id: String,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "user_tokens"]
+pub struct UserToken {
+    pub id: String,
+    pub token_hash: String,
+    pub username: String,
+    pub created: i64,
+    pub expires: i64,
 }
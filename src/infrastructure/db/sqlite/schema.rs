@@ -6,6 +6,11 @@ table! {
         north_east_lat -> Double,
         north_east_lng -> Double,
         username -> Text,
+        name -> Nullable<Text>,
+        categories -> Text,
+        tags -> Text,
+        changes -> Text,
+        delivery -> Text,
     }
 }
 
@@ -24,6 +29,15 @@ table! {
         created -> BigInt,
         text -> Text,
         rating_id -> Text,
+        created_by -> Nullable<Text>,
+        parent_id -> Nullable<Text>,
+    }
+}
+
+table! {
+    contact_opt_outs (email) {
+        email -> Text,
+        created -> BigInt,
     }
 }
 
@@ -48,6 +62,8 @@ table! {
         license -> Nullable<Text>,
         image_url -> Nullable<Text>,
         image_link_url -> Nullable<Text>,
+        archived -> Nullable<BigInt>,
+        pending -> Bool,
     }
 }
 
@@ -59,6 +75,25 @@ table! {
     }
 }
 
+table! {
+    entry_ratings (entry_id) {
+        entry_id -> Text,
+        total -> Double,
+        diversity -> Double,
+        diversity_count -> BigInt,
+        renewable -> Double,
+        renewable_count -> BigInt,
+        fairness -> Double,
+        fairness_count -> BigInt,
+        humanity -> Double,
+        humanity_count -> BigInt,
+        transparency -> Double,
+        transparency_count -> BigInt,
+        solidarity -> Double,
+        solidarity_count -> BigInt,
+    }
+}
+
 table! {
     entry_tag_relations (entry_id, entry_version, tag_id) {
         entry_id -> Text,
@@ -110,6 +145,21 @@ table! {
         context -> Text,
         source -> Nullable<Text>,
         entry_id -> Text,
+        created_by -> Nullable<Text>,
+        updated -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    reports (id) {
+        id -> Text,
+        created -> BigInt,
+        target -> Text,
+        target_id -> Text,
+        reason -> Text,
+        reported_by -> Nullable<Text>,
+        resolution -> Nullable<Text>,
+        resolved_by -> Nullable<Text>,
     }
 }
 
@@ -119,6 +169,16 @@ table! {
     }
 }
 
+table! {
+    user_tokens (id) {
+        id -> Text,
+        token_hash -> Text,
+        username -> Text,
+        created -> BigInt,
+        expires -> BigInt,
+    }
+}
+
 table! {
     users (username) {
         id -> Text,
@@ -127,6 +187,42 @@ table! {
         email -> Text,
         email_confirmed -> Bool,
         role -> SmallInt,
+        language -> Nullable<Text>,
+    }
+}
+
+table! {
+    login_histories (username) {
+        username -> Text,
+        failed_attempts -> Integer,
+        last_failed_attempt -> Nullable<BigInt>,
+        last_login -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    outbox_mails (id) {
+        id -> Text,
+        created -> BigInt,
+        recipients -> Text,
+        subject -> Text,
+        content -> Text,
+        status -> Text,
+        attempts -> Integer,
+        next_attempt -> BigInt,
+        last_error -> Nullable<Text>,
+    }
+}
+
+table! {
+    pending_notifications (id) {
+        id -> Text,
+        username -> Text,
+        delivery -> Text,
+        created -> BigInt,
+        kind -> Text,
+        title -> Text,
+        target_id -> Text,
     }
 }
 
@@ -138,23 +234,61 @@ table! {
     }
 }
 
+table! {
+    webhooks (id) {
+        id -> Text,
+        org_id -> Text,
+        created -> BigInt,
+        url -> Text,
+        secret -> Text,
+        events -> Text,
+    }
+}
+
+table! {
+    webhook_deliveries (id) {
+        id -> Text,
+        webhook_id -> Text,
+        created -> BigInt,
+        event -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Integer,
+        next_attempt -> BigInt,
+        last_error -> Nullable<Text>,
+    }
+}
+
 joinable!(bbox_subscriptions -> users (username));
 joinable!(comments -> ratings (rating_id));
 joinable!(entry_category_relations -> categories (category_id));
 joinable!(entry_tag_relations -> tags (tag_id));
 joinable!(event_tag_relations -> events (event_id));
 joinable!(event_tag_relations -> tags (tag_id));
+joinable!(login_histories -> users (username));
+joinable!(pending_notifications -> users (username));
+joinable!(user_tokens -> users (username));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
 
 allow_tables_to_appear_in_same_query!(
     bbox_subscriptions,
     categories,
     comments,
+    contact_opt_outs,
     entries,
     entry_category_relations,
+    entry_ratings,
     entry_tag_relations,
     event_tag_relations,
     events,
+    login_histories,
+    outbox_mails,
+    pending_notifications,
     ratings,
+    reports,
     tags,
+    user_tokens,
     users,
+    webhook_deliveries,
+    webhooks,
 );
@@ -59,6 +59,8 @@ impl From<e::Entry> for Entry {
             license,
             image_url,
             image_link_url,
+            archived: None,
+            pending: false,
         }
     }
 }
@@ -319,6 +321,7 @@ impl From<User> for e::User {
             email,
             email_confirmed,
             role,
+            language,
         } = u;
         e::User {
             id,
@@ -326,6 +329,7 @@ impl From<User> for e::User {
             password,
             email,
             email_confirmed,
+            language,
             role: e::Role::from_i16(role).unwrap_or_else(|| {
                 warn!(
                     "Could not cast role from i16 (value: {}). Use {:?} instead.",
@@ -348,6 +352,7 @@ impl From<e::User> for User {
             email,
             email_confirmed,
             role,
+            language,
         } = u;
         User {
             id,
@@ -355,6 +360,7 @@ impl From<e::User> for User {
             password,
             email,
             email_confirmed,
+            language,
             role: role.to_i16().unwrap_or_else(|| {
                 warn!("Could not convert role {:?} to i16. Use 0 instead.", role);
                 0
@@ -370,12 +376,16 @@ impl From<Comment> for e::Comment {
             created,
             text,
             rating_id,
+            created_by,
+            parent_id,
         } = c;
         e::Comment {
             id,
             created: created as u64,
             text,
             rating_id,
+            created_by,
+            parent_id,
         }
     }
 }
@@ -387,12 +397,16 @@ impl From<e::Comment> for Comment {
             created,
             text,
             rating_id,
+            created_by,
+            parent_id,
         } = c;
         Comment {
             id,
             created: created as i64,
             text,
             rating_id,
+            created_by,
+            parent_id,
         }
     }
 }
@@ -407,15 +421,19 @@ impl From<Rating> for e::Rating {
             context,
             value,
             source,
+            created_by,
+            updated,
         } = r;
         e::Rating {
             id,
             entry_id,
             created: created as u64,
+            updated: updated.map(|t| t as u64),
             title,
             value: value as i8,
             context: context.parse().unwrap(),
             source,
+            created_by,
         }
     }
 }
@@ -425,20 +443,64 @@ impl From<e::Rating> for Rating {
         let e::Rating {
             id,
             created,
+            updated,
             title,
             context,
             value,
             source,
             entry_id,
+            created_by,
         } = r;
         Rating {
             id,
             created: created as i64,
+            updated: updated.map(|t| t as i64),
             title,
             value: i32::from(value),
             context: context.into(),
             source,
             entry_id,
+            created_by,
+        }
+    }
+}
+
+impl From<AvgRatings> for e::AvgRatings {
+    fn from(r: AvgRatings) -> e::AvgRatings {
+        let avg = |value, count| e::AvgRating {
+            value,
+            count: count as u64,
+        };
+        e::AvgRatings {
+            entry_id: r.entry_id,
+            total: r.total,
+            diversity: avg(r.diversity, r.diversity_count),
+            renewable: avg(r.renewable, r.renewable_count),
+            fairness: avg(r.fairness, r.fairness_count),
+            humanity: avg(r.humanity, r.humanity_count),
+            transparency: avg(r.transparency, r.transparency_count),
+            solidarity: avg(r.solidarity, r.solidarity_count),
+        }
+    }
+}
+
+impl From<e::AvgRatings> for AvgRatings {
+    fn from(r: e::AvgRatings) -> AvgRatings {
+        AvgRatings {
+            entry_id: r.entry_id,
+            total: r.total,
+            diversity: r.diversity.value,
+            diversity_count: r.diversity.count as i64,
+            renewable: r.renewable.value,
+            renewable_count: r.renewable.count as i64,
+            fairness: r.fairness.value,
+            fairness_count: r.fairness.count as i64,
+            humanity: r.humanity.value,
+            humanity_count: r.humanity.count as i64,
+            transparency: r.transparency.value,
+            transparency_count: r.transparency.count as i64,
+            solidarity: r.solidarity.value,
+            solidarity_count: r.solidarity.count as i64,
         }
     }
 }
@@ -452,9 +514,15 @@ impl From<BboxSubscription> for e::BboxSubscription {
             north_east_lat,
             north_east_lng,
             username,
+            name,
+            categories,
+            tags,
+            changes,
+            delivery,
         } = s;
         e::BboxSubscription {
             id,
+            name,
             bbox: e::Bbox {
                 south_west: e::Coordinate {
                     lat: south_west_lat as f64,
@@ -466,13 +534,29 @@ impl From<BboxSubscription> for e::BboxSubscription {
                 },
             },
             username,
+            categories: serde_json::from_str(&categories).unwrap(),
+            tags: serde_json::from_str(&tags).unwrap(),
+            changes: split_list(&changes)
+                .into_iter()
+                .map(|c| c.parse().unwrap())
+                .collect(),
+            delivery: delivery.parse().unwrap(),
         }
     }
 }
 
 impl From<e::BboxSubscription> for BboxSubscription {
     fn from(s: e::BboxSubscription) -> BboxSubscription {
-        let e::BboxSubscription { id, bbox, username } = s;
+        let e::BboxSubscription {
+            id,
+            name,
+            bbox,
+            username,
+            categories,
+            tags,
+            changes,
+            delivery,
+        } = s;
         BboxSubscription {
             id,
             south_west_lat: bbox.south_west.lat,
@@ -480,10 +564,347 @@ impl From<e::BboxSubscription> for BboxSubscription {
             north_east_lat: bbox.north_east.lat,
             north_east_lng: bbox.north_east.lng,
             username,
+            name,
+            categories: serde_json::to_string(&categories).unwrap(),
+            tags: serde_json::to_string(&tags).unwrap(),
+            changes: changes
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+                .join(","),
+            delivery: delivery.into(),
+        }
+    }
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .filter(|x| !x.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl From<e::ChangeKind> for String {
+    fn from(kind: e::ChangeKind) -> String {
+        match kind {
+            e::ChangeKind::NewEntry => "new_entry",
+            e::ChangeKind::UpdatedEntry => "updated_entry",
+            e::ChangeKind::NewEvent => "new_event",
+            e::ChangeKind::NewRating => "new_rating",
+        }
+        .into()
+    }
+}
+
+impl From<e::Delivery> for String {
+    fn from(delivery: e::Delivery) -> String {
+        match delivery {
+            e::Delivery::Instant => "instant",
+            e::Delivery::Daily => "daily",
+            e::Delivery::Weekly => "weekly",
+        }
+        .into()
+    }
+}
+
+impl FromStr for e::Delivery {
+    type Err = String;
+    fn from_str(delivery: &str) -> Result<e::Delivery, String> {
+        Ok(match delivery {
+            "instant" => e::Delivery::Instant,
+            "daily" => e::Delivery::Daily,
+            "weekly" => e::Delivery::Weekly,
+            _ => {
+                return Err(format!("invalid Delivery: '{}'", delivery));
+            }
+        })
+    }
+}
+
+impl From<PendingNotification> for e::PendingNotification {
+    fn from(n: PendingNotification) -> e::PendingNotification {
+        let PendingNotification {
+            id,
+            username,
+            delivery,
+            created,
+            kind,
+            title,
+            target_id,
+        } = n;
+        e::PendingNotification {
+            id,
+            username,
+            delivery: delivery.parse().unwrap(),
+            created: created as u64,
+            kind: kind.parse().unwrap(),
+            title,
+            target_id,
+        }
+    }
+}
+
+impl From<e::PendingNotification> for PendingNotification {
+    fn from(n: e::PendingNotification) -> PendingNotification {
+        let e::PendingNotification {
+            id,
+            username,
+            delivery,
+            created,
+            kind,
+            title,
+            target_id,
+        } = n;
+        PendingNotification {
+            id,
+            username,
+            delivery: delivery.into(),
+            created: created as i64,
+            kind: kind.into(),
+            title,
+            target_id,
+        }
+    }
+}
+
+impl From<e::MailStatus> for String {
+    fn from(status: e::MailStatus) -> String {
+        match status {
+            e::MailStatus::Pending => "pending",
+            e::MailStatus::Failed => "failed",
+        }
+        .into()
+    }
+}
+
+impl FromStr for e::MailStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<e::MailStatus, String> {
+        Ok(match status {
+            "pending" => e::MailStatus::Pending,
+            "failed" => e::MailStatus::Failed,
+            _ => {
+                return Err(format!("invalid MailStatus: '{}'", status));
+            }
+        })
+    }
+}
+
+impl From<OutboxMail> for e::OutboxMail {
+    fn from(m: OutboxMail) -> e::OutboxMail {
+        let OutboxMail {
+            id,
+            created,
+            recipients,
+            subject,
+            content,
+            status,
+            attempts,
+            next_attempt,
+            last_error,
+        } = m;
+        e::OutboxMail {
+            id,
+            created: created as u64,
+            recipients: split_list(&recipients),
+            subject,
+            content,
+            status: status.parse().unwrap(),
+            attempts: attempts as u32,
+            next_attempt: next_attempt as u64,
+            last_error,
+        }
+    }
+}
+
+impl From<e::OutboxMail> for OutboxMail {
+    fn from(m: e::OutboxMail) -> OutboxMail {
+        let e::OutboxMail {
+            id,
+            created,
+            recipients,
+            subject,
+            content,
+            status,
+            attempts,
+            next_attempt,
+            last_error,
+        } = m;
+        OutboxMail {
+            id,
+            created: created as i64,
+            recipients: recipients.join(","),
+            subject,
+            content,
+            status: status.into(),
+            attempts: attempts as i32,
+            next_attempt: next_attempt as i64,
+            last_error,
+        }
+    }
+}
+
+impl FromStr for e::WebhookEvent {
+    type Err = String;
+    fn from_str(event: &str) -> Result<e::WebhookEvent, String> {
+        Ok(match event {
+            "entry.created" => e::WebhookEvent::EntryCreated,
+            "entry.updated" => e::WebhookEvent::EntryUpdated,
+            "entry.archived" => e::WebhookEvent::EntryArchived,
+            "event.created" => e::WebhookEvent::EventCreated,
+            "event.updated" => e::WebhookEvent::EventUpdated,
+            "event.archived" => e::WebhookEvent::EventArchived,
+            "rating.created" => e::WebhookEvent::RatingCreated,
+            "rating.updated" => e::WebhookEvent::RatingUpdated,
+            "rating.archived" => e::WebhookEvent::RatingArchived,
+            _ => {
+                return Err(format!("invalid WebhookEvent: '{}'", event));
+            }
+        })
+    }
+}
+
+impl From<Webhook> for e::Webhook {
+    fn from(w: Webhook) -> e::Webhook {
+        let Webhook {
+            id,
+            org_id,
+            created,
+            url,
+            secret,
+            events,
+        } = w;
+        e::Webhook {
+            id,
+            org_id,
+            created: created as u64,
+            url,
+            secret,
+            events: split_list(&events)
+                .into_iter()
+                .map(|x| x.parse().unwrap())
+                .collect(),
+        }
+    }
+}
+
+impl From<e::Webhook> for Webhook {
+    fn from(w: e::Webhook) -> Webhook {
+        let e::Webhook {
+            id,
+            org_id,
+            created,
+            url,
+            secret,
+            events,
+        } = w;
+        Webhook {
+            id,
+            org_id,
+            created: created as i64,
+            url,
+            secret,
+            events: events
+                .into_iter()
+                .map(e::WebhookEvent::name)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+impl From<e::WebhookDeliveryStatus> for String {
+    fn from(status: e::WebhookDeliveryStatus) -> String {
+        match status {
+            e::WebhookDeliveryStatus::Pending => "pending",
+            e::WebhookDeliveryStatus::Failed => "failed",
+        }
+        .into()
+    }
+}
+
+impl FromStr for e::WebhookDeliveryStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<e::WebhookDeliveryStatus, String> {
+        Ok(match status {
+            "pending" => e::WebhookDeliveryStatus::Pending,
+            "failed" => e::WebhookDeliveryStatus::Failed,
+            _ => {
+                return Err(format!("invalid WebhookDeliveryStatus: '{}'", status));
+            }
+        })
+    }
+}
+
+impl From<WebhookDelivery> for e::WebhookDelivery {
+    fn from(d: WebhookDelivery) -> e::WebhookDelivery {
+        let WebhookDelivery {
+            id,
+            webhook_id,
+            created,
+            event,
+            payload,
+            status,
+            attempts,
+            next_attempt,
+            last_error,
+        } = d;
+        e::WebhookDelivery {
+            id,
+            webhook_id,
+            created: created as u64,
+            event: event.parse().unwrap(),
+            payload,
+            status: status.parse().unwrap(),
+            attempts: attempts as u32,
+            next_attempt: next_attempt as u64,
+            last_error,
+        }
+    }
+}
+
+impl From<e::WebhookDelivery> for WebhookDelivery {
+    fn from(d: e::WebhookDelivery) -> WebhookDelivery {
+        let e::WebhookDelivery {
+            id,
+            webhook_id,
+            created,
+            event,
+            payload,
+            status,
+            attempts,
+            next_attempt,
+            last_error,
+        } = d;
+        WebhookDelivery {
+            id,
+            webhook_id,
+            created: created as i64,
+            event: event.name().into(),
+            payload,
+            status: status.into(),
+            attempts: attempts as i32,
+            next_attempt: next_attempt as i64,
+            last_error,
         }
     }
 }
 
+impl FromStr for e::ChangeKind {
+    type Err = String;
+    fn from_str(kind: &str) -> Result<e::ChangeKind, String> {
+        Ok(match kind {
+            "new_entry" => e::ChangeKind::NewEntry,
+            "updated_entry" => e::ChangeKind::UpdatedEntry,
+            "new_event" => e::ChangeKind::NewEvent,
+            "new_rating" => e::ChangeKind::NewRating,
+            _ => {
+                return Err(format!("invalid ChangeKind: '{}'", kind));
+            }
+        })
+    }
+}
+
 impl From<e::RatingContext> for String {
     fn from(context: e::RatingContext) -> String {
         match context {
@@ -530,3 +951,175 @@ impl From<e::Organization> for Organization {
         }
     }
 }
+
+impl From<e::ReportTarget> for String {
+    fn from(target: e::ReportTarget) -> String {
+        match target {
+            e::ReportTarget::Entry => "entry",
+            e::ReportTarget::Rating => "rating",
+            e::ReportTarget::Comment => "comment",
+        }
+        .into()
+    }
+}
+
+impl FromStr for e::ReportTarget {
+    type Err = String;
+    fn from_str(target: &str) -> Result<e::ReportTarget, String> {
+        Ok(match target {
+            "entry" => e::ReportTarget::Entry,
+            "rating" => e::ReportTarget::Rating,
+            "comment" => e::ReportTarget::Comment,
+            _ => {
+                return Err(format!("invalid ReportTarget: '{}'", target));
+            }
+        })
+    }
+}
+
+impl From<e::ReportResolution> for String {
+    fn from(resolution: e::ReportResolution) -> String {
+        match resolution {
+            e::ReportResolution::Dismissed => "dismissed",
+            e::ReportResolution::Archived => "archived",
+            e::ReportResolution::Deleted => "deleted",
+        }
+        .into()
+    }
+}
+
+impl FromStr for e::ReportResolution {
+    type Err = String;
+    fn from_str(resolution: &str) -> Result<e::ReportResolution, String> {
+        Ok(match resolution {
+            "dismissed" => e::ReportResolution::Dismissed,
+            "archived" => e::ReportResolution::Archived,
+            "deleted" => e::ReportResolution::Deleted,
+            _ => {
+                return Err(format!("invalid ReportResolution: '{}'", resolution));
+            }
+        })
+    }
+}
+
+impl From<Report> for e::Report {
+    fn from(r: Report) -> e::Report {
+        let Report {
+            id,
+            created,
+            target,
+            target_id,
+            reason,
+            reported_by,
+            resolution,
+            resolved_by,
+        } = r;
+        e::Report {
+            id,
+            created: created as u64,
+            target: target.parse().unwrap(),
+            target_id,
+            reason,
+            reported_by,
+            resolution: resolution.map(|r| r.parse().unwrap()),
+            resolved_by,
+        }
+    }
+}
+
+impl From<e::Report> for Report {
+    fn from(r: e::Report) -> Report {
+        let e::Report {
+            id,
+            created,
+            target,
+            target_id,
+            reason,
+            reported_by,
+            resolution,
+            resolved_by,
+        } = r;
+        Report {
+            id,
+            created: created as i64,
+            target: target.into(),
+            target_id,
+            reason,
+            reported_by,
+            resolution: resolution.map(Into::into),
+            resolved_by,
+        }
+    }
+}
+
+impl From<LoginHistory> for e::LoginHistory {
+    fn from(h: LoginHistory) -> e::LoginHistory {
+        let LoginHistory {
+            username,
+            failed_attempts,
+            last_failed_attempt,
+            last_login,
+        } = h;
+        e::LoginHistory {
+            username,
+            failed_attempts: failed_attempts as u32,
+            last_failed_attempt: last_failed_attempt.map(|t| t as u64),
+            last_login: last_login.map(|t| t as u64),
+        }
+    }
+}
+
+impl From<e::LoginHistory> for LoginHistory {
+    fn from(h: e::LoginHistory) -> LoginHistory {
+        let e::LoginHistory {
+            username,
+            failed_attempts,
+            last_failed_attempt,
+            last_login,
+        } = h;
+        LoginHistory {
+            username,
+            failed_attempts: failed_attempts as i32,
+            last_failed_attempt: last_failed_attempt.map(|t| t as i64),
+            last_login: last_login.map(|t| t as i64),
+        }
+    }
+}
+
+impl From<UserToken> for e::UserToken {
+    fn from(t: UserToken) -> e::UserToken {
+        let UserToken {
+            id,
+            token_hash,
+            username,
+            created,
+            expires,
+        } = t;
+        e::UserToken {
+            id,
+            token_hash,
+            username,
+            created: created as u64,
+            expires: expires as u64,
+        }
+    }
+}
+
+impl From<e::UserToken> for UserToken {
+    fn from(t: e::UserToken) -> UserToken {
+        let e::UserToken {
+            id,
+            token_hash,
+            username,
+            created,
+            expires,
+        } = t;
+        UserToken {
+            id,
+            token_hash,
+            username,
+            created: created as i64,
+            expires: expires as i64,
+        }
+    }
+}
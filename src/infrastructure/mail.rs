@@ -1,9 +1,19 @@
+use super::cfg::{MailCfg, MailTransport};
 use chrono::*;
 use fast_chemail::is_valid_email;
+use lettre::{
+    smtp::authentication::Credentials, ClientSecurity, ClientTlsParameters, EmailAddress, Envelope,
+    SendableEmail, SmtpClient, Transport as _,
+};
+use native_tls::TlsConnector;
 use quoted_printable;
-use std::io::{Error, ErrorKind, Result};
-
-const FROM_ADDRESS: &str = "\"Karte von morgen\" <no-reply@kartevonmorgen.org>";
+use std::{
+    fs,
+    io::{prelude::*, Error, ErrorKind, Result},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use uuid::Uuid;
 
 // quoted_printable limits the length of lines to 76 chars
 // and otherwise inserts unintended line breaks! The max.
@@ -71,7 +81,67 @@ fn encode_header_field(name: &str, input: &str) -> String {
     encoded_output
 }
 
-pub fn create(to: &[String], subject: &str, body: &str) -> Result<String> {
+/// Composes a plain text mail. Notification mails pass the URL
+/// for unsubscribing with a single click (RFC 8058).
+pub fn create(
+    from: &str,
+    to: &[String],
+    subject: &str,
+    body: &str,
+    unsubscribe_url: Option<&str>,
+) -> Result<String> {
+    let content = format!(
+        "Content-Type:text/plain;charset=utf-8\r\n\r\n{body}",
+        body = body
+    );
+    compose(from, to, subject, unsubscribe_url, &content)
+}
+
+/// Composes a multipart/alternative mail with the plain text
+/// and an HTML variant of the body.
+pub fn create_with_html(
+    from: &str,
+    to: &[String],
+    subject: &str,
+    body: &str,
+    html: &str,
+    unsubscribe_url: Option<&str>,
+) -> Result<String> {
+    // "=_" never occurs in quoted-printable encoded text
+    let boundary = format!("=_{}", Uuid::new_v4().to_simple_ref());
+    let content = format!(
+        "Content-Type:multipart/alternative;boundary=\"{boundary}\"\r\n\r\n\
+         --{boundary}\r\n\
+         {text}\r\n\
+         --{boundary}\r\n\
+         {html}\r\n\
+         --{boundary}--\r\n",
+        boundary = boundary,
+        text = encode_part("text/plain", body),
+        html = encode_part("text/html", html),
+    );
+    compose(from, to, subject, unsubscribe_url, &content)
+}
+
+fn encode_part(content_type: &str, body: &str) -> String {
+    // Only CRLF is kept as a hard line break by the encoding
+    let body = body.replace("\r\n", "\n").replace('\n', LINE_BREAK);
+    format!(
+        "Content-Type:{};charset=utf-8\r\n\
+         Content-Transfer-Encoding:quoted-printable\r\n\r\n\
+         {}",
+        content_type,
+        quoted_printable::encode_to_str(body.as_bytes())
+    )
+}
+
+fn compose(
+    from: &str,
+    to: &[String],
+    subject: &str,
+    unsubscribe_url: Option<&str>,
+    content: &str,
+) -> Result<String> {
     let to: Vec<_> = to
         .into_iter()
         .filter(|m| is_valid_email(m))
@@ -87,19 +157,28 @@ pub fn create(to: &[String], subject: &str, body: &str) -> Result<String> {
 
     let now = Local::now();
 
+    let unsubscribe_headers = match unsubscribe_url {
+        Some(url) => format!(
+            "List-Unsubscribe:<{}>\r\nList-Unsubscribe-Post:List-Unsubscribe=One-Click\r\n",
+            url
+        ),
+        None => String::new(),
+    };
+
     let email = format!(
         "Date:{date}\r\n\
          From:{from}\r\n\
          To:{to}\r\n\
          {subject_header}\r\n\
+         {unsubscribe_headers}\
          MIME-Version:1.0\r\n\
-         Content-Type:text/plain;charset=utf-8\r\n\r\n\
-         {body}",
+         {content}",
         date = now.to_rfc2822(),
-        from = FROM_ADDRESS,
+        from = from,
         to = to.join(","),
         subject_header = encode_header_field("Subject", &subject),
-        body = body
+        unsubscribe_headers = unsubscribe_headers,
+        content = content
     );
 
     debug!("composed email: {}", &email);
@@ -107,37 +186,146 @@ pub fn create(to: &[String], subject: &str, body: &str) -> Result<String> {
     Ok(email)
 }
 
-#[cfg(all(not(test), feature = "email"))]
-pub mod sendmail {
-    use super::*;
-    use std::io::prelude::*;
-    use std::process::{Command, Stdio};
+/// Delivers composed mails to their recipients.
+pub trait Transport: Send + Sync {
+    fn send(&self, recipients: &[String], mail: &str) -> Result<()>;
+}
 
-    pub fn send(mail: &str) -> Result<()> {
+/// Creates the transport that is selected in the `[mail]` section.
+pub fn transport(cfg: &MailCfg) -> Box<Transport> {
+    match cfg.transport {
+        MailTransport::Sendmail => Box::new(Sendmail),
+        MailTransport::Smtp => Box::new(Smtp {
+            sender: cfg.sender_address.clone(),
+            host: cfg.smtp_host.clone(),
+            port: cfg.smtp_port,
+            username: cfg.smtp_username.clone(),
+            password: cfg.smtp_password.clone(),
+            starttls: cfg.smtp_starttls,
+            tls: cfg.smtp_tls,
+        }),
+        MailTransport::Directory => Box::new(Directory {
+            path: cfg.directory.clone(),
+        }),
+    }
+}
+
+/// Pipes the mails into the local `sendmail` binary.
+pub struct Sendmail;
+
+impl Transport for Sendmail {
+    fn send(&self, _: &[String], mail: &str) -> Result<()> {
         let mut child = Command::new("sendmail")
             .arg("-t")
             .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()?;
         child
             .stdin
             .as_mut()
             .ok_or_else(|| Error::new(ErrorKind::Other, "Could not get stdin"))?
             .write_all(mail.as_bytes())?;
-        child.wait_with_output()?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "sendmail failed ({}): {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
         Ok(())
     }
 }
 
+/// Submits the mails to an SMTP server.
+pub struct Smtp {
+    pub sender: String,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub starttls: bool,
+    pub tls: bool,
+}
+
+impl Transport for Smtp {
+    fn send(&self, recipients: &[String], mail: &str) -> Result<()> {
+        let connector = TlsConnector::new().map_err(other)?;
+        let tls = ClientTlsParameters::new(self.host.clone(), connector);
+        let security = if self.tls {
+            ClientSecurity::Wrapper(tls)
+        } else if self.starttls {
+            ClientSecurity::Required(tls)
+        } else {
+            ClientSecurity::Opportunistic(tls)
+        };
+        let encrypted = self.tls || self.starttls;
+        let mut client =
+            SmtpClient::new((self.host.as_str(), self.port), security).map_err(other)?;
+        if let Some(ref username) = self.username {
+            if !encrypted {
+                // never send the password in cleartext
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "SMTP credentials require an encrypted connection",
+                ));
+            }
+            let password = self.password.clone().unwrap_or_default();
+            client = client.credentials(Credentials::new(username.clone(), password));
+        }
+        let to = recipients
+            .iter()
+            .map(|r| EmailAddress::new(r.clone()))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(other)?;
+        let from = EmailAddress::new(self.sender.clone()).map_err(other)?;
+        let envelope = Envelope::new(Some(from), to).map_err(other)?;
+        let id = Uuid::new_v4().to_simple_ref().to_string();
+        let email = SendableEmail::new(envelope, id, mail.as_bytes().to_vec());
+        client.transport().send(email).map_err(other)?;
+        Ok(())
+    }
+}
+
+/// Writes every mail into an `.eml` file of a directory
+/// instead of delivering it (e.g. for testing).
+pub struct Directory {
+    pub path: PathBuf,
+}
+
+impl Transport for Directory {
+    fn send(&self, _: &[String], mail: &str) -> Result<()> {
+        fs::create_dir_all(&self.path)?;
+        let file_name = format!(
+            "{}-{}.eml",
+            Utc::now().timestamp(),
+            Uuid::new_v4().to_simple_ref()
+        );
+        fs::write(self.path.join(file_name), mail)
+    }
+}
+
+fn other<E: ToString>(err: E) -> Error {
+    Error::new(ErrorKind::Other, err.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const FROM: &str = "\"Karte von morgen\" <no-reply@kartevonmorgen.org>";
+
     #[test]
     fn create_simple_mail() {
         let mail = create(
+            FROM,
             &vec!["mail@test.org".into()],
             "My veeeeerrrrryyyyy looooonnnnnggggg Subject with äöüÄÖÜß Umlaute and even more characters that are distributed onto multiple lines",
             "Hello Mail",
+            None,
         ).unwrap();
         let expected =
             "From:\"Karte von morgen\" <no-reply@kartevonmorgen.org>\r\n\
@@ -151,9 +339,90 @@ mod tests {
         assert!(mail.contains(expected));
     }
 
+    #[test]
+    fn create_mail_with_unsubscribe_link() {
+        let mail = create(
+            FROM,
+            &["mail@test.org".into()],
+            "Subject",
+            "Hello Mail",
+            Some("https://api.ofdb.io/v0/unsubscribe/abc.123"),
+        )
+        .unwrap();
+        let expected = "Subject:=?UTF-8?Q?Subject?=\r\n\
+                        List-Unsubscribe:<https://api.ofdb.io/v0/unsubscribe/abc.123>\r\n\
+                        List-Unsubscribe-Post:List-Unsubscribe=One-Click\r\n\
+                        MIME-Version:1.0\r\n";
+        assert!(mail.contains(expected));
+        assert!(!create(
+            FROM,
+            &["mail@test.org".into()],
+            "Subject",
+            "Hello Mail",
+            None
+        )
+        .unwrap()
+        .contains("List-Unsubscribe"));
+    }
+
+    #[test]
+    fn create_multipart_mail_with_html() {
+        let mail = create_with_html(
+            FROM,
+            &["mail@test.org".into()],
+            "Subject",
+            "Grüße\nan alle",
+            "<p>Grüße</p>",
+            None,
+        )
+        .unwrap();
+        let start = mail.find("boundary=\"").unwrap() + "boundary=\"".len();
+        let boundary = &mail[start..start + mail[start..].find('"').unwrap()];
+        assert!(boundary.starts_with("=_"));
+        let expected = format!(
+            "MIME-Version:1.0\r\n\
+             Content-Type:multipart/alternative;boundary=\"{b}\"\r\n\r\n\
+             --{b}\r\n\
+             Content-Type:text/plain;charset=utf-8\r\n\
+             Content-Transfer-Encoding:quoted-printable\r\n\r\n\
+             Gr=C3=BC=C3=9Fe\r\nan alle\r\n\
+             --{b}\r\n\
+             Content-Type:text/html;charset=utf-8\r\n\
+             Content-Transfer-Encoding:quoted-printable\r\n\r\n\
+             <p>Gr=C3=BC=C3=9Fe</p>\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        assert!(mail.ends_with(&expected));
+    }
+
+    #[test]
+    fn write_mails_into_a_directory() {
+        let path = std::env::temp_dir().join(Uuid::new_v4().to_simple_ref().to_string());
+        let transport = Directory { path: path.clone() };
+        let mail = create(
+            FROM,
+            &["mail@test.org".into()],
+            "Subject",
+            "Hello Mail",
+            None,
+        )
+        .unwrap();
+        transport.send(&["mail@test.org".into()], &mail).unwrap();
+        transport.send(&["mail@test.org".into()], &mail).unwrap();
+        let files: Vec<_> = fs::read_dir(&path)
+            .unwrap()
+            .map(|f| f.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|f| f.extension().unwrap() == "eml"));
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), mail);
+        fs::remove_dir_all(path).unwrap();
+    }
+
     #[test]
     fn check_addresses() {
-        assert!(create(&vec![], "foo", "bar").is_err());
-        assert!(create(&vec!["not-valid".into()], "foo", "bar").is_err());
+        assert!(create(FROM, &vec![], "foo", "bar", None).is_err());
+        assert!(create(FROM, &vec!["not-valid".into()], "foo", "bar", None).is_err());
     }
 }
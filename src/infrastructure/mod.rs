@@ -1,5 +1,9 @@
+pub mod cfg;
 mod db;
 pub mod error;
 #[cfg(feature = "email")]
 pub mod mail;
+pub mod notifications;
 pub mod osm;
+pub mod outbox;
+pub mod webhooks;
@@ -0,0 +1,152 @@
+use crate::{
+    adapters::{templates::Templates, user_communication},
+    core::{prelude::*, usecases},
+    infrastructure::{cfg::Cfg, error::AppError},
+    ports::web::sqlite::{create_connection_pool, transaction}, //TODO: import from infrastructure
+};
+
+#[cfg(feature = "email")]
+use crate::infrastructure::mail;
+#[cfg(feature = "email")]
+use std::io;
+#[cfg(not(feature = "email"))]
+use std::io::{Error, ErrorKind};
+
+use std::result;
+
+type Result<T> = result::Result<T, AppError>;
+
+/// Composes a notification mail with the links to unsubscribe
+/// from the given subscriptions (if signed links are enabled).
+/// Messages with an HTML body are sent as multipart mails.
+#[cfg(feature = "email")]
+pub fn create_mail(
+    cfg: &Cfg,
+    templates: &Templates,
+    recipient: &usecases::Recipient,
+    msg: &user_communication::Message,
+) -> io::Result<String> {
+    let links: Vec<_> = if cfg.notifications.signed_links() {
+        recipient
+            .subscriptions
+            .iter()
+            .map(|s| (s, cfg.notifications.unsubscribe_url(&s.id)))
+            .collect()
+    } else {
+        vec![]
+    };
+    let lang = recipient.language.as_ref().map(String::as_str);
+    let mut body = msg.body.clone();
+    if !links.is_empty() {
+        body += &user_communication::unsubscribe_footer(templates, lang, &links);
+    }
+    let unsubscribe_url = links.first().map(|(_, url)| url.as_str());
+    let html = msg.html.as_ref().and_then(|html| {
+        let footer = if links.is_empty() {
+            String::new()
+        } else {
+            user_communication::unsubscribe_footer_html(templates, lang, &links)?
+        };
+        templates.render_html(
+            lang,
+            "layout",
+            &[],
+            &[("content", html), ("footer", &footer)],
+        )
+    });
+    create(
+        cfg,
+        &[recipient.email.clone()],
+        &msg.subject,
+        &body,
+        html.as_ref().map(String::as_str),
+        unsubscribe_url,
+    )
+}
+
+/// Composes the mail to the contact of a changed entry.
+/// The message already contains the link to opt out.
+#[cfg(feature = "email")]
+pub fn create_entry_contact_mail(
+    cfg: &Cfg,
+    templates: &Templates,
+    email: &str,
+    msg: &user_communication::Message,
+    opt_out_url: &str,
+) -> io::Result<String> {
+    let html = msg.html.as_ref().and_then(|html| {
+        templates.render_html(None, "layout", &[], &[("content", html), ("footer", "")])
+    });
+    create(
+        cfg,
+        &[email.into()],
+        &msg.subject,
+        &msg.body,
+        html.as_ref().map(String::as_str),
+        Some(opt_out_url),
+    )
+}
+
+#[cfg(feature = "email")]
+fn create(
+    cfg: &Cfg,
+    to: &[String],
+    subject: &str,
+    body: &str,
+    html: Option<&str>,
+    unsubscribe_url: Option<&str>,
+) -> io::Result<String> {
+    match html {
+        Some(html) => {
+            mail::create_with_html(&cfg.mail.from(), to, subject, body, html, unsubscribe_url)
+        }
+        None => mail::create(&cfg.mail.from(), to, subject, body, unsubscribe_url),
+    }
+}
+
+#[cfg(feature = "email")]
+fn enqueue_digest<D: Db>(
+    db: &mut D,
+    cfg: &Cfg,
+    templates: &Templates,
+    d: &usecases::Digest,
+) -> Result<()> {
+    let lang = d.language.as_ref().map(String::as_str);
+    let msg = user_communication::digest_email(templates, lang, &d.notifications);
+    let recipient = usecases::Recipient {
+        email: d.email.clone(),
+        language: d.language.clone(),
+        subscriptions: d.subscriptions.clone(),
+    };
+    let mail = create_mail(cfg, templates, &recipient, &msg)?;
+    usecases::enqueue_mail(db, &[d.email.clone()], &msg.subject, &mail)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "email"))]
+fn enqueue_digest<D: Db>(_: &mut D, _: &Cfg, _: &Templates, _: &usecases::Digest) -> Result<()> {
+    Err(Error::new(ErrorKind::Other, "Sending e-mails is not supported").into())
+}
+
+/// Puts the digests of pending notifications into the outbox and
+/// removes the notifications of all digests that have been queued.
+/// Meant to be invoked periodically, e.g. by a cron job.
+pub fn send_digests(db_url: &str, cfg: &Cfg, delivery: Option<Delivery>) -> Result<()> {
+    let templates = cfg.mail.templates()?;
+    let pool = create_connection_pool(db_url)?;
+    let db = &mut *pool.get()?;
+    let digests = usecases::pending_digests(db, delivery)?;
+    debug!("sending {} digests", digests.len());
+    for d in digests {
+        // the notifications are only removed together with the queued digest
+        let res = transaction(db, |db| {
+            enqueue_digest(db, cfg, &templates, &d)?;
+            usecases::digest_sent(db, &d)?;
+            Ok(())
+        });
+        if let Err(err) = res {
+            warn!("Could not send digest to {}: {}", d.email, err);
+        }
+    }
+    Ok(())
+}
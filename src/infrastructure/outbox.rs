@@ -0,0 +1,77 @@
+use crate::{
+    core::{prelude::*, usecases},
+    infrastructure::error::AppError,
+    ports::web::sqlite::create_connection_pool, //TODO: import from infrastructure
+};
+
+#[cfg(feature = "email")]
+use crate::{
+    infrastructure::{cfg::OutboxCfg, mail::Transport},
+    ports::web::sqlite::ConnectionPool,
+};
+#[cfg(feature = "email")]
+use chrono::prelude::*;
+#[cfg(feature = "email")]
+use std::{thread, time::Duration};
+
+use std::result;
+
+type Result<T> = result::Result<T, AppError>;
+
+/// Tries to deliver all due mails of the outbox.
+#[cfg(feature = "email")]
+fn deliver_due_mails<D: Db>(
+    db: &mut D,
+    transport: &Transport,
+    backoff: &usecases::Backoff,
+) -> Result<()> {
+    let now = Utc::now().timestamp() as u64;
+    for m in usecases::due_mails(db, now)? {
+        match transport.send(&m.recipients, &m.content) {
+            Ok(_) => usecases::mail_delivered(db, &m.id)?,
+            Err(err) => {
+                warn!("Could not send e-mail {}: {}", m.id, err);
+                usecases::mail_delivery_failed(db, &m.id, &err.to_string(), backoff, now)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Delivers the mails of the outbox in the background.
+#[cfg(feature = "email")]
+pub fn spawn_worker(pool: ConnectionPool, cfg: OutboxCfg, transport: Box<Transport>) {
+    thread::spawn(move || loop {
+        match pool.get() {
+            Ok(mut db) => {
+                if let Err(err) = deliver_due_mails(&mut *db, &*transport, &cfg.backoff()) {
+                    warn!("Could not deliver the mails of the outbox: {}", err);
+                }
+            }
+            Err(err) => {
+                warn!("Could not connect to the database: {}", err);
+            }
+        }
+        thread::sleep(Duration::from_secs(cfg.poll_interval));
+    });
+}
+
+pub fn list_mails(db_url: &str, status: Option<MailStatus>) -> Result<Vec<OutboxMail>> {
+    let pool = create_connection_pool(db_url)?;
+    let db = &*pool.get()?;
+    Ok(usecases::outbox_mails(db, status)?)
+}
+
+/// Requeues the given mail or all failed mails
+/// and returns the number of requeued mails.
+pub fn requeue_mails(db_url: &str, id: Option<&str>) -> Result<usize> {
+    let pool = create_connection_pool(db_url)?;
+    let db = &mut *pool.get()?;
+    match id {
+        Some(id) => {
+            usecases::requeue_mail(db, id)?;
+            Ok(1)
+        }
+        None => Ok(usecases::requeue_failed_mails(db)?),
+    }
+}
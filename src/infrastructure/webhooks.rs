@@ -0,0 +1,376 @@
+use crate::{
+    core::{prelude::*, usecases},
+    infrastructure::{cfg::WebhooksCfg, error::AppError},
+    ports::web::sqlite::ConnectionPool,
+};
+use chrono::prelude::*;
+use hmac::{Hmac, Mac};
+use hyper::{
+    client::{Client, RedirectPolicy},
+    header::{ContentType, Headers},
+    net::{HttpStream, HttpsConnector, NetworkConnector},
+};
+use hyper_native_tls::NativeTlsClient;
+use sha2::Sha256;
+use std::{
+    collections::HashSet,
+    io,
+    net::{IpAddr, TcpStream, ToSocketAddrs},
+    result,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+type Result<T> = result::Result<T, AppError>;
+
+/// The hex encoded HMAC-SHA256 of the payload that allows
+/// the receivers to verify that the request has been sent by us.
+pub fn signature(secret: &str, payload: &str) -> String {
+    let mut hmac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    hmac.input(payload.as_bytes());
+    hmac.result()
+        .code()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The addresses of the host (none if it cannot be resolved).
+pub fn resolve(host: &str, port: u16) -> Vec<IpAddr> {
+    (host, port)
+        .to_socket_addrs()
+        .map(|addrs| addrs.map(|a| a.ip()).collect())
+        .unwrap_or_default()
+}
+
+/// Connects with a timeout and (unless `public_only` is disabled
+/// for tests) only to public addresses, because the host of a
+/// webhook might resolve to other addresses than on registration.
+struct Connector {
+    timeout: Duration,
+    public_only: bool,
+}
+
+impl NetworkConnector for Connector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, _: &str) -> hyper::Result<HttpStream> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let mut last_err = io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Could not resolve {}", host),
+        );
+        for ip in resolve(host, port) {
+            if self.public_only && !usecases::is_public_address(&ip) {
+                last_err = io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("{} is not a public address", ip),
+                );
+                continue;
+            }
+            match TcpStream::connect_timeout(&(ip, port).into(), self.timeout) {
+                Ok(stream) => return Ok(HttpStream(stream)),
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err.into())
+    }
+}
+
+fn client(timeout: Duration, public_only: bool) -> result::Result<Client, String> {
+    let tls = NativeTlsClient::new().map_err(|err| err.to_string())?;
+    let connector = Connector {
+        timeout,
+        public_only,
+    };
+    let mut client = Client::with_connector(HttpsConnector::with_connector(tls, connector));
+    client.set_redirect_policy(RedirectPolicy::FollowNone);
+    client.set_read_timeout(Some(timeout));
+    client.set_write_timeout(Some(timeout));
+    Ok(client)
+}
+
+/// Posts the payload to the webhook that has to respond with a 2xx status.
+fn deliver(client: &Client, w: &Webhook, d: &WebhookDelivery) -> result::Result<(), String> {
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    headers.set_raw("X-OFDB-Event", vec![d.event.name().as_bytes().to_vec()]);
+    headers.set_raw("X-OFDB-Delivery", vec![d.id.as_bytes().to_vec()]);
+    headers.set_raw(
+        "X-OFDB-Signature",
+        vec![format!("sha256={}", signature(&w.secret, &d.payload)).into_bytes()],
+    );
+    let res = client
+        .post(&w.url)
+        .headers(headers)
+        .body(d.payload.as_str())
+        .send()
+        .map_err(|err| err.to_string())?;
+    if res.status.is_success() {
+        Ok(())
+    } else {
+        Err(format!("Unexpected response: {}", res.status))
+    }
+}
+
+/// Loads the due payloads grouped by their webhooks.
+fn due_webhooks(pool: &ConnectionPool, now: u64) -> Result<Vec<(Webhook, Vec<WebhookDelivery>)>> {
+    let db = pool.get()?;
+    Ok(group_by_webhook(usecases::due_webhook_deliveries(
+        &*db, now,
+    )?))
+}
+
+fn group_by_webhook(due: Vec<(Webhook, WebhookDelivery)>) -> Vec<(Webhook, Vec<WebhookDelivery>)> {
+    let mut groups: Vec<(Webhook, Vec<WebhookDelivery>)> = vec![];
+    for (w, d) in due {
+        match groups.iter_mut().find(|(x, _)| x.id == w.id) {
+            Some((_, deliveries)) => deliveries.push(d),
+            None => groups.push((w, vec![d])),
+        }
+    }
+    groups
+}
+
+/// Delivers the payloads of a webhook in their order and records
+/// each outcome. It stops at the first failure, so that an unreachable
+/// webhook only takes a single timeout until its next attempt.
+fn deliver_in_order(
+    client: &Client,
+    w: &Webhook,
+    deliveries: &[WebhookDelivery],
+    record: &mut FnMut(&WebhookDelivery, result::Result<(), String>) -> Result<()>,
+) -> Result<()> {
+    for d in deliveries {
+        let res = deliver(client, w, d);
+        let failed = res.is_err();
+        if let Err(ref err) = res {
+            warn!("Could not deliver {} to {}: {}", d.id, w.url, err);
+        }
+        record(d, res)?;
+        if failed {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn record_outcome<D: Db>(
+    db: &mut D,
+    d: &WebhookDelivery,
+    res: result::Result<(), String>,
+    backoff: &usecases::Backoff,
+) -> Result<()> {
+    match res {
+        Ok(_) => usecases::webhook_delivered(db, &d.id)?,
+        Err(err) => {
+            let now = Utc::now().timestamp() as u64;
+            usecases::webhook_delivery_failed(db, &d.id, &err, backoff, now)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delivers the payloads of the webhooks in the background.
+/// Every webhook is served by its own thread that does not
+/// hold a database connection while it waits for the response,
+/// so a slow webhook only delays its own payloads.
+pub fn spawn_worker(pool: ConnectionPool, cfg: WebhooksCfg) {
+    thread::spawn(move || {
+        let client = match client(Duration::from_secs(cfg.timeout), true) {
+            Ok(client) => Arc::new(client),
+            Err(err) => {
+                error!("Could not create the HTTP client for webhooks: {}", err);
+                return;
+            }
+        };
+        let busy = Arc::new(Mutex::new(HashSet::new()));
+        loop {
+            let now = Utc::now().timestamp() as u64;
+            match due_webhooks(&pool, now) {
+                Ok(due) => {
+                    for (w, deliveries) in due {
+                        if !busy.lock().unwrap().insert(w.id.clone()) {
+                            // the payloads are still delivered by another thread
+                            continue;
+                        }
+                        let pool = pool.clone();
+                        let client = Arc::clone(&client);
+                        let busy = Arc::clone(&busy);
+                        let backoff = cfg.backoff();
+                        thread::spawn(move || {
+                            if let Err(err) =
+                                deliver_in_order(&client, &w, &deliveries, &mut |d, res| {
+                                    let mut db = pool.get()?;
+                                    record_outcome(&mut *db, d, res, &backoff)
+                                })
+                            {
+                                warn!("Could not record the deliveries of {}: {}", w.url, err);
+                            }
+                            busy.lock().unwrap().remove(&w.id);
+                        });
+                    }
+                }
+                Err(err) => {
+                    warn!("Could not load the payloads of webhooks: {}", err);
+                }
+            }
+            thread::sleep(Duration::from_secs(cfg.poll_interval));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::usecases::tests::MockDb;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    /// A local HTTP server that answers the requests with
+    /// the given status codes and returns the received requests.
+    fn stand_in(statuses: Vec<u16>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            statuses
+                .into_iter()
+                .map(|status| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut request = String::new();
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let lower = line.to_lowercase();
+                        if lower.starts_with("content-length:") {
+                            content_length =
+                                lower["content-length:".len()..].trim().parse().unwrap();
+                        }
+                        request.push_str(&lower);
+                        if line == "\r\n" {
+                            break;
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    request.push_str(&String::from_utf8(body).unwrap());
+                    write!(
+                        reader.get_mut(),
+                        "HTTP/1.1 {} Stand-in\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    )
+                    .unwrap();
+                    request
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn sign_payloads() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn deliver_signed_payloads_to_a_local_stand_in() {
+        let (url, stand_in) = stand_in(vec![200, 500]);
+        let mut db = MockDb::new();
+        db.webhooks = vec![Webhook {
+            id: "w".into(),
+            org_id: "o".into(),
+            created: 0,
+            url,
+            secret: "secret".into(),
+            events: vec![],
+        }];
+        let payload = "{\"event\":\"entry.created\",\"id\":\"e\"}";
+        usecases::enqueue_webhook_deliveries(&mut db, WebhookEvent::EntryCreated, payload).unwrap();
+        usecases::enqueue_webhook_deliveries(&mut db, WebhookEvent::EntryUpdated, "{}").unwrap();
+        let delivered = db.webhook_deliveries[0].id.clone();
+        let backoff = usecases::Backoff {
+            max_attempts: 3,
+            delay: 60,
+        };
+        // The stand-in listens on a loopback address
+        let client = client(Duration::from_secs(5), false).unwrap();
+        let now = db.webhook_deliveries[0].next_attempt;
+        let due = group_by_webhook(usecases::due_webhook_deliveries(&db, now).unwrap());
+        assert_eq!(due.len(), 1);
+        let (w, deliveries) = &due[0];
+        assert_eq!(deliveries.len(), 2);
+        deliver_in_order(&client, w, deliveries, &mut |d, res| {
+            record_outcome(&mut db, d, res, &backoff)
+        })
+        .unwrap();
+
+        let requests = stand_in.join().unwrap();
+        assert!(requests[0].starts_with("post /hook http/1.1\r\n"));
+        assert!(requests[0].contains("content-type: application/json\r\n"));
+        assert!(requests[0].contains("x-ofdb-event: entry.created\r\n"));
+        assert!(requests[0].contains(&format!("x-ofdb-delivery: {}\r\n", delivered)));
+        assert!(requests[0].contains(&format!(
+            "x-ofdb-signature: sha256={}\r\n",
+            signature("secret", payload)
+        )));
+        assert!(requests[0].ends_with(&format!("\r\n\r\n{}", payload)));
+        assert!(requests[1].contains("x-ofdb-event: entry.updated\r\n"));
+
+        assert_eq!(db.webhook_deliveries.len(), 1);
+        let failed = &db.webhook_deliveries[0];
+        assert_eq!(failed.status, WebhookDeliveryStatus::Pending);
+        assert_eq!(failed.attempts, 1);
+        assert!(failed.last_error.as_ref().unwrap().contains("500"));
+    }
+
+    #[test]
+    fn stop_at_the_first_failure_and_refuse_internal_addresses() {
+        let (url, stand_in) = stand_in(vec![]);
+        let w = Webhook {
+            id: "w".into(),
+            org_id: "o".into(),
+            created: 0,
+            url,
+            secret: "secret".into(),
+            events: vec![],
+        };
+        let mut db = MockDb::new();
+        db.webhooks = vec![w.clone()];
+        usecases::enqueue_webhook_deliveries(&mut db, WebhookEvent::EntryCreated, "{}").unwrap();
+        usecases::enqueue_webhook_deliveries(&mut db, WebhookEvent::EntryUpdated, "{}").unwrap();
+        let deliveries = db.webhook_deliveries.clone();
+        let backoff = usecases::Backoff {
+            max_attempts: 3,
+            delay: 60,
+        };
+        let client = client(Duration::from_secs(5), true).unwrap();
+        let mut attempts = vec![];
+        deliver_in_order(&client, &w, &deliveries, &mut |d, res| {
+            attempts.push(d.id.clone());
+            record_outcome(&mut db, d, res, &backoff)
+        })
+        .unwrap();
+        assert_eq!(attempts, vec![deliveries[0].id.clone()]);
+        let failed = db.get_webhook_delivery(&deliveries[0].id).unwrap();
+        assert!(failed
+            .last_error
+            .unwrap()
+            .contains("127.0.0.1 is not a public address"));
+        assert_eq!(
+            db.get_webhook_delivery(&deliveries[1].id).unwrap().attempts,
+            0
+        );
+        assert!(stand_in.join().unwrap().is_empty());
+    }
+}
@@ -1,10 +1,14 @@
 use super::web;
-use crate::infrastructure::osm;
+use crate::{
+    core::entities::{Delivery, MailStatus},
+    infrastructure::{cfg, notifications, osm, outbox},
+};
 use clap::{App, Arg, SubCommand};
 use dotenv::dotenv;
-use std::{env, process};
+use std::{env, io, path::Path, process};
 
 const DEFAULT_DB_URL: &str = "openfair.db";
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
 
 pub fn run() {
     dotenv().ok();
@@ -25,6 +29,13 @@ pub fn run() {
                 .value_name("DATABASE_URL")
                 .help("URL to the database"),
         )
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("CONFIG_FILE")
+                .help("Path to the configuration file"),
+        )
         .arg(
             Arg::with_name("enable-cors")
                 .long("enable-cors")
@@ -43,6 +54,43 @@ pub fn run() {
                         ),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("notifications")
+                .about("Subscription notifications")
+                .subcommand(
+                    SubCommand::with_name("send-digests")
+                        .about("send the digests of pending notifications")
+                        .arg(
+                            Arg::with_name("delivery")
+                                .long("delivery")
+                                .value_name("DELIVERY")
+                                .possible_values(&["daily", "weekly"])
+                                .help("Only send the digests of this kind of delivery"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("outbox")
+                .about("Outgoing mails")
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("list the mails that have not been delivered yet")
+                        .arg(
+                            Arg::with_name("failed")
+                                .long("failed")
+                                .help("Only list the mails that could not be delivered"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("requeue")
+                        .about("try to deliver failed mails again")
+                        .arg(
+                            Arg::with_name("id")
+                                .value_name("ID")
+                                .help("The mail to requeue (all failed mails if omitted)"),
+                        ),
+                ),
+        )
         .get_matches();
 
     let db_url = match matches.value_of("db-url") {
@@ -53,6 +101,22 @@ pub fn run() {
         },
     };
 
+    let cfg_file = match matches.value_of("config") {
+        Some(cfg_file) => Some(cfg_file),
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => Some(DEFAULT_CONFIG_FILE),
+        None => None,
+    };
+    let cfg = match cfg_file {
+        Some(cfg_file) => match cfg::load(cfg_file) {
+            Ok(cfg) => cfg,
+            Err(err) => {
+                println!("Could not load '{}': {}", cfg_file, err);
+                process::exit(1)
+            }
+        },
+        None => cfg::Cfg::default(),
+    };
+
     match matches.subcommand() {
         ("osm", Some(osm_matches)) => match osm_matches.subcommand() {
             ("import", Some(import_matches)) => {
@@ -70,6 +134,61 @@ pub fn run() {
             }
             _ => println!("{}", osm_matches.usage()),
         },
+        ("notifications", Some(notifications_matches)) => {
+            match notifications_matches.subcommand() {
+                ("send-digests", Some(digest_matches)) => {
+                    let delivery = match digest_matches.value_of("delivery") {
+                        Some("daily") => Some(Delivery::Daily),
+                        Some("weekly") => Some(Delivery::Weekly),
+                        _ => None,
+                    };
+                    check_signed_links(&cfg);
+                    if let Err(err) = notifications::send_digests(&db_url, &cfg, delivery) {
+                        println!("Could not send digests: {}", err);
+                        process::exit(1)
+                    }
+                }
+                _ => println!("{}", notifications_matches.usage()),
+            }
+        }
+        ("outbox", Some(outbox_matches)) => match outbox_matches.subcommand() {
+            ("list", Some(list_matches)) => {
+                let status = if list_matches.is_present("failed") {
+                    Some(MailStatus::Failed)
+                } else {
+                    None
+                };
+                match outbox::list_mails(&db_url, status) {
+                    Ok(mails) => {
+                        for m in mails {
+                            println!(
+                                "{}\t{:?}\t{}\t{}\t{}\t{}",
+                                m.id,
+                                m.status,
+                                m.attempts,
+                                m.recipients.join(","),
+                                m.subject,
+                                m.last_error.unwrap_or_default()
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        println!("Could not list the outbox: {}", err);
+                        process::exit(1)
+                    }
+                }
+            }
+            ("requeue", Some(requeue_matches)) => {
+                match outbox::requeue_mails(&db_url, requeue_matches.value_of("id")) {
+                    Ok(count) => println!("Requeued {} mails", count),
+                    Err(err) => {
+                        println!("Could not requeue: {}", err);
+                        process::exit(1)
+                    }
+                }
+            }
+            _ => println!("{}", outbox_matches.usage()),
+        },
         _ => {
             let port = match matches.value_of("port") {
                 Some(port) => port.parse::<u16>().unwrap(),
@@ -79,7 +198,27 @@ pub fn run() {
                 }
             };
 
-            web::run(&db_url, port, matches.is_present("enable-cors"));
+            check_signed_links(&cfg);
+            check_cfg(cfg.mail.check_smtp());
+            web::run(&db_url, port, matches.is_present("enable-cors"), cfg);
         }
     }
 }
+
+/// Refuses to start with an insecure or incomplete configuration.
+fn check_cfg(res: io::Result<()>) {
+    if let Err(err) = res {
+        println!("Invalid configuration: {}", err);
+        process::exit(1)
+    }
+}
+
+fn check_signed_links(cfg: &cfg::Cfg) {
+    check_cfg(cfg.notifications.check_secret());
+    if !cfg.notifications.signed_links() {
+        warn!(
+            "There is no `unsubscribe-secret` within the `[notifications]` section: \
+             the links to unsubscribe, opt out and revert changes are disabled"
+        );
+    }
+}
@@ -6,12 +6,35 @@ use rocket::request::{FromQuery, Query};
 #[post("/events", format = "application/json", data = "<e>")]
 pub fn post_event_with_token(
     mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
     token: Bearer,
+    spam_filter: State<SpamFilter>,
     e: Json<usecases::NewEvent>,
 ) -> Result<String> {
     let mut e = e.into_inner();
+    usecases::check_new_event_for_spam(&spam_filter, &e)?;
     e.token = Some(token.0);
-    let id = usecases::create_new_event(&mut *db, e.clone())?;
+    let id = db.transaction(|db| {
+        let id = usecases::create_new_event(db, e.clone())?;
+        let ev = usecases::get_event(db, &id)?;
+        if let Some(ref location) = ev.location {
+            let change = usecases::Change {
+                kind: ChangeKind::NewEvent,
+                id: &ev.id,
+                title: &ev.title,
+                categories: &[],
+                tags: &ev.tags,
+            };
+            let x = Coordinate {
+                lat: location.lat,
+                lng: location.lng,
+            };
+            util::notify_create_event(db, &cfg, &templates, &change, &x, &ev)?;
+        }
+        util::notify_event_webhooks(db, WebhookEvent::EventCreated, &id)?;
+        Ok(id)
+    })?;
     Ok(Json(id))
 }
 
@@ -49,6 +72,7 @@ pub fn put_event_with_token(
     let mut e = e.into_inner();
     e.token = Some(token.0);
     usecases::update_event(&mut *db, &id.to_string(), e.clone())?;
+    util::notify_event_webhooks(&mut *db, WebhookEvent::EventUpdated, &id.to_string())?;
     Ok(Json(()))
 }
 
@@ -113,6 +137,7 @@ pub fn get_events(db: DbConn, query: EventQuery) -> Result<Vec<json::Event>> {
 #[delete("/events/<id>")]
 pub fn delete_event_with_token(mut db: DbConn, token: Bearer, id: &RawStr) -> Result<()> {
     usecases::delete_event(&mut *db, &id.to_string(), &token.0)?;
+    util::notify_event_webhooks(&mut *db, WebhookEvent::EventArchived, &id.to_string())?;
     Ok(Json(()))
 }
 
@@ -265,6 +290,34 @@ mod tests {
             assert_eq!(res.status(), Status::Unauthorized);
         }
 
+        #[test]
+        fn bulk_import_with_api_token() {
+            let mut cfg = crate::infrastructure::cfg::Cfg::default();
+            cfg.spam.max_submissions_per_hour = 1;
+            cfg.spam.review_score = 1;
+            cfg.rate_limit.writes.burst = 1;
+            let (client, db) = setup_with_cfg(cfg);
+            db.get()
+                .unwrap()
+                .create_org(Organization {
+                    id: "foo".into(),
+                    name: "bar".into(),
+                    owned_tags: vec![],
+                    api_token: "foo".into(),
+                })
+                .unwrap();
+            for _ in 0..3 {
+                let res = client
+                    .post("/events")
+                    .header(ContentType::JSON)
+                    .header(Header::new("Authorization", "Bearer foo"))
+                    .remote("10.0.0.1:8000".parse().unwrap())
+                    .body(r#"{"title":"x","start":0,"created_by":"foo@bar.com"}"#)
+                    .dispatch();
+                assert_eq!(res.status(), Status::Ok);
+            }
+            assert_eq!(db.get().unwrap().all_events().unwrap().len(), 3);
+        }
     }
 
     mod read {
@@ -409,6 +462,7 @@ mod tests {
                     email: m.into(),
                     email_confirmed: true,
                     role: Role::default(),
+                    language: None,
                 })
                 .unwrap();
             }
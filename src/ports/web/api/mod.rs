@@ -1,12 +1,15 @@
 use super::{guards::*, sqlite::DbConn, util};
 use crate::{
-    adapters::{self, json, user_communication},
+    adapters::{self, json, templates::Templates, user_communication},
     core::{
         prelude::*,
         usecases::{self, DuplicateType},
-        util::geo,
+        util::{
+            geo,
+            spam::{SpamFilter, SubmissionLog, Verdict},
+        },
     },
-    infrastructure::error::AppError,
+    infrastructure::{cfg::Cfg, error::AppError},
 };
 
 use csv;
@@ -14,19 +17,26 @@ use rocket::{
     self,
     http::{ContentType, Cookie, Cookies, Status},
     request::Form,
-    response::{content::Content, Responder, Response},
-    Route,
+    response::{
+        content::{Content, Html},
+        Responder, Response,
+    },
+    Route, State,
 };
 use rocket_contrib::json::Json;
-use std::result;
+use std::{collections::HashMap, result, sync::Mutex};
 
 mod count;
 mod events;
+mod outbox;
 mod ratings;
+mod reports;
+mod review;
 mod search;
 #[cfg(test)]
 pub mod tests;
 mod users;
+mod webhooks;
 
 type Result<T> = result::Result<Json<T>, AppError>;
 
@@ -37,6 +47,8 @@ pub fn routes() -> Vec<Route> {
         confirm_email_address,
         subscribe_to_bbox,
         get_bbox_subscriptions,
+        post_bbox_subscription,
+        delete_bbox_subscription,
         unsubscribe_all_bboxes,
         get_entry,
         post_entry,
@@ -49,10 +61,31 @@ pub fn routes() -> Vec<Route> {
         events::put_event_with_token,
         events::delete_event_with_token,
         users::post_user,
+        users::export_user_data,
         ratings::post_rating,
         ratings::get_rating,
+        ratings::put_rating,
+        ratings::delete_rating,
+        ratings::post_comment,
+        ratings::put_comment,
+        ratings::delete_comment,
+        reports::post_report,
+        reports::get_reports,
+        reports::resolve_report,
+        outbox::get_outbox,
+        outbox::requeue_outbox_mail,
+        review::get_pending_entries,
+        review::approve_entry,
+        review::reject_entry,
         users::get_user,
+        users::patch_user,
         users::delete_user,
+        users::post_token,
+        users::get_tokens,
+        users::delete_token,
+        webhooks::post_webhook,
+        webhooks::get_webhooks,
+        webhooks::delete_webhook,
         get_categories,
         get_category,
         get_tags,
@@ -66,6 +99,19 @@ pub fn routes() -> Vec<Route> {
     ]
 }
 
+/// The routes of the signed links within notification mails
+/// that are only mounted if an `unsubscribe-secret` is configured.
+pub fn signed_link_routes() -> Vec<Route> {
+    routes![
+        get_unsubscribe,
+        post_unsubscribe,
+        get_contact_opt_out,
+        post_contact_opt_out,
+        get_revert_entry,
+        post_revert_entry
+    ]
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct UserId {
     u_id: String,
@@ -78,15 +124,15 @@ fn get_entry(db: DbConn, ids: String) -> Result<Vec<json::Entry>> {
     let ids = util::extract_ids(&ids);
     let entries = usecases::get_entries(&*db, &ids)?;
     let ratings = usecases::get_ratings_by_entry_ids(&*db, &ids)?;
-    Ok(Json(
-        entries
-            .into_iter()
-            .map(|e| {
-                let r = ratings.get(&e.id).cloned().unwrap_or_else(|| vec![]);
-                json::Entry::from_entry_with_ratings(e, r)
-            })
-            .collect::<Vec<json::Entry>>(),
-    ))
+    let entries = entries
+        .into_iter()
+        .map(|e| {
+            let r = ratings.get(&e.id).cloned().unwrap_or_else(|| vec![]);
+            let avg = db.get_avg_ratings(&e.id)?;
+            Ok(json::Entry::from_entry_with_ratings(e, r, avg))
+        })
+        .collect::<result::Result<Vec<json::Entry>, RepoError>>()?;
+    Ok(Json(entries))
 }
 
 #[get("/duplicates")]
@@ -160,37 +206,176 @@ fn get_bbox_subscriptions(db: DbConn, user: Login) -> Result<Vec<json::BboxSubsc
     let Login(username) = user;
     let user_subscriptions = usecases::get_bbox_subscriptions(&username, &*db)?
         .into_iter()
-        .map(|s| json::BboxSubscription {
-            id: s.id,
-            south_west_lat: s.bbox.south_west.lat,
-            south_west_lng: s.bbox.south_west.lng,
-            north_east_lat: s.bbox.north_east.lat,
-            north_east_lng: s.bbox.north_east.lng,
-        })
+        .map(json::BboxSubscription::from)
         .collect();
     Ok(Json(user_subscriptions))
 }
 
+#[post("/bbox-subscriptions", format = "application/json", data = "<s>")]
+fn post_bbox_subscription(
+    mut db: DbConn,
+    user: Login,
+    s: Json<usecases::NewBboxSubscription>,
+) -> Result<String> {
+    let Login(username) = user;
+    let id = usecases::create_bbox_subscription(&mut *db, &username, s.into_inner())?;
+    Ok(Json(id))
+}
+
+#[delete("/bbox-subscriptions/<id>")]
+fn delete_bbox_subscription(mut db: DbConn, user: Login, id: String) -> Result<()> {
+    let Login(username) = user;
+    usecases::delete_bbox_subscription(&mut *db, &username, &id)?;
+    Ok(Json(()))
+}
+
+/// The link within notification mails only asks for a confirmation
+/// because mail scanners and prefetchers follow all links.
+#[get("/unsubscribe/<_token>")]
+fn get_unsubscribe(templates: State<Templates>, _token: String) -> Html<String> {
+    Html(user_communication::confirmation_page(
+        &templates,
+        None,
+        "confirm_unsubscribe",
+        &[],
+    ))
+}
+
+/// One-click unsubscribe of mail clients (`List-Unsubscribe-Post`)
+#[post("/unsubscribe/<token>")]
+fn post_unsubscribe(mut db: DbConn, cfg: State<Cfg>, token: String) -> Result<()> {
+    usecases::unsubscribe_with_token(&mut *db, &cfg.notifications.unsubscribe_secret, &token)?;
+    Ok(Json(()))
+}
+
 #[post("/entries", format = "application/json", data = "<e>")]
-fn post_entry(mut db: DbConn, e: Json<usecases::NewEntry>) -> Result<String> {
+fn post_entry(
+    mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    spam_filter: State<SpamFilter>,
+    submissions: State<Mutex<SubmissionLog>>,
+    ip: ClientIp,
+    e: Json<usecases::NewEntry>,
+) -> Result<String> {
     let e = e.into_inner();
-    let id = usecases::create_new_entry(&mut *db, e.clone())?;
-    let email_addresses = usecases::email_addresses_by_coordinate(&mut *db, &e.lat, &e.lng)?;
-    let all_categories = db.all_categories()?;
-    util::notify_create_entry(&email_addresses, &e, &id, all_categories);
+    let recent_submissions = util::record_submission(&submissions, &ip);
+    let verdict = usecases::check_new_entry_for_spam(&*db, &spam_filter, &e, recent_submissions)?;
+    if cfg.entries.require_review || verdict == Verdict::Suspicious {
+        // subscribers are notified as soon as a scout approved the entry
+        let id = usecases::create_new_entry_for_review(&mut *db, e)?;
+        return Ok(Json(id));
+    }
+    let id = db.transaction(|db| {
+        let id = usecases::create_new_entry(db, e.clone())?;
+        let entry = db.get_entry(&id)?;
+        let change = usecases::Change {
+            kind: ChangeKind::NewEntry,
+            id: &entry.id,
+            title: &entry.title,
+            categories: &entry.categories,
+            tags: &entry.tags,
+        };
+        let all_categories = db.all_categories()?;
+        util::notify_create_entry(db, &cfg, &templates, &change, &e, &id, all_categories)?;
+        util::notify_entry_webhooks(db, WebhookEvent::EntryCreated, &id)?;
+        Ok(id)
+    })?;
     Ok(Json(id))
 }
 
 #[put("/entries/<id>", format = "application/json", data = "<e>")]
-fn put_entry(mut db: DbConn, id: String, e: Json<usecases::UpdateEntry>) -> Result<String> {
+fn put_entry(
+    mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    id: String,
+    e: Json<usecases::UpdateEntry>,
+) -> Result<String> {
     let e = e.into_inner();
-    usecases::update_entry(&mut *db, e.clone())?;
-    let email_addresses = usecases::email_addresses_by_coordinate(&mut *db, &e.lat, &e.lng)?;
-    let all_categories = db.all_categories()?;
-    util::notify_update_entry(&email_addresses, &e, all_categories);
+    db.transaction(|db| {
+        let old = db.get_entry(&e.id)?;
+        usecases::update_entry(db, e.clone())?;
+        let entry = db.get_entry(&e.id)?;
+        let change = usecases::Change {
+            kind: ChangeKind::UpdatedEntry,
+            id: &entry.id,
+            title: &entry.title,
+            categories: &entry.categories,
+            tags: &entry.tags,
+        };
+        let all_categories = db.all_categories()?;
+        util::notify_entry_contacts(db, &cfg, &templates, &old, &entry, &all_categories)?;
+        util::notify_update_entry(db, &cfg, &templates, &change, &e, all_categories)?;
+        util::notify_entry_webhooks(db, WebhookEvent::EntryUpdated, &entry.id)?;
+        Ok(())
+    })?;
     Ok(Json(id))
 }
 
+/// The link within the notifications to the contacts of an entry
+/// only asks for a confirmation (see `get_unsubscribe`).
+#[get("/entries/<id>/revert/<_token>")]
+fn get_revert_entry(
+    db: DbConn,
+    templates: State<Templates>,
+    id: String,
+    _token: String,
+) -> result::Result<Html<String>, AppError> {
+    let entry = db.get_entry(&id)?;
+    Ok(Html(user_communication::confirmation_page(
+        &templates,
+        None,
+        "confirm_revert_entry",
+        &[("title", &entry.title)],
+    )))
+}
+
+#[post("/entries/<id>/revert/<token>")]
+fn post_revert_entry(
+    mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    id: String,
+    token: String,
+) -> Result<u64> {
+    let secret = &cfg.notifications.unsubscribe_secret;
+    let entry = db.transaction(|db| {
+        let entry = usecases::revert_entry_with_token(db, secret, &id, &token)?;
+        let change = usecases::Change {
+            kind: ChangeKind::UpdatedEntry,
+            id: &entry.id,
+            title: &entry.title,
+            categories: &entry.categories,
+            tags: &entry.tags,
+        };
+        let all_categories = db.all_categories()?;
+        util::notify_revert_entry(db, &cfg, &templates, &change, &entry, all_categories)?;
+        util::notify_entry_webhooks(db, WebhookEvent::EntryUpdated, &entry.id)?;
+        Ok(entry)
+    })?;
+    Ok(Json(entry.version))
+}
+
+/// The link within the notifications to the contacts of an entry
+/// only asks for a confirmation (see `get_unsubscribe`).
+#[get("/contact-opt-out/<_token>")]
+fn get_contact_opt_out(templates: State<Templates>, _token: String) -> Html<String> {
+    Html(user_communication::confirmation_page(
+        &templates,
+        None,
+        "confirm_contact_opt_out",
+        &[],
+    ))
+}
+
+/// One-click opt-out of mail clients (`List-Unsubscribe-Post`)
+#[post("/contact-opt-out/<token>")]
+fn post_contact_opt_out(mut db: DbConn, cfg: State<Cfg>, token: String) -> Result<()> {
+    usecases::opt_out_contact_with_token(&mut *db, &cfg.notifications.unsubscribe_secret, &token)?;
+    Ok(Json(()))
+}
+
 #[get("/tags")]
 fn get_tags(db: DbConn) -> Result<Vec<String>> {
     Ok(Json(db.all_tags()?.into_iter().map(|t| t.id).collect()))
@@ -231,10 +416,11 @@ fn csv_export<'a>(
 
     let entries: Vec<_> = db.get_entries_by_bbox(&bbox)?;
     let all_categories: Vec<_> = db.all_categories()?;
-    let avg_ratings = match super::ENTRY_RATINGS.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => poisoned.into_inner(),
-    };
+    let avg_ratings: HashMap<_, _> = db
+        .all_avg_ratings()?
+        .into_iter()
+        .map(|r| (r.entry_id, r.total))
+        .collect();
 
     let entries_categories_and_ratings = entries
         .into_iter()
@@ -277,6 +463,7 @@ impl<'r> Responder<'r> for AppError {
                         ParameterError::EmailNotConfirmed => {
                             <Status>::new(403, "EmailNotConfirmed")
                         }
+                        ParameterError::LoginLocked => <Status>::new(403, "LoginLocked"),
                         ParameterError::Forbidden => Status::Forbidden,
                         ParameterError::Unauthorized => Status::Unauthorized,
                         _ => Status::BadRequest,
@@ -0,0 +1,27 @@
+use super::*;
+
+#[get("/outbox?<status>")]
+pub fn get_outbox(
+    db: DbConn,
+    user: Login,
+    status: Option<String>,
+) -> Result<Vec<json::OutboxMail>> {
+    usecases::check_admin(&*db, &user.0)?;
+    let status = match status.as_ref().map(String::as_str) {
+        None => None,
+        Some("pending") => Some(MailStatus::Pending),
+        Some("failed") => Some(MailStatus::Failed),
+        Some(_) => return Err(Error::Parameter(ParameterError::MailStatus).into()),
+    };
+    let mails = usecases::outbox_mails(&*db, status)?;
+    Ok(Json(
+        mails.into_iter().map(json::OutboxMail::from).collect(),
+    ))
+}
+
+#[post("/outbox/<id>/requeue")]
+pub fn requeue_outbox_mail(mut db: DbConn, user: Login, id: String) -> Result<()> {
+    usecases::check_admin(&*db, &user.0)?;
+    usecases::requeue_mail(&mut *db, &id)?;
+    Ok(Json(()))
+}
@@ -1,11 +1,118 @@
 use super::*;
 
 #[post("/ratings", format = "application/json", data = "<u>")]
-pub fn post_rating(mut db: DbConn, u: Json<usecases::RateEntry>) -> Result<()> {
-    let u = u.into_inner();
-    let e_id = u.entry.clone();
-    usecases::rate_entry(&mut *db, u)?;
-    super::super::calculate_rating_for_entry(&*db, &e_id)?;
+pub fn post_rating(
+    mut db: DbConn,
+    user: Option<Login>,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    u: Json<usecases::RateEntry>,
+) -> Result<()> {
+    if user.is_none() && cfg.ratings.require_login {
+        return Err(Error::Parameter(ParameterError::Unauthorized).into());
+    }
+    let mut u = u.into_inner();
+    // Never trust the author given in the request body
+    u.user = user.map(|Login(username)| username);
+    db.transaction(|db| {
+        let (rating_id, updated) = usecases::rate_entry(db, u.clone())?;
+        let event = if updated {
+            WebhookEvent::RatingUpdated
+        } else {
+            WebhookEvent::RatingCreated
+        };
+        let e = db.get_entry(&u.entry)?;
+        let change = usecases::Change {
+            kind: ChangeKind::NewRating,
+            id: &e.id,
+            title: &e.title,
+            categories: &e.categories,
+            tags: &e.tags,
+        };
+        util::notify_rating(db, &cfg, &templates, &change, &e, &u, updated)?;
+        util::notify_rating_webhooks(db, event, &rating_id)?;
+        Ok(())
+    })?;
+    Ok(Json(()))
+}
+
+#[put("/ratings/<id>", format = "application/json", data = "<u>")]
+pub fn put_rating(
+    mut db: DbConn,
+    user: Login,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    id: String,
+    u: Json<usecases::UpdateRating>,
+) -> Result<()> {
+    db.transaction(|db| {
+        usecases::update_rating(db, &user.0, &id, u.into_inner())?;
+        let r = db.get_rating(&id)?;
+        let e = db.get_entry(&r.entry_id)?;
+        let change = usecases::Change {
+            kind: ChangeKind::NewRating,
+            id: &e.id,
+            title: &e.title,
+            categories: &e.categories,
+            tags: &e.tags,
+        };
+        let rating = usecases::RateEntry {
+            entry: r.entry_id,
+            title: r.title,
+            value: r.value,
+            context: r.context,
+            comment: String::new(),
+            source: r.source,
+            user: r.created_by,
+        };
+        util::notify_rating(db, &cfg, &templates, &change, &e, &rating, true)?;
+        util::notify_rating_webhooks(db, WebhookEvent::RatingUpdated, &id)?;
+        Ok(())
+    })?;
+    Ok(Json(()))
+}
+
+#[delete("/ratings/<id>")]
+pub fn delete_rating(mut db: DbConn, user: Login, id: String) -> Result<()> {
+    db.transaction(|db| {
+        usecases::delete_rating(db, &user.0, &id)?;
+        util::notify_rating_webhooks(db, WebhookEvent::RatingArchived, &id)?;
+        Ok(())
+    })?;
+    Ok(Json(()))
+}
+
+#[post("/ratings/<id>/comments", format = "application/json", data = "<c>")]
+pub fn post_comment(
+    mut db: DbConn,
+    user: Option<Login>,
+    cfg: State<Cfg>,
+    id: String,
+    c: Json<usecases::NewComment>,
+) -> Result<String> {
+    if user.is_none() && cfg.ratings.require_login {
+        return Err(Error::Parameter(ParameterError::Unauthorized).into());
+    }
+    let mut c = c.into_inner();
+    c.user = user.map(|Login(username)| username);
+    let comment_id = usecases::comment_rating(&mut *db, &id, c)?;
+    Ok(Json(comment_id))
+}
+
+#[put("/comments/<id>", format = "application/json", data = "<u>")]
+pub fn put_comment(
+    mut db: DbConn,
+    user: Login,
+    id: String,
+    u: Json<usecases::UpdateComment>,
+) -> Result<()> {
+    usecases::update_comment(&mut *db, &user.0, &id, u.into_inner())?;
+    Ok(Json(()))
+}
+
+#[delete("/comments/<id>")]
+pub fn delete_comment(mut db: DbConn, user: Login, id: String) -> Result<()> {
+    usecases::delete_comment(&mut *db, &user.0, &id)?;
     Ok(Json(()))
 }
 
@@ -25,21 +132,14 @@ pub fn get_rating(db: DbConn, ids: String) -> Result<Vec<json::Rating>> {
         .map(|x| json::Rating {
             id: x.id.clone(),
             created: x.created,
+            updated: x.updated,
             title: x.title,
             value: x.value,
             context: x.context,
             source: x.source.unwrap_or_else(|| "".into()),
-            comments: comments
-                .get(&x.id)
-                .cloned()
-                .unwrap_or_else(|| vec![])
-                .into_iter()
-                .map(|c| json::Comment {
-                    id: c.id.clone(),
-                    created: c.created,
-                    text: c.text,
-                })
-                .collect(),
+            comments: json::Comment::threads(
+                comments.get(&x.id).cloned().unwrap_or_else(|| vec![]),
+            ),
         })
         .collect();
     Ok(Json(result))
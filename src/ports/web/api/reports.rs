@@ -0,0 +1,50 @@
+use super::*;
+
+#[post("/reports", format = "application/json", data = "<r>")]
+pub fn post_report(
+    mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    user: Option<Login>,
+    r: Json<usecases::NewReport>,
+) -> Result<String> {
+    let mut r = r.into_inner();
+    r.user = user.map(|Login(username)| username);
+    let report = db.transaction(|db| {
+        let report = usecases::create_report(db, r)?;
+        let scouts = usecases::scouts(db)?;
+        util::notify_report(db, &cfg, &templates, &scouts, &report)?;
+        Ok(report)
+    })?;
+    Ok(Json(report.id))
+}
+
+#[get("/reports")]
+pub fn get_reports(db: DbConn, user: Login) -> Result<Vec<json::Report>> {
+    let reports = usecases::open_reports(&*db, &user.0)?;
+    Ok(Json(reports.into_iter().map(json::Report::from).collect()))
+}
+
+#[post("/reports/<id>/resolve", format = "application/json", data = "<r>")]
+pub fn resolve_report(
+    mut db: DbConn,
+    user: Login,
+    id: String,
+    r: Json<usecases::ResolveReport>,
+) -> Result<()> {
+    db.transaction(|db| {
+        usecases::resolve_report(db, &user.0, &id, r.into_inner().action)?;
+        let report = db.get_report(&id)?;
+        match (report.target, report.resolution) {
+            (ReportTarget::Entry, Some(ReportResolution::Archived)) => {
+                util::notify_entry_webhooks(db, WebhookEvent::EntryArchived, &report.target_id)?;
+            }
+            (ReportTarget::Rating, Some(ReportResolution::Deleted)) => {
+                util::notify_rating_webhooks(db, WebhookEvent::RatingArchived, &report.target_id)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(Json(()))
+}
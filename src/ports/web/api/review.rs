@@ -0,0 +1,44 @@
+use super::*;
+
+#[get("/pending-entries")]
+pub fn get_pending_entries(db: DbConn, user: Login) -> Result<Vec<json::Entry>> {
+    let entries = usecases::pending_entries(&*db, &user.0)?
+        .into_iter()
+        .map(|e| {
+            let avg = db.get_avg_ratings(&e.id)?;
+            Ok(json::Entry::from_entry_with_ratings(e, vec![], avg))
+        })
+        .collect::<result::Result<Vec<json::Entry>, RepoError>>()?;
+    Ok(Json(entries))
+}
+
+#[post("/pending-entries/<id>/approve")]
+pub fn approve_entry(
+    mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    user: Login,
+    id: String,
+) -> Result<()> {
+    db.transaction(|db| {
+        let e = usecases::approve_entry(db, &user.0, &id)?;
+        let change = usecases::Change {
+            kind: ChangeKind::NewEntry,
+            id: &e.id,
+            title: &e.title,
+            categories: &e.categories,
+            tags: &e.tags,
+        };
+        let all_categories = db.all_categories()?;
+        util::notify_approve_entry(db, &cfg, &templates, &change, &e, all_categories)?;
+        util::notify_entry_webhooks(db, WebhookEvent::EntryCreated, &e.id)?;
+        Ok(())
+    })?;
+    Ok(Json(()))
+}
+
+#[post("/pending-entries/<id>/reject")]
+pub fn reject_entry(mut db: DbConn, user: Login, id: String) -> Result<()> {
+    usecases::reject_entry(&mut *db, &user.0, &id)?;
+    Ok(Json(()))
+}
@@ -2,10 +2,10 @@ use super::super::{sqlite::DbConn, util};
 use crate::{
     adapters::json,
     core::{prelude::*, usecases, util::geo},
-    infrastructure::error::AppError,
+    infrastructure::{cfg::Cfg, error::AppError},
 };
 
-use rocket::{self, request::Form};
+use rocket::{self, request::Form, State};
 use rocket_contrib::json::Json;
 use std::result;
 
@@ -15,12 +15,20 @@ pub struct SearchQuery {
     categories: Option<String>,
     text: Option<String>,
     tags: Option<String>,
+    min_rating: Option<f64>,
+    rating_context: Option<String>,
+    kinds: Option<String>,
+    events_until: Option<u64>,
 }
 
 type Result<T> = result::Result<Json<T>, AppError>;
 
 #[get("/search?<search..>")]
-pub fn get_search(db: DbConn, search: Form<SearchQuery>) -> Result<json::SearchResponse> {
+pub fn get_search(
+    db: DbConn,
+    cfg: State<Cfg>,
+    search: Form<SearchQuery>,
+) -> Result<json::SearchResponse> {
     let bbox = geo::extract_bbox(&search.bbox)
         .map_err(Error::Parameter)
         .map_err(AppError::Business)?;
@@ -47,9 +55,30 @@ pub fn get_search(db: DbConn, search: Form<SearchQuery>) -> Result<json::SearchR
         None => "".into(),
     };
 
-    let avg_ratings = match super::super::ENTRY_RATINGS.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => poisoned.into_inner(),
+    let kinds = match search.kinds {
+        Some(ref kinds_str) => util::extract_ids(kinds_str)
+            .iter()
+            .map(|k| k.parse())
+            .collect::<result::Result<_, _>>()
+            .map_err(Error::Parameter)
+            .map_err(AppError::Business)?,
+        None => vec![usecases::SearchKind::Entries],
+    };
+
+    let min_rating = match search.min_rating {
+        Some(value) => {
+            let context = match search.rating_context {
+                Some(ref ctx) => Some(
+                    ctx.parse()
+                        .map_err(|_| ParameterError::RatingContext)
+                        .map_err(Error::Parameter)
+                        .map_err(AppError::Business)?,
+                ),
+                None => None,
+            };
+            Some(usecases::MinRating { context, value })
+        }
+        None => None,
     };
 
     let req = usecases::SearchRequest {
@@ -57,10 +86,17 @@ pub fn get_search(db: DbConn, search: Form<SearchQuery>) -> Result<json::SearchR
         categories,
         text,
         tags,
-        entry_ratings: &*avg_ratings,
+        typo_tolerance: cfg.search.typo_tolerance,
+        min_rating,
+        events_until: search.events_until,
+        kinds,
     };
 
-    let (visible, invisible) = usecases::search(&*db, &req)?;
+    let usecases::SearchResult {
+        visible,
+        invisible,
+        events,
+    } = usecases::search(&*db, &req)?;
 
     let visible = visible
         .into_iter()
@@ -72,5 +108,15 @@ pub fn get_search(db: DbConn, search: Form<SearchQuery>) -> Result<json::SearchR
         .map(json::EntryIdWithCoordinates::from)
         .collect();
 
-    Ok(Json(json::SearchResponse { visible, invisible }))
+    let events = if req.kinds.contains(&usecases::SearchKind::Events) {
+        Some(events.into_iter().map(json::Event::from).collect())
+    } else {
+        None
+    };
+
+    Ok(Json(json::SearchResponse {
+        visible,
+        invisible,
+        events,
+    }))
 }
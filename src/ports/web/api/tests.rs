@@ -1,9 +1,11 @@
 use super::{util::*, *};
 use crate::{adapters::json, core::usecases as usecase, test::Bencher};
 use pwhash::bcrypt;
+use rocket::http::Header;
 
 pub mod prelude {
     use super::super::super::{rocket_instance, sqlite};
+    use crate::infrastructure::cfg::Cfg;
     use rocket::{
         config::{Config, Environment},
         logger::LoggingLevel,
@@ -19,6 +21,10 @@ pub mod prelude {
     };
 
     pub fn setup() -> (Client, sqlite::ConnectionPool) {
+        setup_with_cfg(Cfg::default())
+    }
+
+    pub fn setup_with_cfg(app_cfg: Cfg) -> (Client, sqlite::ConnectionPool) {
         let cfg = Config::build(Environment::Development)
             .log_level(LoggingLevel::Debug)
             .finalize()
@@ -26,7 +32,7 @@ pub mod prelude {
         let uuid = Uuid::new_v4().to_simple_ref().to_string();
         fs::create_dir_all("test-dbs").unwrap();
         let pool = sqlite::create_connection_pool(&format!("./test-dbs/{}", uuid)).unwrap();
-        let rocket = rocket_instance(cfg, pool.clone());
+        let rocket = rocket_instance(cfg, pool.clone(), app_cfg);
         let client = Client::new(rocket).unwrap();
         (client, pool)
     }
@@ -144,6 +150,106 @@ fn update_entry_with_tag_duplicates() {
     assert_eq!(e.tags, vec!["bar"]);
 }
 
+#[test]
+fn notify_the_contact_of_a_changed_entry() {
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.notifications.unsubscribe_secret = "secret".into();
+    app_cfg.notifications.notify_entry_contacts = true;
+    let (client, db) = setup_with_cfg(app_cfg);
+    db.get()
+        .unwrap()
+        .create_category_if_it_does_not_exist(&Category {
+            id: "x".into(),
+            created: 0,
+            version: 0,
+            name: "x".into(),
+        })
+        .unwrap();
+    let response = client.post("/entries")
+                    .header(ContentType::JSON)
+                    .body(r#"{"title":"foo","description":"blablabla","lat":0.0,"lng":0.0,"categories":["x"],"license":"CC0-1.0","tags":[],"email":"owner@example.org"}"#)
+                    .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let e = db.get().unwrap().all_entries().unwrap()[0].clone();
+    let update = |version: u64| {
+        let json = format!(
+            r#"{{"version":{},"id":"{}","title":"spam","description":"blablabla","lat":0.0,"lng":0.0,"categories":["x"],"tags":[],"email":"owner@example.org"}}"#,
+            version, e.id
+        );
+        client
+            .put(format!("/entries/{}", e.id))
+            .header(ContentType::JSON)
+            .body(json)
+            .dispatch()
+    };
+    assert_eq!(update(e.version + 1).status(), Status::Ok);
+    let mails = db.get().unwrap().all_outbox_mails().unwrap();
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].recipients, vec!["owner@example.org"]);
+    assert!(mails[0].content.contains("List-Unsubscribe:<"));
+
+    let token = usecases::revert_token("secret", &e.id, e.version + 1);
+    let url = format!("/entries/{}/revert/{}", e.id, token);
+    // Following the link only asks for a confirmation
+    let mut response = client.get(url.clone()).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.body_string().unwrap().contains("<form method=\"post\">"));
+    assert_eq!(db.get().unwrap().get_entry(&e.id).unwrap().title, "spam");
+    // Subscribers are notified about the revert
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "sub".into(),
+            username: "sub".into(),
+            password: "secret".into(),
+            email: "sub@example.org".into(),
+            email_confirmed: true,
+            role: Role::User,
+            language: None,
+        })
+        .unwrap();
+    db.get()
+        .unwrap()
+        .create_bbox_subscription(&BboxSubscription {
+            id: "s".into(),
+            name: None,
+            bbox: Bbox {
+                south_west: Coordinate {
+                    lat: -1.0,
+                    lng: -1.0,
+                },
+                north_east: Coordinate { lat: 1.0, lng: 1.0 },
+            },
+            username: "sub".into(),
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            delivery: Delivery::Instant,
+        })
+        .unwrap();
+    let response = client.post(url.clone()).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let reverted = db.get().unwrap().get_entry(&e.id).unwrap();
+    assert_eq!(reverted.title, "foo");
+    assert_eq!(reverted.version, e.version + 2);
+    assert_eq!(client.post(url).dispatch().status(), Status::BadRequest);
+    let mails = db.get().unwrap().all_outbox_mails().unwrap();
+    assert_eq!(mails.len(), 2);
+    assert!(mails.iter().any(|m| m.recipients == vec!["sub@example.org"]));
+
+    let token = usecases::contact_opt_out_token("secret", "owner@example.org");
+    let url = format!("/contact-opt-out/{}", token);
+    assert_eq!(client.get(url.clone()).dispatch().status(), Status::Ok);
+    assert_eq!(update(e.version + 3).status(), Status::Ok);
+    // only the owner and the subscriber
+    assert_eq!(db.get().unwrap().all_outbox_mails().unwrap().len(), 4);
+    let response = client.post(url).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(update(e.version + 4).status(), Status::Ok);
+    // only the subscriber
+    assert_eq!(db.get().unwrap().all_outbox_mails().unwrap().len(), 5);
+}
+
 #[test]
 fn get_one_entry() {
     let e = Entry::build()
@@ -175,10 +281,12 @@ fn get_one_entry() {
     assert_eq!(body_str.as_str().chars().nth(0).unwrap(), '[');
     let entries: Vec<json::Entry> = serde_json::from_str(&body_str).unwrap();
     let rating = db.get().unwrap().all_ratings().unwrap()[0].clone();
+    let avg = db.get().unwrap().get_avg_ratings(&e.id).unwrap();
+    assert_eq!(avg.humanity.count, 1);
     assert!(body_str.contains(&format!(r#""ratings":["{}"]"#, rating.id)));
     assert_eq!(
         entries[0],
-        json::Entry::from_entry_with_ratings(e, vec![rating])
+        json::Entry::from_entry_with_ratings(e, vec![rating], avg)
     );
 }
 
@@ -205,12 +313,10 @@ fn get_multiple_entries() {
     assert_eq!(body_str.as_str().chars().nth(0).unwrap(), '[');
     let entries: Vec<json::Entry> = serde_json::from_str(&body_str).unwrap();
     assert_eq!(entries.len(), 2);
-    assert!(entries
-        .iter()
-        .any(|x| *x == json::Entry::from_entry_with_ratings(one.clone(), vec![])));
-    assert!(entries
-        .iter()
-        .any(|x| *x == json::Entry::from_entry_with_ratings(two.clone(), vec![])));
+    let unrated =
+        |e: &Entry| json::Entry::from_entry_with_ratings(e.clone(), vec![], AvgRatings::default());
+    assert!(entries.iter().any(|x| *x == unrated(&one)));
+    assert!(entries.iter().any(|x| *x == unrated(&two)));
 }
 
 #[test]
@@ -513,6 +619,108 @@ fn search_without_specifying_hashtag_symbol() {
     assert!(body_str.contains(r#""visible":[{"id":"b","lat":0.0,"lng":0.0}]"#,));
 }
 
+#[test]
+fn search_with_events() {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let (client, db) = setup();
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("a").title("foo").finish())
+        .unwrap();
+    conn.create_event(Event {
+        id: "x".into(),
+        title: "foo".into(),
+        description: None,
+        start: now + 100,
+        end: None,
+        location: Some(Location {
+            lat: 1.0,
+            lng: 1.0,
+            address: None,
+        }),
+        contact: None,
+        homepage: None,
+        tags: vec![],
+        created_by: None,
+    })
+    .unwrap();
+
+    let mut response = client.get("/search?bbox=-10,-10,10,10&text=foo").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(r#""id":"a""#));
+    assert!(!body_str.contains("events"));
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&text=foo&kinds=events")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.starts_with(r#"{"visible":[],"invisible":[],"events":[{"id":"x","#));
+
+    let response = client
+        .get("/search?bbox=-10,-10,10,10&kinds=entries,foo")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn search_with_min_rating() {
+    let (client, db) = setup();
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("a").finish()).unwrap();
+    conn.create_entry(Entry::build().id("b").finish()).unwrap();
+    conn.create_rating(Rating {
+        id: "1".into(),
+        entry_id: "a".into(),
+        created: 0,
+        updated: None,
+        title: "good".into(),
+        value: 2,
+        context: RatingContext::Fairness,
+        source: None,
+        created_by: None,
+    })
+    .unwrap();
+    conn.create_rating(Rating {
+        id: "2".into(),
+        entry_id: "b".into(),
+        created: 0,
+        updated: None,
+        title: "bad".into(),
+        value: -1,
+        context: RatingContext::Fairness,
+        source: None,
+        created_by: None,
+    })
+    .unwrap();
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&min_rating=1&rating_context=fairness")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(r#""id":"a""#));
+    assert!(!body_str.contains(r#""id":"b""#));
+
+    let response = client
+        .get("/search?bbox=-10,-10,10,10&min_rating=1&rating_context=foo")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let mut response = client.get("/entries/a").dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let entries: Vec<json::Entry> = serde_json::from_str(&body_str).unwrap();
+    let summary = &entries[0].rating_summary;
+    assert_eq!(
+        summary.fairness,
+        json::AvgRating {
+            average: 2.0,
+            count: 1
+        }
+    );
+    assert_eq!(summary.diversity.count, 0);
+}
+
 #[test]
 fn extract_ids_test() {
     assert_eq!(extract_ids("abc"), vec!["abc"]);
@@ -567,6 +775,92 @@ fn create_new_user() {
     test_json(&response);
 }
 
+#[test]
+fn queue_mails_in_outbox() {
+    let (client, db) = setup();
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "1".into(),
+            username: "admin".into(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: "admin@bar.com".into(),
+            email_confirmed: true,
+            role: Role::Admin,
+            language: None,
+        })
+        .unwrap();
+    let response = client
+        .post("/users")
+        .header(ContentType::JSON)
+        .body(r#"{"username":"foo","email":"foo@bar.com","password":"bar"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let mails = db.get().unwrap().all_outbox_mails().unwrap();
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].recipients, vec!["foo@bar.com"]);
+    assert_eq!(mails[0].status, MailStatus::Pending);
+
+    let mut failed = mails[0].clone();
+    failed.status = MailStatus::Failed;
+    failed.attempts = 8;
+    db.get().unwrap().update_outbox_mail(&failed).unwrap();
+
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "admin", "password": "secret"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+    let mut response = client
+        .get("/outbox?status=failed")
+        .cookie(cookie.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let listed: Vec<serde_json::Value> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["status"], "failed");
+    assert!(listed[0].get("content").is_none());
+
+    let response = client
+        .post(format!("/outbox/{}/requeue", failed.id))
+        .cookie(cookie)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let m = db.get().unwrap().get_outbox_mail(&failed.id).unwrap();
+    assert_eq!(m.status, MailStatus::Pending);
+    assert_eq!(m.attempts, 0);
+}
+
+#[test]
+fn only_admins_can_access_the_outbox() {
+    let (client, db) = setup();
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "1".into(),
+            username: "scout".into(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: "scout@bar.com".into(),
+            email_confirmed: true,
+            role: Role::Scout,
+            language: None,
+        })
+        .unwrap();
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "scout", "password": "secret"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+    let response = client.get("/outbox").cookie(cookie).dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    client.post("/logout").header(ContentType::JSON).dispatch();
+    let response = client.get("/outbox").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
 #[test]
 fn create_rating() {
     let (client, db) = setup();
@@ -584,6 +878,189 @@ fn create_rating() {
     test_json(&response);
 }
 
+#[test]
+fn create_rating_as_logged_in_user() {
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.ratings.require_login = true;
+    let (client, db) = setup_with_cfg(app_cfg);
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("foo").finish())
+        .unwrap();
+    conn.create_user(User {
+        id: "123".into(),
+        username: "foo".into(),
+        password: bcrypt::hash("bar").unwrap(),
+        email: "foo@bar".into(),
+        email_confirmed: true,
+        role: Role::User,
+        language: None,
+    })
+    .unwrap();
+    let rate = |value: i8| {
+        client
+            .post("/ratings")
+            .header(ContentType::JSON)
+            .body(format!(
+                r#"{{"value":{},"context":"fairness","entry":"foo","comment":"test","title":"t","user":"bar"}}"#,
+                value
+            ))
+            .dispatch()
+    };
+
+    assert_eq!(rate(1).status(), Status::Unauthorized);
+    assert!(db.get().unwrap().all_ratings().unwrap().is_empty());
+
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "foo", "password": "bar"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    assert_eq!(rate(1).status(), Status::Ok);
+    assert_eq!(rate(2).status(), Status::Ok);
+    let ratings = db.get().unwrap().all_ratings().unwrap();
+    assert_eq!(ratings.len(), 1);
+    assert_eq!(ratings[0].value, 2);
+    assert_eq!(ratings[0].created_by, Some("foo".into()));
+}
+
+#[test]
+fn edit_and_delete_own_rating() {
+    let (client, db) = setup();
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("foo").finish())
+        .unwrap();
+    conn.create_user(User {
+        id: "123".into(),
+        username: "foo".into(),
+        password: bcrypt::hash("bar").unwrap(),
+        email: "foo@bar".into(),
+        email_confirmed: true,
+        role: Role::User,
+        language: None,
+    })
+    .unwrap();
+    conn.create_user(User {
+        id: "456".into(),
+        username: "subscriber".into(),
+        password: bcrypt::hash("bar").unwrap(),
+        email: "subscriber@bar.de".into(),
+        email_confirmed: true,
+        role: Role::User,
+        language: None,
+    })
+    .unwrap();
+    usecase::subscribe_to_bbox(
+        &[
+            Coordinate { lat: -1.0, lng: -1.0 },
+            Coordinate { lat: 1.0, lng: 1.0 },
+        ],
+        "subscriber",
+        &mut *conn,
+    )
+    .unwrap();
+    client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "foo", "password": "bar"}"#)
+        .dispatch();
+    client
+        .post("/ratings")
+        .header(ContentType::JSON)
+        .body(r#"{"value":1,"context":"fairness","entry":"foo","comment":"test","title":"t"}"#)
+        .dispatch();
+    let rating = conn.all_ratings().unwrap()[0].clone();
+    let comment = conn.all_comments().unwrap()[0].clone();
+    assert_eq!(comment.created_by, Some("foo".into()));
+    assert_eq!(conn.all_outbox_mails().unwrap().len(), 1);
+
+    let response = client
+        .put(format!("/ratings/{}", rating.id))
+        .header(ContentType::JSON)
+        .body(r#"{"value":-1,"title":"changed"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(conn.get_rating(&rating.id).unwrap().value, -1);
+    assert_eq!(conn.get_avg_ratings("foo").unwrap().fairness.value, -1.0);
+    // the subscribers are notified about the changed rating
+    let mails = conn.all_outbox_mails().unwrap();
+    assert_eq!(mails.len(), 2);
+    assert!(mails.iter().all(|m| m.recipients == vec!["subscriber@bar.de"]));
+
+    let response = client
+        .put(format!("/comments/{}", comment.id))
+        .header(ContentType::JSON)
+        .body(r#"{"text":"changed"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(conn.get_comment(&comment.id).unwrap().text, "changed");
+
+    let response = client.delete(format!("/ratings/{}", rating.id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(conn.all_ratings().unwrap().is_empty());
+    assert!(conn.all_comments().unwrap().is_empty());
+    assert_eq!(conn.get_avg_ratings("foo").unwrap().fairness.count, 0);
+}
+
+#[test]
+fn delete_rating_of_others() {
+    let (client, db) = setup();
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("foo").finish())
+        .unwrap();
+    usecase::rate_entry(
+        &mut *conn,
+        usecase::RateEntry {
+            context: RatingContext::Humanity,
+            value: 2,
+            user: None,
+            title: "title".into(),
+            entry: "foo".into(),
+            comment: "bla".into(),
+            source: None,
+        },
+    )
+    .unwrap();
+    let id = conn.all_ratings().unwrap()[0].id.clone();
+
+    let response = client.delete(format!("/ratings/{}", id)).dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    for (username, role) in &[("user", Role::User), ("scout", Role::Scout)] {
+        conn.create_user(User {
+            id: username.to_string(),
+            username: username.to_string(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: format!("{}@bar", username),
+            email_confirmed: true,
+            role: *role,
+            language: None,
+        })
+        .unwrap();
+    }
+    let login = |username: &str| {
+        client
+            .post("/login")
+            .header(ContentType::JSON)
+            .body(format!(
+                r#"{{"username": "{}", "password": "secret"}}"#,
+                username
+            ))
+            .dispatch()
+    };
+
+    login("user");
+    let response = client.delete(format!("/ratings/{}", id)).dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    assert_eq!(conn.all_ratings().unwrap().len(), 1);
+
+    login("scout");
+    let response = client.delete(format!("/ratings/{}", id)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(conn.all_ratings().unwrap().is_empty());
+}
+
 #[test]
 fn get_one_rating() {
     let e = Entry::build().id("foo").finish();
@@ -615,14 +1092,13 @@ fn get_one_rating() {
 }
 
 #[test]
-fn ratings_with_and_without_source() {
-    let e1 = Entry::build().id("foo").finish();
-    let e2 = Entry::build().id("bar").finish();
+fn reply_to_rating_comments() {
     let (client, db) = setup();
-    db.get().unwrap().create_entry(e1).unwrap();
-    db.get().unwrap().create_entry(e2).unwrap();
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("foo").finish())
+        .unwrap();
     usecase::rate_entry(
-        &mut *db.get().unwrap(),
+        &mut *conn,
         usecase::RateEntry {
             context: RatingContext::Humanity,
             value: 2,
@@ -630,39 +1106,374 @@ fn ratings_with_and_without_source() {
             title: "title".into(),
             entry: "foo".into(),
             comment: "bla".into(),
-            source: Some("blabla blabla".into()),
-        },
-    )
-    .unwrap();
-    usecase::rate_entry(
-        &mut *db.get().unwrap(),
-        usecase::RateEntry {
-            context: RatingContext::Humanity,
-            value: 2,
-            user: None,
-            title: "title".into(),
-            entry: "bar".into(),
-            comment: "bla".into(),
-            source: Some("blabla blabla".into()),
+            source: None,
         },
     )
     .unwrap();
+    let rid = conn.all_ratings().unwrap()[0].id.clone();
+    let cid = conn.all_comments().unwrap()[0].id.clone();
 
-    let rid = db.get().unwrap().all_ratings().unwrap()[0].id.clone();
-    let req = client.get(format!("/ratings/{}", rid));
-    let mut response = req.dispatch();
+    let mut response = client
+        .post(format!("/ratings/{}/comments", rid))
+        .header(ContentType::JSON)
+        .body(format!(r#"{{"text":"reply","parent":"{}"}}"#, cid))
+        .dispatch();
     assert_eq!(response.status(), Status::Ok);
-    test_json(&response);
     let body_str = response.body().and_then(|b| b.into_string()).unwrap();
-    assert_eq!(body_str.as_str().chars().nth(0).unwrap(), '[');
-    let ratings: Vec<json::Rating> = serde_json::from_str(&body_str).unwrap();
-    assert_eq!(ratings[0].id, rid);
-    assert_eq!(ratings[0].comments.len(), 1);
-}
+    let reply_id: String = serde_json::from_str(&body_str).unwrap();
 
-fn user_id_cookie(response: &Response) -> Option<Cookie<'static>> {
-    let cookie = response
-        .headers()
+    let response = client
+        .post(format!("/ratings/{}/comments", rid))
+        .header(ContentType::JSON)
+        .body(r#"{"text":"another comment"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post("/ratings/unknown/comments")
+        .header(ContentType::JSON)
+        .body(r#"{"text":"foo"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let mut response = client.get(format!("/ratings/{}", rid)).dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let ratings: Vec<json::Rating> = serde_json::from_str(&body_str).unwrap();
+    let comments = &ratings[0].comments;
+    assert_eq!(comments.len(), 2);
+    let first = comments.iter().find(|c| c.id == cid).unwrap();
+    assert_eq!(first.replies.len(), 1);
+    assert_eq!(first.replies[0].id, reply_id);
+    assert_eq!(first.replies[0].text, "reply");
+}
+
+#[test]
+fn report_and_archive_entry() {
+    let (client, db) = setup();
+    let mut conn = db.get().unwrap();
+    conn.create_entry(Entry::build().id("foo").finish())
+        .unwrap();
+    conn.create_user(User {
+        id: "123".into(),
+        username: "scout".into(),
+        password: bcrypt::hash("secret").unwrap(),
+        email: "scout@bar".into(),
+        email_confirmed: true,
+        role: Role::Scout,
+        language: None,
+    })
+    .unwrap();
+
+    let mut response = client
+        .post("/reports")
+        .header(ContentType::JSON)
+        .body(r#"{"target":"entry","target_id":"foo","reason":"spam"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let id: String = serde_json::from_str(&body_str).unwrap();
+
+    let response = client
+        .post("/reports")
+        .header(ContentType::JSON)
+        .body(r#"{"target":"rating","target_id":"foo","reason":"spam"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.get("/reports").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "scout", "password": "secret"}"#)
+        .dispatch();
+    let mut response = client.get("/reports").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let reports: Vec<json::Report> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].id, id);
+    assert_eq!(reports[0].reason, "spam");
+
+    let response = client
+        .post(format!("/reports/{}/resolve", id))
+        .header(ContentType::JSON)
+        .body(r#"{"action":"archive"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(conn.get_entry("foo").is_err());
+    assert!(conn.all_entries().unwrap().is_empty());
+    let mut response = client.get("/entries/foo").dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert_eq!(body_str, "[]");
+
+    let mut response = client.get("/reports").dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert_eq!(body_str, "[]");
+}
+
+#[test]
+fn review_new_entries() {
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.entries.require_review = true;
+    let (client, db) = setup_with_cfg(app_cfg);
+    let mut conn = db.get().unwrap();
+    conn.create_category_if_it_does_not_exist(&Category {
+        id: "x".into(),
+        created: 0,
+        version: 0,
+        name: "x".into(),
+    })
+    .unwrap();
+    conn.create_user(User {
+        id: "123".into(),
+        username: "scout".into(),
+        password: bcrypt::hash("secret").unwrap(),
+        email: "scout@bar".into(),
+        email_confirmed: true,
+        role: Role::Scout,
+        language: None,
+    })
+    .unwrap();
+
+    let mut ids = vec![];
+    for title in &["foo", "bar"] {
+        let mut response = client
+            .post("/entries")
+            .header(ContentType::JSON)
+            .body(format!(r#"{{"title":"{}","description":"blablabla","lat":0.0,"lng":0.0,"categories":["x"],"license":"CC0-1.0","tags":[]}}"#, title))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        ids.push(serde_json::from_str::<String>(&body_str).unwrap());
+    }
+    assert!(conn.all_entries().unwrap().is_empty());
+    let mut response = client.get(format!("/entries/{}", ids[0])).dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert_eq!(body_str, "[]");
+
+    let response = client.get("/pending-entries").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "scout", "password": "secret"}"#)
+        .dispatch();
+    let mut response = client.get("/pending-entries").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let pending: Vec<json::Entry> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(pending.len(), 2);
+
+    let response = client
+        .post(format!("/pending-entries/{}/approve", ids[0]))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post(format!("/pending-entries/{}/reject", ids[1]))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .post(format!("/pending-entries/{}/reject", ids[0]))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+
+    let entries = conn.all_entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].id, ids[0]);
+    let mut response = client.get("/pending-entries").dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert_eq!(body_str, "[]");
+}
+
+#[test]
+fn reject_and_review_spam() {
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.spam.blacklisted_words = vec!["casino".into()];
+    app_cfg.spam.max_submissions_per_hour = 3;
+    app_cfg.spam.reject_score = 2;
+    let (client, db) = setup_with_cfg(app_cfg);
+    let mut conn = db.get().unwrap();
+    conn.create_category_if_it_does_not_exist(&Category {
+        id: "x".into(),
+        created: 0,
+        version: 0,
+        name: "x".into(),
+    })
+    .unwrap();
+    let post = |title: &str, description: &str| {
+        client
+            .post("/entries")
+            .header(ContentType::JSON)
+            .remote("10.0.0.1:8000".parse().unwrap())
+            .body(format!(r#"{{"title":"{}","description":"{}","lat":0.0,"lng":0.0,"categories":["x"],"license":"CC0-1.0","tags":[]}}"#, title, description))
+            .dispatch()
+            .status()
+    };
+
+    assert_eq!(post("foo", "blablabla"), Status::Ok);
+    assert_eq!(conn.all_entries().unwrap().len(), 1);
+
+    // duplicate titles are reviewed by scouts
+    assert_eq!(post("Foo", "blablabla"), Status::Ok);
+    assert_eq!(conn.all_entries().unwrap().len(), 1);
+    assert_eq!(conn.pending_entries().unwrap().len(), 1);
+
+    // the blacklisted word and the duplicate title exceed the reject score
+    assert_eq!(post("foo", "online casino"), Status::BadRequest);
+
+    // too many submissions within an hour
+    assert_eq!(post("bar", "blablabla"), Status::Ok);
+    assert_eq!(conn.pending_entries().unwrap().len(), 2);
+    assert_eq!(post("baz", "blablabla"), Status::BadRequest);
+    assert_eq!(conn.all_entries().unwrap().len(), 1);
+}
+
+#[test]
+fn rate_limit_write_requests() {
+    use crate::infrastructure::cfg::RateLimit;
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.rate_limit.writes = RateLimit {
+        burst: 2,
+        per_minute: 1,
+    };
+    app_cfg.rate_limit.login = RateLimit {
+        burst: 1,
+        per_minute: 1,
+    };
+    let (client, db) = setup_with_cfg(app_cfg);
+    db.get()
+        .unwrap()
+        .create_category_if_it_does_not_exist(&Category {
+            id: "x".into(),
+            created: 0,
+            version: 0,
+            name: "x".into(),
+        })
+        .unwrap();
+    let post_entry = |ip: &str| {
+        client
+            .post("/entries")
+            .header(ContentType::JSON)
+            .remote(ip.parse().unwrap())
+            .body(r#"{"title":"foo","description":"blablabla","lat":0.0,"lng":0.0,"categories":["x"],"license":"CC0-1.0","tags":[]}"#)
+            .dispatch()
+    };
+    assert_eq!(post_entry("10.0.0.1:8000").status(), Status::Ok);
+    assert_eq!(post_entry("10.0.0.1:8000").status(), Status::Ok);
+    let response = post_entry("10.0.0.1:8000");
+    assert_eq!(response.status(), Status::TooManyRequests);
+    let retry_after: u64 = response
+        .headers()
+        .get_one("Retry-After")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+    assert_eq!(post_entry("10.0.0.2:8000").status(), Status::Ok);
+    assert_eq!(db.get().unwrap().all_entries().unwrap().len(), 1);
+    assert_eq!(db.get().unwrap().pending_entries().unwrap().len(), 2);
+
+    // all write requests share the limit and
+    // the header of an untrusted client is ignored
+    let response = client
+        .post("/reports")
+        .header(ContentType::JSON)
+        .header(Header::new("X-Real-IP", "10.0.0.5"))
+        .remote("10.0.0.1:8000".parse().unwrap())
+        .body(r#"{"entry_id":"foo","reason":"spam"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::TooManyRequests);
+
+    // login attempts are also limited per username
+    let login = |ip: &str| {
+        client
+            .post("/login")
+            .header(ContentType::JSON)
+            .remote(ip.parse().unwrap())
+            .body(r#"{"username": "foo", "password": "bar"}"#)
+            .dispatch()
+            .status()
+    };
+    assert_eq!(login("10.0.0.3:8000"), Status::Unauthorized);
+    assert_eq!(login("10.0.0.4:8000"), Status::TooManyRequests);
+    assert_eq!(client.get("/entries/foo").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn rate_limit_clients_behind_a_trusted_proxy() {
+    use crate::infrastructure::cfg::RateLimit;
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.rate_limit.writes = RateLimit {
+        burst: 1,
+        per_minute: 1,
+    };
+    app_cfg.rate_limit.trusted_proxies = vec!["10.0.0.9".parse().unwrap()];
+    let (client, _) = setup_with_cfg(app_cfg);
+    let delete_comment = |real_ip: &str| {
+        client
+            .delete("/comments/foo")
+            .header(Header::new("X-Real-IP", real_ip.to_string()))
+            .remote("10.0.0.9:8000".parse().unwrap())
+            .dispatch()
+            .status()
+    };
+    assert_eq!(delete_comment("10.0.0.1"), Status::Unauthorized);
+    assert_eq!(delete_comment("10.0.0.2"), Status::Unauthorized);
+    assert_eq!(delete_comment("10.0.0.1"), Status::TooManyRequests);
+}
+
+#[test]
+fn ratings_with_and_without_source() {
+    let e1 = Entry::build().id("foo").finish();
+    let e2 = Entry::build().id("bar").finish();
+    let (client, db) = setup();
+    db.get().unwrap().create_entry(e1).unwrap();
+    db.get().unwrap().create_entry(e2).unwrap();
+    usecase::rate_entry(
+        &mut *db.get().unwrap(),
+        usecase::RateEntry {
+            context: RatingContext::Humanity,
+            value: 2,
+            user: None,
+            title: "title".into(),
+            entry: "foo".into(),
+            comment: "bla".into(),
+            source: Some("blabla blabla".into()),
+        },
+    )
+    .unwrap();
+    usecase::rate_entry(
+        &mut *db.get().unwrap(),
+        usecase::RateEntry {
+            context: RatingContext::Humanity,
+            value: 2,
+            user: None,
+            title: "title".into(),
+            entry: "bar".into(),
+            comment: "bla".into(),
+            source: Some("blabla blabla".into()),
+        },
+    )
+    .unwrap();
+
+    let rid = db.get().unwrap().all_ratings().unwrap()[0].id.clone();
+    let req = client.get(format!("/ratings/{}", rid));
+    let mut response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    test_json(&response);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert_eq!(body_str.as_str().chars().nth(0).unwrap(), '[');
+    let ratings: Vec<json::Rating> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(ratings[0].id, rid);
+    assert_eq!(ratings[0].comments.len(), 1);
+}
+
+fn user_id_cookie(response: &Response) -> Option<Cookie<'static>> {
+    let cookie = response
+        .headers()
         .get("Set-Cookie")
         .filter(|v| v.starts_with("user_id"))
         .nth(0)
@@ -712,6 +1523,7 @@ fn login_with_valid_credentials() {
         email: "foo@bar".into(),
         email_confirmed: true,
         role: Role::Guest,
+        language: None,
     }];
     let mut conn = db.get().unwrap();
     for u in users {
@@ -737,6 +1549,7 @@ fn login_logout_succeeds() {
         email: "foo@bar".into(),
         email_confirmed: true,
         role: Role::Guest,
+        language: None,
     }];
     let mut conn = db.get().unwrap();
     for u in users {
@@ -773,6 +1586,7 @@ fn get_user() {
             email: "a@bar".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         },
         User {
             id: "123".into(),
@@ -781,6 +1595,7 @@ fn get_user() {
             email: "b@bar".into(),
             email_confirmed: true,
             role: Role::Guest,
+            language: None,
         },
     ];
     let mut conn = db.get().unwrap();
@@ -811,10 +1626,267 @@ fn get_user() {
 
     let body_str = response.body().and_then(|b| b.into_string()).unwrap();
     assert_eq!(response.status(), Status::Ok);
-    assert_eq!(body_str, r#"{"username":"a","email":"a@bar"}"#);
+    let last_login = conn.get_login_history("a").unwrap().last_login.unwrap();
+    assert_eq!(
+        body_str,
+        format!(
+            r#"{{"username":"a","email":"a@bar","language":null,"last_login":{},"failed_login_attempts":0,"locked_until":null}}"#,
+            last_login
+        )
+    );
     test_json(&response);
 }
 
+#[test]
+fn change_the_language_of_a_user() {
+    let (client, db) = setup();
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "123".into(),
+            username: "a".into(),
+            password: bcrypt::hash("a").unwrap(),
+            email: "a@bar".into(),
+            email_confirmed: true,
+            role: Role::Guest,
+            language: None,
+        })
+        .unwrap();
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "a", "password": "a"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+
+    let response = client
+        .patch("/users/a")
+        .header(ContentType::JSON)
+        .cookie(cookie.clone())
+        .body(r#"{"language": "english"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client
+        .patch("/users/a")
+        .header(ContentType::JSON)
+        .cookie(cookie.clone())
+        .body(r#"{"language": "en"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        db.get().unwrap().get_user("a").unwrap().language,
+        Some("en".into())
+    );
+
+    let mut response = client
+        .get("/users/a")
+        .header(ContentType::JSON)
+        .cookie(cookie)
+        .dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let user: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(user["language"], "en");
+}
+
+#[test]
+fn lock_out_after_failed_logins() {
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    // don't hit the rate limit
+    app_cfg.rate_limit.login.burst = 10;
+    let (client, db) = setup_with_cfg(app_cfg);
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "123".into(),
+            username: "foo".into(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: "foo@bar".into(),
+            email_confirmed: true,
+            role: Role::Guest,
+            language: None,
+        })
+        .unwrap();
+    let login = |password: &str| {
+        client
+            .post("/login")
+            .header(ContentType::JSON)
+            .body(format!(
+                r#"{{"username": "foo", "password": "{}"}}"#,
+                password
+            ))
+            .dispatch()
+            .status()
+    };
+    for _ in 0..usecase::MAX_FAILED_LOGIN_ATTEMPTS {
+        assert_eq!(login("wrong"), Status::Unauthorized);
+    }
+    assert_eq!(login("secret").code, 403);
+    let history = db.get().unwrap().get_login_history("foo").unwrap();
+    assert_eq!(history.failed_attempts, usecase::MAX_FAILED_LOGIN_ATTEMPTS);
+    assert!(history.last_login.is_none());
+}
+
+#[test]
+fn authenticate_with_user_tokens() {
+    let (client, db) = setup();
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "123".into(),
+            username: "foo".into(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: "foo@bar".into(),
+            email_confirmed: true,
+            role: Role::Guest,
+            language: None,
+        })
+        .unwrap();
+    let response = client
+        .post("/tokens")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "foo", "password": "wrong"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let mut response = client
+        .post("/tokens")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "foo", "password": "secret"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(user_id_cookie(&response).is_none());
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let t: json::UserToken = serde_json::from_str(&body_str).unwrap();
+    let token = t.token.unwrap();
+    assert!(t.expires > t.created);
+    // only the hash of the token is stored
+    assert_ne!(
+        db.get().unwrap().get_user_token(&t.id).unwrap().token_hash,
+        token
+    );
+    let auth = Header::new("Authorization", format!("Bearer {}", token));
+
+    let mut response = client.get("/tokens").header(auth.clone()).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let tokens: Vec<json::UserToken> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].id, t.id);
+    assert!(tokens[0].token.is_none());
+
+    let response = client
+        .get("/users/foo")
+        .header(ContentType::JSON)
+        .header(auth.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // user tokens are not accepted as API tokens of organizations
+    let response = client
+        .post("/events")
+        .header(ContentType::JSON)
+        .header(auth.clone())
+        .body(r#"{"title":"x","start":0}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .delete(format!("/tokens/{}", t.id))
+        .header(auth.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/tokens").header(auth).dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let response = client
+        .get("/tokens")
+        .header(Header::new("Authorization", "Bearer foo"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[test]
+fn export_and_delete_user_data() {
+    let (client, db) = setup();
+    let mut conn = db.get().unwrap();
+    for name in &["foo", "bar"] {
+        conn.create_user(User {
+            id: name.to_string(),
+            username: name.to_string(),
+            password: bcrypt::hash("secret").unwrap(),
+            email: format!("{}@bar", name),
+            email_confirmed: true,
+            role: Role::Guest,
+            language: None,
+        })
+        .unwrap();
+    }
+    conn.create_entry(Entry::build().id("a").finish()).unwrap();
+    conn.create_rating(Rating {
+        id: "1".into(),
+        entry_id: "a".into(),
+        created: 0,
+        updated: None,
+        title: "good".into(),
+        value: 2,
+        context: RatingContext::Fairness,
+        source: None,
+        created_by: Some("foo".into()),
+    })
+    .unwrap();
+    usecase::subscribe_to_bbox(
+        &[
+            Coordinate { lat: 0.0, lng: 0.0 },
+            Coordinate { lat: 1.0, lng: 1.0 },
+        ],
+        "foo",
+        &mut *conn,
+    )
+    .unwrap();
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "foo", "password": "secret"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+
+    let mut response = client
+        .get("/users/foo/export")
+        .header(ContentType::JSON)
+        .cookie(cookie.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let data: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(data["user"]["username"], "foo");
+    assert_eq!(data["user"]["email"], "foo@bar");
+    assert!(data["user"]["last_login"].is_number());
+    assert_eq!(data["bbox_subscriptions"].as_array().unwrap().len(), 1);
+    assert_eq!(data["ratings"][0]["id"], "1");
+    assert_eq!(data["ratings"][0]["entry_id"], "a");
+    assert!(!body_str.contains("password"));
+
+    let response = client
+        .get("/users/bar/export")
+        .header(ContentType::JSON)
+        .cookie(cookie.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    for to in &["foo@bar", "bar@bar"] {
+        usecase::enqueue_mail(&mut *conn, &[to.to_string()], "subject", "mail").unwrap();
+    }
+
+    let response = client.delete("/users/foo").cookie(cookie).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(conn.get_user("foo").is_err());
+    assert!(conn.all_bbox_subscriptions().unwrap().is_empty());
+    let mails = conn.all_outbox_mails().unwrap();
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].recipients, vec!["bar@bar"]);
+    let rating = conn.get_rating("1").unwrap();
+    assert!(rating.created_by.is_none());
+}
+
 #[test]
 fn confirm_email_address() {
     let (client, db) = setup();
@@ -825,6 +1897,7 @@ fn confirm_email_address() {
         email: "a@bar.de".into(),
         email_confirmed: false,
         role: Role::Guest,
+        language: None,
     }];
     let mut conn = db.get().unwrap();
     for u in users {
@@ -886,6 +1959,7 @@ fn send_confirmation_email() {
         email: "a@bar.de".into(),
         email_confirmed: false,
         role: Role::Guest,
+        language: None,
     }];
     let mut conn = db.get().unwrap();
     for u in users {
@@ -910,6 +1984,7 @@ fn subscribe_to_bbox() {
         email: "foo@bar".into(),
         email_confirmed: true,
         role: Role::Guest,
+        language: None,
     }];
     let mut conn = db.get().unwrap();
     for u in users {
@@ -930,6 +2005,250 @@ fn subscribe_to_bbox() {
     assert_eq!(response.status(), Status::Ok);
 }
 
+#[test]
+fn manage_named_bbox_subscriptions() {
+    let (client, db) = setup();
+    db.get()
+        .unwrap()
+        .create_user(User {
+            id: "123".into(),
+            username: "foo".into(),
+            password: bcrypt::hash("bar").unwrap(),
+            email: "foo@bar".into(),
+            email_confirmed: true,
+            role: Role::Guest,
+            language: None,
+        })
+        .unwrap();
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "foo", "password": "bar"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+    let mut response = client
+        .post("/bbox-subscriptions")
+        .header(ContentType::JSON)
+        .cookie(cookie.clone())
+        .body(r##"{"name":"Home","south_west_lat":-10.0,"south_west_lng":-10.0,"north_east_lat":10.0,"north_east_lng":10.0,"tags":["#bio"],"changes":["new_entry"],"delivery":"daily"}"##)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let id: String = serde_json::from_str(&body_str).unwrap();
+    let response = client
+        .post("/subscribe-to-bbox")
+        .header(ContentType::JSON)
+        .cookie(cookie.clone())
+        .body(r#"[{"lat":-10.0,"lng":-10.0},{"lat":10.0,"lng":10.0}]"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client
+        .get("/bbox-subscriptions")
+        .cookie(cookie.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let subs: Vec<serde_json::Value> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(subs.len(), 2);
+    let home = subs.iter().find(|s| s["id"] == id).unwrap();
+    assert_eq!(home["name"], "Home");
+    assert_eq!(home["tags"][0], "bio");
+    assert_eq!(home["changes"][0], "new_entry");
+    assert_eq!(home["delivery"], "daily");
+
+    let response = client
+        .delete(format!("/bbox-subscriptions/{}", id))
+        .cookie(cookie.clone())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .delete(format!("/bbox-subscriptions/{}", id))
+        .cookie(cookie)
+        .dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let subs = db.get().unwrap().all_bbox_subscriptions().unwrap();
+    assert_eq!(subs.len(), 1);
+    assert!(subs[0].name.is_none());
+}
+
+#[test]
+fn unsubscribe_with_token() {
+    let mut app_cfg = crate::infrastructure::cfg::Cfg::default();
+    app_cfg.notifications.unsubscribe_secret = "secret".into();
+    let (client, db) = setup_with_cfg(app_cfg);
+    for id in &["home", "work"] {
+        db.get()
+            .unwrap()
+            .create_bbox_subscription(&BboxSubscription {
+                id: id.to_string(),
+                name: Some(id.to_string()),
+                bbox: Bbox {
+                    south_west: Coordinate { lat: 0.0, lng: 0.0 },
+                    north_east: Coordinate { lat: 1.0, lng: 1.0 },
+                },
+                username: "foo".into(),
+                categories: vec![],
+                tags: vec![],
+                changes: vec![],
+                delivery: Delivery::Instant,
+            })
+            .unwrap();
+    }
+    let token = crate::core::util::unsubscribe::token("other", "home");
+    let response = client.post(format!("/unsubscribe/{}", token)).dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let token = crate::core::util::unsubscribe::token("secret", "home");
+    // Following the link only asks for a confirmation
+    let response = client.get(format!("/unsubscribe/{}", token)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(db.get().unwrap().all_bbox_subscriptions().unwrap().len(), 2);
+    let response = client.post(format!("/unsubscribe/{}", token)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(format!("/unsubscribe/{}", token)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let subs = db.get().unwrap().all_bbox_subscriptions().unwrap();
+    assert_eq!(subs.len(), 1);
+    assert_eq!(subs[0].id, "work");
+}
+
+#[test]
+fn disable_signed_links_without_secret() {
+    let (client, db) = setup();
+    db.get()
+        .unwrap()
+        .create_bbox_subscription(&BboxSubscription {
+            id: "home".into(),
+            name: None,
+            bbox: Bbox {
+                south_west: Coordinate { lat: 0.0, lng: 0.0 },
+                north_east: Coordinate { lat: 1.0, lng: 1.0 },
+            },
+            username: "foo".into(),
+            categories: vec![],
+            tags: vec![],
+            changes: vec![],
+            delivery: Delivery::Instant,
+        })
+        .unwrap();
+    let token = crate::core::util::unsubscribe::token("", "home");
+    let response = client.get(format!("/unsubscribe/{}", token)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.post(format!("/unsubscribe/{}", token)).dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.post("/contact-opt-out/foo").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.post("/entries/foo/revert/bar").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(db.get().unwrap().all_bbox_subscriptions().unwrap().len(), 1);
+}
+
+#[test]
+fn register_webhooks_and_queue_changes() {
+    let (client, db) = setup();
+    for id in &["a", "b"] {
+        db.get()
+            .unwrap()
+            .create_org(Organization {
+                id: id.to_string(),
+                name: id.to_string(),
+                owned_tags: vec![],
+                api_token: format!("{}-token", id),
+            })
+            .unwrap();
+    }
+    db.get()
+        .unwrap()
+        .create_category_if_it_does_not_exist(&Category {
+            id: "x".into(),
+            created: 0,
+            version: 0,
+            name: "x".into(),
+        })
+        .unwrap();
+    let auth = |org: &str| Header::new("Authorization", format!("Bearer {}-token", org));
+    let body = r#"{"url":"https://example.org/hook","events":["entry.created","rating.created"]}"#;
+    let response = client
+        .post("/webhooks")
+        .header(ContentType::JSON)
+        .header(auth("c"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let mut response = client
+        .post("/webhooks")
+        .header(ContentType::JSON)
+        .header(auth("a"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let w: json::Webhook = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(
+        w.events,
+        vec![WebhookEvent::EntryCreated, WebhookEvent::RatingCreated]
+    );
+    assert!(w.secret.is_some());
+
+    let mut response = client.get("/webhooks").header(auth("a")).dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let webhooks: Vec<json::Webhook> = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].id, w.id);
+    assert!(webhooks[0].secret.is_none());
+    let mut response = client.get("/webhooks").header(auth("b")).dispatch();
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert_eq!(body_str, "[]");
+
+    let response = client.post("/entries")
+        .header(ContentType::JSON)
+        .body(r#"{"title":"foo","description":"blablabla","lat":0.0,"lng":0.0,"categories":["x"],"license":"CC0-1.0","tags":[]}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let eid = db.get().unwrap().all_entries().unwrap()[0].id.clone();
+    let response = client
+        .post("/ratings")
+        .header(ContentType::JSON)
+        .body(format!(
+            r#"{{"value":1,"context":"fairness","entry":"{}","comment":"test","title":"t"}}"#,
+            eid
+        ))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let deliveries = db.get().unwrap().all_webhook_deliveries().unwrap();
+    assert_eq!(deliveries.len(), 2);
+    assert!(deliveries.iter().all(|d| d.webhook_id == w.id));
+    let d = deliveries
+        .iter()
+        .find(|d| d.event == WebhookEvent::EntryCreated)
+        .unwrap();
+    let payload: json::WebhookPayload<json::Entry> = serde_json::from_str(&d.payload).unwrap();
+    assert_eq!(payload.event, WebhookEvent::EntryCreated);
+    assert_eq!(payload.id, eid);
+    assert_eq!(payload.data.unwrap().title, "foo");
+    assert!(deliveries
+        .iter()
+        .any(|d| d.event == WebhookEvent::RatingCreated));
+
+    let response = client
+        .delete(format!("/webhooks/{}", w.id))
+        .header(auth("b"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+    let response = client
+        .delete(format!("/webhooks/{}", w.id))
+        .header(auth("a"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(db.get().unwrap().all_webhooks().unwrap().is_empty());
+    assert!(db
+        .get()
+        .unwrap()
+        .all_webhook_deliveries()
+        .unwrap()
+        .is_empty());
+}
+
 #[test]
 fn openapi() {
     let (client, _) = setup();
@@ -1015,25 +2334,27 @@ fn export_csv() {
         id: "123".into(),
         entry_id: "entry1".into(),
         created: 123,
+        updated: None,
         title: "rating1".into(),
         value: 2,
         context: diversity.clone(),
         source: None,
+        created_by: None,
     })
     .unwrap();
     conn.create_rating(Rating {
         id: "345".into(),
         entry_id: "entry1".into(),
         created: 123,
+        updated: None,
         title: "rating2".into(),
         value: 4,
         context: diversity,
         source: None,
+        created_by: None,
     })
     .unwrap();
 
-    super::super::calculate_all_ratings(&*conn).unwrap();
-
     let req = client.get("/export/entries.csv?bbox=-1,-1,1,1");
     let mut response = req.dispatch();
     assert_eq!(response.status(), Status::Ok);
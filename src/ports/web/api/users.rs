@@ -1,16 +1,25 @@
 use super::*;
 
 #[post("/users", format = "application/json", data = "<u>")]
-pub fn post_user(mut db: DbConn, u: Json<usecases::NewUser>) -> Result<()> {
+pub fn post_user(
+    mut db: DbConn,
+    cfg: State<Cfg>,
+    templates: State<Templates>,
+    u: Json<usecases::NewUser>,
+) -> Result<()> {
     let new_user = u.into_inner();
-    usecases::create_new_user(&mut *db, new_user.clone())?;
-    let user = db.get_user(&new_user.username)?;
-    let subject = "Karte von morgen: bitte bestätige deine Email-Adresse";
-    let body = user_communication::email_confirmation_email(&user.id);
+    db.transaction(|db| {
+        usecases::create_new_user(db, new_user.clone())?;
+        let user = db.get_user(&new_user.username)?;
+        let lang = user.language.as_ref().map(String::as_str);
+        let user_communication::Message { subject, body, .. } =
+            user_communication::email_confirmation_email(&templates, lang, &user.id);
 
-    #[cfg(feature = "email")]
-    util::send_mails(&[user.email], subject, &body);
+        #[cfg(feature = "email")]
+        util::send_mails(db, &cfg, &[user.email], &subject, &body)?;
 
+        Ok(())
+    })?;
     Ok(Json(()))
 }
 
@@ -22,6 +31,77 @@ pub fn delete_user(mut db: DbConn, user: Login, u_id: String) -> Result<()> {
 
 #[get("/users/<username>", format = "application/json")]
 pub fn get_user(mut db: DbConn, user: Login, username: String) -> Result<json::User> {
-    let (_, email) = usecases::get_user(&mut *db, &user.0, &username)?;
-    Ok(Json(json::User { username, email }))
+    let u = usecases::get_user(&mut *db, &user.0, &username)?;
+    let history = usecases::login_history(&*db, &username)?;
+    Ok(Json(json::User {
+        username,
+        email: u.email,
+        language: u.language,
+        last_login: history.last_login,
+        failed_login_attempts: history.failed_attempts,
+        locked_until: usecases::locked_until(&history),
+    }))
+}
+
+#[patch("/users/<username>", format = "application/json", data = "<s>")]
+pub fn patch_user(
+    mut db: DbConn,
+    user: Login,
+    username: String,
+    s: Json<usecases::UserSettings>,
+) -> Result<()> {
+    usecases::update_user_settings(&mut *db, &user.0, &username, s.into_inner())?;
+    Ok(Json(()))
+}
+
+#[get("/users/<username>/export", format = "application/json")]
+pub fn export_user_data(db: DbConn, user: Login, username: String) -> Result<json::UserExport> {
+    let data = usecases::export_user_data(&*db, &user.0, &username)?;
+    let usecases::UserData {
+        user,
+        login_history,
+        bbox_subscriptions,
+        tokens,
+        events,
+        ratings,
+        comments,
+    } = data;
+    Ok(Json(json::UserExport {
+        user: json::User {
+            username: user.username,
+            email: user.email,
+            language: user.language,
+            last_login: login_history.last_login,
+            failed_login_attempts: login_history.failed_attempts,
+            locked_until: usecases::locked_until(&login_history),
+        },
+        bbox_subscriptions: bbox_subscriptions.into_iter().map(Into::into).collect(),
+        tokens: tokens.into_iter().map(Into::into).collect(),
+        events: events.into_iter().map(Into::into).collect(),
+        ratings: ratings.into_iter().map(Into::into).collect(),
+        comments: comments.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[post("/tokens", format = "application/json", data = "<login>")]
+pub fn post_token(mut db: DbConn, login: Json<usecases::Login>) -> Result<json::UserToken> {
+    let (t, token) = usecases::create_user_token(&mut *db, &login.into_inner())?;
+    Ok(Json(json::UserToken {
+        token: Some(token),
+        ..json::UserToken::from(t)
+    }))
+}
+
+#[get("/tokens")]
+pub fn get_tokens(db: DbConn, user: Login) -> Result<Vec<json::UserToken>> {
+    let tokens = usecases::user_tokens(&*db, &user.0)?;
+    Ok(Json(
+        tokens.into_iter().map(json::UserToken::from).collect(),
+    ))
+}
+
+#[delete("/tokens/<id>")]
+pub fn delete_token(mut db: DbConn, user: Login, id: String) -> Result<()> {
+    usecases::revoke_user_token(&mut *db, &user.0, &id)?;
+    Ok(Json(()))
 }
@@ -0,0 +1,30 @@
+use super::{super::guards::Bearer, *};
+use crate::infrastructure::webhooks;
+
+#[post("/webhooks", format = "application/json", data = "<w>")]
+pub fn post_webhook(
+    mut db: DbConn,
+    token: Bearer,
+    w: Json<usecases::NewWebhook>,
+) -> Result<json::Webhook> {
+    let webhook = usecases::create_webhook(&mut *db, &token.0, w.into_inner(), &webhooks::resolve)?;
+    let secret = webhook.secret.clone();
+    Ok(Json(json::Webhook {
+        secret: Some(secret),
+        ..json::Webhook::from(webhook)
+    }))
+}
+
+#[get("/webhooks")]
+pub fn get_webhooks(db: DbConn, token: Bearer) -> Result<Vec<json::Webhook>> {
+    let webhooks = usecases::webhooks_of_org(&*db, &token.0)?;
+    Ok(Json(
+        webhooks.into_iter().map(json::Webhook::from).collect(),
+    ))
+}
+
+#[delete("/webhooks/<id>")]
+pub fn delete_webhook(mut db: DbConn, token: Bearer, id: String) -> Result<()> {
+    usecases::delete_webhook(&mut *db, &token.0, &id)?;
+    Ok(Json(()))
+}
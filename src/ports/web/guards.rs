@@ -1,9 +1,12 @@
+use super::sqlite::DbConn;
+use crate::{core::usecases, infrastructure::cfg::Cfg};
 use rocket::{
     self,
     http::Status,
     request::{self, FromRequest, Request},
-    Outcome,
+    Outcome, State,
 };
+use std::net::IpAddr;
 
 #[derive(Debug)]
 pub struct Bearer(pub String);
@@ -41,9 +44,43 @@ impl<'a, 'r> FromRequest<'a, 'r> for Login {
             .get_private(COOKIE_USER_KEY)
             .and_then(|cookie| cookie.value().parse().ok())
             .map(Login);
-        match user {
-            Some(user) => Outcome::Success(user),
-            None => Outcome::Failure((Status::Unauthorized, ())),
+        if let Some(user) = user {
+            return Outcome::Success(user);
         }
+        // The access tokens of users are looked up separately
+        // from the API tokens of organizations.
+        if let Outcome::Success(Bearer(token)) = request.guard::<Bearer>() {
+            let db = request.guard::<DbConn>()?;
+            if let Ok(username) = usecases::authenticate_user_token(&*db, &token) {
+                return Outcome::Success(Login(username));
+            }
+        }
+        Outcome::Failure((Status::Unauthorized, ()))
+    }
+}
+
+/// The IP address of the client. The `X-Real-IP` header is only
+/// trusted if the request has been forwarded by one of the given proxies.
+pub fn client_ip(request: &Request, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let remote = request.remote().map(|addr| addr.ip());
+    match remote {
+        Some(ip) if trusted_proxies.contains(&ip) => request.real_ip().or(remote),
+        _ => remote,
+    }
+}
+
+/// The IP address of the client (see `client_ip`).
+#[derive(Debug)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for ClientIp {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<ClientIp, ()> {
+        let cfg = request.guard::<State<Cfg>>()?;
+        Outcome::Success(ClientIp(client_ip(
+            request,
+            &cfg.rate_limit.trusted_proxies,
+        )))
     }
 }
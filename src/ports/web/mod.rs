@@ -1,20 +1,17 @@
-use crate::core::{prelude::*, util::sort::Rated};
-use crate::infrastructure::error::AppError;
+use crate::core::{prelude::*, util::spam::SubmissionLog};
+use crate::infrastructure::{cfg::Cfg, webhooks};
 use diesel::r2d2::{self, Pool};
 use rocket::{
     self,
     config::{Config, Environment},
     Rocket,
 };
-use rocket_contrib::json::Json;
-use std::{collections::HashMap, result, sync::Mutex};
+use std::sync::Mutex;
 
 #[cfg(feature = "email")]
 use crate::infrastructure::mail;
-
-lazy_static! {
-    static ref ENTRY_RATINGS: Mutex<HashMap<String, f64>> = Mutex::new(HashMap::new());
-}
+#[cfg(feature = "email")]
+use crate::infrastructure::outbox;
 
 mod api;
 #[cfg(test)]
@@ -23,47 +20,37 @@ pub mod sqlite;
 #[cfg(test)]
 pub use self::api::tests;
 mod guards;
+mod rate_limit;
 mod util;
 
-use self::sqlite::create_connection_pool;
-
-type Result<T> = result::Result<Json<T>, AppError>;
-
-fn calculate_all_ratings<D: Db>(db: &D) -> Result<()> {
-    let entries = db.all_entries()?;
-    let ratings = db.all_ratings()?;
-    let mut avg_ratings = match ENTRY_RATINGS.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => poisoned.into_inner(),
-    };
-    for e in entries {
-        avg_ratings.insert(e.id.clone(), e.avg_rating(&ratings));
-    }
-    Ok(Json(()))
-}
-
-fn calculate_rating_for_entry<D: Db>(db: &D, e_id: &str) -> Result<()> {
-    let ratings = db.all_ratings()?;
-    let e = db.get_entry(e_id)?;
-    let mut avg_ratings = match ENTRY_RATINGS.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => poisoned.into_inner(),
-    };
-    avg_ratings.insert(e.id.clone(), e.avg_rating(&ratings));
-    Ok(Json(()))
-}
+use self::{rate_limit::RateLimiter, sqlite::create_connection_pool};
 
-fn rocket_instance<T: r2d2::ManageConnection>(cfg: Config, pool: Pool<T>) -> Rocket
+fn rocket_instance<T: r2d2::ManageConnection>(cfg: Config, pool: Pool<T>, app_cfg: Cfg) -> Rocket
 where
     <T as r2d2::ManageConnection>::Connection: Db,
 {
-    info!("Calculating the average rating of all entries...");
-    calculate_all_ratings(&*pool.get().unwrap()).unwrap();
-    info!("done.");
-    rocket::custom(cfg).manage(pool).mount("/", api::routes())
+    let signed_links = app_cfg.notifications.signed_links();
+    let rocket = rocket::custom(cfg)
+        .manage(pool)
+        .manage(app_cfg.spam.filter())
+        .manage(
+            app_cfg
+                .mail
+                .templates()
+                .expect("Could not load the mail templates"),
+        )
+        .manage(Mutex::new(SubmissionLog::default()))
+        .attach(RateLimiter::new(&app_cfg.rate_limit))
+        .manage(app_cfg)
+        .mount("/", api::routes());
+    if signed_links {
+        rocket.mount("/", api::signed_link_routes())
+    } else {
+        rocket
+    }
 }
 
-pub fn run(db_url: &str, port: u16, enable_cors: bool) {
+pub fn run(db_url: &str, port: u16, enable_cors: bool, app_cfg: Cfg) {
     if enable_cors {
         panic!(
             "enable-cors is currently not available until\
@@ -79,5 +66,14 @@ pub fn run(db_url: &str, port: u16, enable_cors: bool) {
 
     let pool = create_connection_pool(db_url).unwrap();
 
-    rocket_instance(cfg, pool).launch();
+    #[cfg(feature = "email")]
+    outbox::spawn_worker(
+        pool.clone(),
+        app_cfg.outbox.clone(),
+        mail::transport(&app_cfg.mail),
+    );
+
+    webhooks::spawn_worker(pool.clone(), app_cfg.webhooks.clone());
+
+    rocket_instance(cfg, pool, app_cfg).launch();
 }
@@ -0,0 +1,225 @@
+use super::{
+    guards::{client_ip, Bearer},
+    sqlite::DbConn,
+};
+use crate::{
+    core::prelude::*,
+    infrastructure::cfg::{RateLimit, RateLimitCfg},
+};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{uri::Origin, ContentType, Method, Status},
+    Data, Outcome, Request, Response,
+};
+use serde_json;
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    net::IpAddr,
+    result,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Rate limited requests are redirected to this path
+/// that is not handled by any route.
+const RATE_LIMITED_PATH: &str = "/rate-limited";
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+fn secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) * 1e-9
+}
+
+struct Clients {
+    buckets: HashMap<String, TokenBucket>,
+    cleaned: Instant,
+}
+
+/// The token buckets of all clients that
+/// sent requests within the recent past.
+struct Buckets {
+    limit: RateLimit,
+    clients: Mutex<Clients>,
+}
+
+impl Buckets {
+    fn new(limit: RateLimit) -> Self {
+        Buckets {
+            limit,
+            clients: Mutex::new(Clients {
+                buckets: HashMap::new(),
+                cleaned: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket of the given client or returns
+    /// the number of seconds until the next token is available.
+    fn take(&self, key: &str, now: Instant) -> result::Result<(), u64> {
+        let capacity = f64::from(self.limit.burst);
+        let rate = f64::from(self.limit.per_minute) / 60.0;
+        let refill =
+            |b: &TokenBucket| (b.tokens + secs(now.duration_since(b.updated)) * rate).min(capacity);
+        let mut clients = self.clients.lock().unwrap();
+        // Full buckets are no longer needed, but they are only removed
+        // once an empty bucket could have been refilled completely.
+        if secs(now.duration_since(clients.cleaned)) * rate >= capacity {
+            clients.buckets.retain(|_, b| refill(b) < capacity);
+            clients.cleaned = now;
+        }
+        let bucket = clients
+            .buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: capacity,
+                updated: now,
+            });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / rate).ceil() as u64)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginUsername {
+    username: String,
+}
+
+struct RetryAfter(Option<u64>);
+
+/// Organizations with a valid API token (e.g. importing events) are not limited.
+fn is_org(request: &Request) -> bool {
+    match (request.guard::<Bearer>(), request.guard::<DbConn>()) {
+        (Outcome::Success(Bearer(token)), Outcome::Success(db)) => {
+            db.get_org_by_api_token(&token).is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// Limits the number of write requests per client IP address
+/// (and the number of login attempts per username) and
+/// answers exceeding requests with `429 Too Many Requests`.
+pub struct RateLimiter {
+    writes: Buckets,
+    login: Buckets,
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &RateLimitCfg) -> Self {
+        RateLimiter {
+            writes: Buckets::new(cfg.writes),
+            login: Buckets::new(cfg.login),
+            trusted_proxies: cfg.trusted_proxies.clone(),
+        }
+    }
+
+    fn take_user(&self, data: &Data, now: Instant) -> result::Result<(), u64> {
+        match serde_json::from_slice::<LoginUsername>(data.peek()) {
+            Ok(l) => self.login.take(&format!("user:{}", l.username), now),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Fairing for RateLimiter {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limiter",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    fn on_request(&self, request: &mut Request, data: &Data) {
+        let now = Instant::now();
+        let ip = client_ip(request, &self.trusted_proxies).map(|ip| ip.to_string());
+        let take_ip = |buckets: &Buckets| match ip {
+            Some(ref ip) => buckets.take(ip, now),
+            None => Ok(()),
+        };
+        let result = match (request.method(), request.uri().path()) {
+            (Method::Post, "/login") | (Method::Post, "/tokens") => {
+                take_ip(&self.login).and_then(|_| self.take_user(data, now))
+            }
+            (Method::Post, "/logout") => Ok(()),
+            (Method::Post, _) | (Method::Put, _) | (Method::Patch, _) | (Method::Delete, _) => {
+                if is_org(request) {
+                    Ok(())
+                } else {
+                    take_ip(&self.writes)
+                }
+            }
+            _ => Ok(()),
+        };
+        if let Err(retry_after) = result {
+            warn!(
+                "Too many requests to {} from {}",
+                request.uri(),
+                ip.unwrap_or_default()
+            );
+            request.local_cache(|| RetryAfter(Some(retry_after)));
+            request.set_method(Method::Get);
+            request.set_uri(Origin::parse(RATE_LIMITED_PATH).unwrap());
+        }
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        if let RetryAfter(Some(retry_after)) = request.local_cache(|| RetryAfter(None)) {
+            response.set_status(Status::TooManyRequests);
+            response.set_raw_header("Retry-After", retry_after.to_string());
+            response.set_header(ContentType::Plain);
+            response.set_sized_body(Cursor::new("Too many requests"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_tokens() {
+        let buckets = Buckets::new(RateLimit {
+            burst: 2,
+            per_minute: 6,
+        });
+        let now = Instant::now();
+        assert!(buckets.take("a", now).is_ok());
+        assert!(buckets.take("a", now).is_ok());
+        assert_eq!(buckets.take("a", now), Err(10));
+        assert!(buckets.take("b", now).is_ok());
+        let later = now + Duration::from_secs(4);
+        assert_eq!(buckets.take("a", later), Err(6));
+        let later = now + Duration::from_secs(11);
+        assert!(buckets.take("a", later).is_ok());
+        assert!(buckets.take("a", later).is_err());
+    }
+
+    #[test]
+    fn forget_full_buckets() {
+        let buckets = Buckets::new(RateLimit {
+            burst: 1,
+            per_minute: 60,
+        });
+        let now = Instant::now();
+        assert!(buckets.take("a", now).is_ok());
+        assert!(buckets.take("b", now + Duration::from_secs(2)).is_ok());
+        assert_eq!(buckets.clients.lock().unwrap().buckets.len(), 1);
+        // not before all buckets could have been refilled
+        assert!(buckets.take("c", now + Duration::from_millis(2500)).is_ok());
+        assert_eq!(buckets.clients.lock().unwrap().buckets.len(), 2);
+        assert!(buckets.take("d", now + Duration::from_secs(4)).is_ok());
+        assert_eq!(buckets.clients.lock().unwrap().buckets.len(), 1);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::infrastructure::error::AppError;
+use crate::{core::error::RepoError, infrastructure::error::AppError};
+use diesel::connection::{Connection, TransactionManager};
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 use diesel::sqlite::SqliteConnection;
 use rocket::http::Status;
@@ -23,6 +24,40 @@ pub fn create_connection_pool(db_url: &str) -> Result<ConnectionPool, AppError>
     Ok(pool)
 }
 
+/// Runs `f` within a transaction that is rolled back if `f` fails,
+/// e.g. to queue the mails about a change together with the change.
+pub fn transaction<T, F>(con: &mut SqliteConnection, f: F) -> Result<T, AppError>
+where
+    F: FnOnce(&mut SqliteConnection) -> Result<T, AppError>,
+{
+    con.transaction_manager()
+        .begin_transaction(con)
+        .map_err(RepoError::from)?;
+    match f(con) {
+        Ok(value) => {
+            con.transaction_manager()
+                .commit_transaction(con)
+                .map_err(RepoError::from)?;
+            Ok(value)
+        }
+        Err(err) => {
+            con.transaction_manager()
+                .rollback_transaction(con)
+                .map_err(RepoError::from)?;
+            Err(err)
+        }
+    }
+}
+
+impl DbConn {
+    pub fn transaction<T, F>(&mut self, f: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&mut SqliteConnection) -> Result<T, AppError>,
+    {
+        transaction(&mut *self.0, f)
+    }
+}
+
 impl<'a, 'r> FromRequest<'a, 'r> for DbConn {
     type Error = ();
 
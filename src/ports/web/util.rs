@@ -1,10 +1,16 @@
+use super::guards::ClientIp;
 #[cfg(feature = "email")]
 use super::mail;
+#[cfg(feature = "email")]
+use crate::infrastructure::notifications;
 use crate::{
-    adapters::user_communication,
-    core::{prelude::*, usecases},
+    adapters::{json, templates::Templates, user_communication},
+    core::{prelude::*, usecases, util::spam::SubmissionLog},
+    infrastructure::cfg::Cfg,
 };
+use chrono::Utc;
 use regex::Regex;
+use std::sync::Mutex;
 
 lazy_static! {
     static ref HASH_TAG_REGEX: Regex = Regex::new(r"#(?P<tag>\w+((-\w+)*)?)").unwrap();
@@ -19,70 +25,308 @@ pub fn extract_ids(s: &str) -> Vec<String> {
         .collect()
 }
 
-#[cfg(all(not(test), feature = "email"))]
-fn send_mail(mail: String) {
-    std::thread::spawn(move || {
-        if let Err(err) = mail::sendmail::send(&mail) {
-            warn!("Could not send e-mail: {}", err);
-        }
-    });
-}
-
-/// Don't actually send emails while running the tests
-#[cfg(all(test, feature = "email"))]
-fn send_mail(mail: String) {
-    debug!("Would send e-mail: {}", mail);
-}
-
+/// Puts the mails into the outbox that is
+/// delivered by a background worker.
 #[cfg(feature = "email")]
-pub fn send_mails(email_addresses: &[String], subject: &str, body: &str) {
+pub fn send_mails(
+    db: &mut Db,
+    cfg: &Cfg,
+    email_addresses: &[String],
+    subject: &str,
+    body: &str,
+) -> Result<()> {
     debug!("sending emails to: {:?}", email_addresses);
     for email_address in email_addresses.to_owned() {
         let to = vec![email_address];
-        match mail::create(&to, subject, body) {
+        match mail::create(&cfg.mail.from(), &to, subject, body, None) {
             Ok(mail) => {
-                send_mail(mail);
+                usecases::enqueue_mail(db, &to, subject, &mail)?;
             }
             Err(e) => {
                 warn!("could not create notification mail: {}", e);
             }
         }
     }
+    Ok(())
+}
+
+/// Sends the notification to the subscribers that want to be notified
+/// instantly and queues it for the digests of all other subscribers.
+fn notify_subscribers(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    x: &Coordinate,
+    change: &usecases::Change,
+    compose: &Fn(Option<&str>) -> user_communication::Message,
+) -> Result<()> {
+    let recipients = usecases::notify_subscribers(db, x, change)?;
+    debug!("notifying {} subscribers", recipients.len());
+
+    #[cfg(feature = "email")]
+    for r in recipients {
+        let msg = compose(r.language.as_ref().map(String::as_str));
+        match notifications::create_mail(cfg, templates, &r, &msg) {
+            Ok(mail) => {
+                usecases::enqueue_mail(db, &[r.email], &msg.subject, &mail)?;
+            }
+            Err(e) => {
+                warn!("could not create notification mail: {}", e);
+            }
+        }
+    }
+
+    Ok(())
 }
 
 pub fn notify_create_entry(
-    email_addresses: &[String],
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    change: &usecases::Change,
     e: &usecases::NewEntry,
     id: &str,
     all_categories: Vec<Category>,
-) {
-    let subject = String::from("Karte von morgen - neuer Eintrag: ") + &e.title;
+) -> Result<()> {
     let categories: Vec<String> = all_categories
         .into_iter()
         .filter(|c| e.categories.clone().into_iter().any(|c_id| *c.id == c_id))
         .map(|c| c.name)
         .collect();
-    let body = user_communication::new_entry_email(e, id, &categories);
-
-    #[cfg(feature = "email")]
-    send_mails(email_addresses, &subject, &body);
+    let compose = |lang: Option<&str>| {
+        user_communication::new_entry_email(templates, lang, e, id, &categories)
+    };
+    let x = Coordinate {
+        lat: e.lat,
+        lng: e.lng,
+    };
+    notify_subscribers(db, cfg, templates, &x, change, &compose)
 }
 
 pub fn notify_update_entry(
-    email_addresses: &[String],
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    change: &usecases::Change,
     e: &usecases::UpdateEntry,
     all_categories: Vec<Category>,
-) {
-    let subject = String::from("Karte von morgen - Eintrag verändert: ") + &e.title;
+) -> Result<()> {
     let categories: Vec<String> = all_categories
         .into_iter()
         .filter(|c| e.categories.clone().into_iter().any(|c_id| *c.id == c_id))
         .map(|c| c.name)
         .collect();
-    let body = user_communication::changed_entry_email(e, &categories);
+    let compose = |lang: Option<&str>| {
+        user_communication::changed_entry_email(templates, lang, e, &categories)
+    };
+    let x = Coordinate {
+        lat: e.lat,
+        lng: e.lng,
+    };
+    notify_subscribers(db, cfg, templates, &x, change, &compose)
+}
 
-    #[cfg(feature = "email")]
-    send_mails(email_addresses, &subject, &body);
+/// Tells the contacts of a changed entry what has been changed if
+/// enabled within the `[notifications]` section (requires signed links).
+pub fn notify_entry_contacts(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    old: &Entry,
+    new: &Entry,
+    all_categories: &[Category],
+) -> Result<()> {
+    if !cfg.notifications.notify_entry_contacts || !cfg.notifications.signed_links() {
+        return Ok(());
+    }
+    let changes = usecases::changed_fields(old, new, all_categories);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let revert_url = cfg.notifications.revert_entry_url(&new.id, new.version);
+    for email in usecases::entry_contacts_to_notify(db, old, new)? {
+        let opt_out_url = cfg.notifications.contact_opt_out_url(&email);
+        let msg = user_communication::changed_entry_contact_email(
+            templates,
+            None,
+            new,
+            &changes,
+            &revert_url,
+            &opt_out_url,
+        );
+
+        #[cfg(feature = "email")]
+        match notifications::create_entry_contact_mail(cfg, templates, &email, &msg, &opt_out_url) {
+            Ok(mail) => {
+                usecases::enqueue_mail(db, &[email], &msg.subject, &mail)?;
+            }
+            Err(e) => {
+                warn!("could not create notification mail: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn notify_approve_entry(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    change: &usecases::Change,
+    e: &Entry,
+    all_categories: Vec<Category>,
+) -> Result<()> {
+    let categories: Vec<String> = all_categories
+        .into_iter()
+        .filter(|c| e.categories.iter().any(|c_id| *c.id == *c_id))
+        .map(|c| c.name)
+        .collect();
+    let compose = |lang: Option<&str>| {
+        user_communication::approved_entry_email(templates, lang, e, &categories)
+    };
+    let x = Coordinate {
+        lat: e.location.lat,
+        lng: e.location.lng,
+    };
+    notify_subscribers(db, cfg, templates, &x, change, &compose)
+}
+
+/// Tells the subscribers that a change has been reverted.
+pub fn notify_revert_entry(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    change: &usecases::Change,
+    e: &Entry,
+    all_categories: Vec<Category>,
+) -> Result<()> {
+    let categories: Vec<String> = all_categories
+        .into_iter()
+        .filter(|c| e.categories.iter().any(|c_id| *c.id == *c_id))
+        .map(|c| c.name)
+        .collect();
+    let compose = |lang: Option<&str>| {
+        user_communication::reverted_entry_email(templates, lang, e, &categories)
+    };
+    let x = Coordinate {
+        lat: e.location.lat,
+        lng: e.location.lng,
+    };
+    notify_subscribers(db, cfg, templates, &x, change, &compose)
+}
+
+pub fn notify_create_event(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    change: &usecases::Change,
+    x: &Coordinate,
+    e: &Event,
+) -> Result<()> {
+    let compose = |lang: Option<&str>| user_communication::new_event_email(templates, lang, e);
+    notify_subscribers(db, cfg, templates, x, change, &compose)
+}
+
+pub fn notify_rating(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    change: &usecases::Change,
+    e: &Entry,
+    r: &usecases::RateEntry,
+    updated: bool,
+) -> Result<()> {
+    let compose =
+        |lang: Option<&str>| user_communication::rating_email(templates, lang, e, r, updated);
+    let x = Coordinate {
+        lat: e.location.lat,
+        lng: e.location.lng,
+    };
+    notify_subscribers(db, cfg, templates, &x, change, &compose)
+}
+
+/// Notifies the scouts in their language about a new report.
+pub fn notify_report(
+    db: &mut Db,
+    cfg: &Cfg,
+    templates: &Templates,
+    scouts: &[User],
+    r: &Report,
+) -> Result<()> {
+    for u in scouts {
+        let lang = u.language.as_ref().map(String::as_str);
+        let user_communication::Message { subject, body, .. } =
+            user_communication::report_email(templates, lang, r);
+
+        #[cfg(feature = "email")]
+        send_mails(db, cfg, &[u.email.clone()], &subject, &body)?;
+    }
+    Ok(())
+}
+
+/// Queues the change for the webhooks that subscribed to it.
+fn notify_webhooks<T: serde::Serialize>(
+    db: &mut Db,
+    event: WebhookEvent,
+    id: &str,
+    data: Option<T>,
+) -> Result<()> {
+    let payload = json::WebhookPayload {
+        event,
+        id: id.into(),
+        created: Utc::now().timestamp() as u64,
+        data,
+    };
+    match serde_json::to_string(&payload) {
+        Ok(payload) => {
+            let count = usecases::enqueue_webhook_deliveries(db, event, &payload)?;
+            debug!("notifying {} webhooks about {}", count, event.name());
+        }
+        Err(e) => {
+            warn!("could not serialize the payload of {}: {}", event.name(), e);
+        }
+    }
+    Ok(())
+}
+
+pub fn notify_entry_webhooks(db: &mut Db, event: WebhookEvent, id: &str) -> Result<()> {
+    let data = match event {
+        WebhookEvent::EntryArchived => None,
+        _ => {
+            let e = db.get_entry(id)?;
+            let ratings = db.ratings_of_entry(id)?;
+            let avg = db.get_avg_ratings(id)?;
+            Some(json::Entry::from_entry_with_ratings(e, ratings, avg))
+        }
+    };
+    notify_webhooks(db, event, id, data)
+}
+
+pub fn notify_event_webhooks(db: &mut Db, event: WebhookEvent, id: &str) -> Result<()> {
+    let data = match event {
+        WebhookEvent::EventArchived => None,
+        _ => Some(json::Event::from(db.get_event(id)?)),
+    };
+    notify_webhooks(db, event, id, data)
+}
+
+pub fn notify_rating_webhooks(db: &mut Db, event: WebhookEvent, id: &str) -> Result<()> {
+    let data = match event {
+        WebhookEvent::RatingArchived => None,
+        _ => Some(json::ExportedRating::from(db.get_rating(id)?)),
+    };
+    notify_webhooks(db, event, id, data)
+}
+
+/// Returns the number of submissions of the client within the last hour.
+pub fn record_submission(submissions: &Mutex<SubmissionLog>, ip: &ClientIp) -> usize {
+    match ip.0 {
+        Some(ip) => submissions
+            .lock()
+            .unwrap()
+            .record(ip, Utc::now().timestamp() as u64),
+        None => 0,
+    }
 }
 
 pub fn extract_hash_tags(text: &str) -> Vec<String> {